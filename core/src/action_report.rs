@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use derive_more::From;
 use ergo_lib::ergo_chain_types::EcPoint;
 
@@ -21,26 +23,29 @@ pub enum PoolActionReport {
 
 #[derive(Debug)]
 pub struct ActionReportStorage {
-    refresh: Option<RefreshActionReport>,
-    publish_datapoint: Option<PublishDatapointActionReport>,
+    /// Time of the last successful action (or of the start if there was none yet)
+    last_action_at: Instant,
+}
+
+impl Default for ActionReportStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ActionReportStorage {
     pub fn new() -> Self {
         Self {
-            refresh: None,
-            publish_datapoint: None,
+            last_action_at: Instant::now(),
         }
     }
 
     pub fn add(&mut self, report: PoolActionReport) {
-        match report {
-            PoolActionReport::Refresh(report) => self.refresh = Some(report),
-            PoolActionReport::PublishDatapoint(report) => self.publish_datapoint = Some(report),
-        }
+        log::debug!("Action report: {:?}", report);
+        self.last_action_at = Instant::now();
     }
 
-    pub fn get_last_refresh_report(&self) -> Option<&RefreshActionReport> {
-        self.refresh.as_ref()
+    pub fn secs_since_last_action(&self) -> u64 {
+        self.last_action_at.elapsed().as_secs()
     }
 }
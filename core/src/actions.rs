@@ -2,17 +2,15 @@
 /// by an oracle part of the oracle pool. These actions
 /// are implemented on the `OraclePool` struct.
 use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::TxId;
 
 use derive_more::From;
 use ergo_node_interface::node_interface::NodeError;
 use thiserror::Error;
 
+use crate::context::OracleContext;
 use crate::explorer_api::ergo_explorer_transaction_link;
-use crate::node_interface::node_api::NodeApi;
 use crate::node_interface::node_api::NodeApiError;
-use crate::oracle_config::ORACLE_CONFIG;
-
-mod action_result;
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, From)]
@@ -37,13 +35,17 @@ pub enum ActionExecError {
     NodeError(#[from] NodeApiError),
 }
 
-pub fn execute_action(action: PoolAction, node_api: &NodeApi) -> Result<(), anyhow::Error> {
+/// Returns the id of the submitted tx or `None` if the node rejected it as a duplicate
+pub fn execute_action(
+    action: PoolAction,
+    ctx: &OracleContext,
+) -> Result<Option<TxId>, anyhow::Error> {
     let exec_res = match action {
-        PoolAction::Refresh(action) => execute_refresh_action(action, node_api),
-        PoolAction::PublishDatapoint(action) => execute_publish_datapoint_action(action, node_api),
+        PoolAction::Refresh(action) => execute_refresh_action(action, ctx),
+        PoolAction::PublishDatapoint(action) => execute_publish_datapoint_action(action, ctx),
     };
     match exec_res {
-        Ok(_) => Ok(()),
+        Ok(tx_id) => Ok(Some(tx_id)),
         Err(ActionExecError::NodeError(NodeApiError::NodeInterfaceError(
             NodeError::BadRequest(msg),
         ))) if msg.as_str() == "Double spending attempt"
@@ -53,7 +55,7 @@ pub fn execute_action(action: PoolAction, node_api: &NodeApi) -> Result<(), anyh
             =>
         {
             log::debug!("Node rejected tx with error: {msg}");
-            Ok(())
+            Ok(None)
         }
         Err(e) => Err(e.into()),
     }
@@ -61,26 +63,24 @@ pub fn execute_action(action: PoolAction, node_api: &NodeApi) -> Result<(), anyh
 
 fn execute_refresh_action(
     action: RefreshAction,
-    node_api: &NodeApi,
-) -> Result<(), ActionExecError> {
-    let tx_id = node_api.sign_and_submit_transaction(&action.tx)?;
-    let network_prefix = &ORACLE_CONFIG.oracle_address.network();
+    ctx: &OracleContext,
+) -> Result<TxId, ActionExecError> {
+    let tx_id = ctx.node_api.sign_and_submit_transaction(&action.tx)?;
     log::info!(
         "Refresh tx published. Check status: {}",
-        ergo_explorer_transaction_link(tx_id, *network_prefix)
+        ergo_explorer_transaction_link(&ctx.oracle_config, tx_id)
     );
-    Ok(())
+    Ok(tx_id)
 }
 
 fn execute_publish_datapoint_action(
     action: PublishDataPointAction,
-    node_api: &NodeApi,
-) -> Result<(), ActionExecError> {
-    let tx_id = node_api.sign_and_submit_transaction(&action.tx)?;
-    let network_prefix = &ORACLE_CONFIG.oracle_address.network();
+    ctx: &OracleContext,
+) -> Result<TxId, ActionExecError> {
+    let tx_id = ctx.node_api.sign_and_submit_transaction(&action.tx)?;
     log::info!(
         "Datapoint tx published. Check status: {}",
-        ergo_explorer_transaction_link(tx_id, *network_prefix)
+        ergo_explorer_transaction_link(&ctx.oracle_config, tx_id)
     );
-    Ok(())
+    Ok(tx_id)
 }
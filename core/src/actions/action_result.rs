@@ -1,8 +0,0 @@
-use crate::action_report::RefreshActionReport;
-
-use super::RefreshAction;
-
-pub struct RefreshActionResult {
-    action: RefreshAction,
-    report: RefreshActionReport,
-}
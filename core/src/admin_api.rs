@@ -0,0 +1,482 @@
+//! Opt-in admin REST API with write operations, guarded by an API key. Every call is recorded in
+//! the audit log.
+use std::convert::TryFrom;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::ConnectInfo;
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderMap;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::middleware;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::routing::post;
+use axum::Json;
+use axum::Router;
+use ergo_lib::ergo_chain_types::Digest32;
+use ergo_lib::ergotree_ir::chain::token::TokenAmount;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use ergo_node_interface::scanning::NodeError;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use thiserror::Error;
+use tokio::task;
+
+use crate::cli_commands::extract_reward_tokens::extract_reward_tokens_unattended;
+use crate::cli_commands::vote_update_pool::vote_update_pool_unattended;
+use crate::context::SharedContext;
+use crate::contracts::ballot::BallotContract;
+use crate::oracle_state::DataSourceError;
+use crate::oracle_state::OraclePool;
+use crate::oracle_types::BlockHeight;
+use crate::pool_control::ForcedCommand;
+use crate::pool_control::PoolControl;
+use crate::spec_token::RewardTokenId;
+use crate::spec_token::SpecToken;
+use crate::spec_token::TokenIdKind;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Clone)]
+struct AdminApiState {
+    api_key: Arc<String>,
+    ctx: Arc<SharedContext>,
+    oracle_pool: Arc<OraclePool>,
+    pool_control: Arc<PoolControl>,
+    audit_log: Arc<AuditLog>,
+}
+
+/// Routes of the admin API (to be nested under `/admin`)
+pub fn admin_router(
+    api_key: String,
+    ctx: Arc<SharedContext>,
+    oracle_pool: Arc<OraclePool>,
+    pool_control: Arc<PoolControl>,
+    audit_log_path: PathBuf,
+) -> Router {
+    let state = AdminApiState {
+        api_key: Arc::new(api_key),
+        ctx,
+        oracle_pool,
+        pool_control,
+        audit_log: Arc::new(AuditLog::new(audit_log_path)),
+    };
+    Router::new()
+        .route("/status", get(status))
+        .route("/pausePublishing", post(pause_publishing))
+        .route("/resumePublishing", post(resume_publishing))
+        .route("/republish", post(force_republish))
+        .route("/refresh", post(force_refresh))
+        .route("/extractRewardTokens", post(extract_reward_tokens))
+        .route("/voteUpdatePool", post(vote_update_pool))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ))
+        .with_state(state)
+}
+
+async fn require_api_key<B>(
+    State(state): State<AdminApiState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, AdminApiError> {
+    if is_authorized(request.headers(), &state.api_key) {
+        return Ok(next.run(request).await);
+    }
+    let remote_addr = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.to_string());
+    let res = Err(AdminApiError::Unauthorized);
+    state.audit_log.record(
+        remote_addr,
+        request.uri().path(),
+        serde_json::Value::Null,
+        &res,
+    );
+    res
+}
+
+/// Checks the `X-Api-Key` or `Authorization: Bearer` header against the key, nothing matches an
+/// empty key
+pub fn is_authorized(headers: &HeaderMap, api_key: &str) -> bool {
+    if api_key.is_empty() {
+        return false;
+    }
+    let provided = headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        });
+    match provided {
+        Some(provided) => constant_time_eq(provided.as_bytes(), api_key.as_bytes()),
+        None => false,
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Status of the operator overrides
+async fn status(State(state): State<AdminApiState>) -> impl IntoResponse {
+    Json(json!({
+        "publishing_paused": state.pool_control.is_publishing_paused(),
+        "forced_command": state.pool_control.forced_command(),
+    }))
+}
+
+async fn pause_publishing(
+    State(state): State<AdminApiState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+) -> Result<Json<serde_json::Value>, AdminApiError> {
+    state.pool_control.pause_publishing();
+    audited(&state, remote_addr, "pausePublishing", json!(null), || {
+        Ok(json!({ "publishing_paused": true }))
+    })
+    .await
+}
+
+async fn resume_publishing(
+    State(state): State<AdminApiState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+) -> Result<Json<serde_json::Value>, AdminApiError> {
+    state.pool_control.resume_publishing();
+    audited(&state, remote_addr, "resumePublishing", json!(null), || {
+        Ok(json!({ "publishing_paused": false }))
+    })
+    .await
+}
+
+async fn force_republish(
+    State(state): State<AdminApiState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+) -> Result<Json<serde_json::Value>, AdminApiError> {
+    state.pool_control.force(ForcedCommand::Republish);
+    audited(&state, remote_addr, "republish", json!(null), || {
+        Ok(json!({ "forced_command": ForcedCommand::Republish }))
+    })
+    .await
+}
+
+async fn force_refresh(
+    State(state): State<AdminApiState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+) -> Result<Json<serde_json::Value>, AdminApiError> {
+    state.pool_control.force(ForcedCommand::Refresh);
+    audited(&state, remote_addr, "refresh", json!(null), || {
+        Ok(json!({ "forced_command": ForcedCommand::Refresh }))
+    })
+    .await
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ExtractRewardTokensRequest {
+    rewards_address: String,
+}
+
+async fn extract_reward_tokens(
+    State(state): State<AdminApiState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    request: Result<Json<ExtractRewardTokensRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, AdminApiError> {
+    let request = parse_request(&state, remote_addr, "extractRewardTokens", request)?;
+    let ctx = state.ctx.load();
+    let oracle_pool = state.oracle_pool.clone();
+    let params = json!(request);
+    audited(
+        &state,
+        remote_addr,
+        "extractRewardTokens",
+        params,
+        move || {
+            let height = BlockHeight(ctx.node_api.node.current_block_height()? as u32);
+            let (tx_id, reward_tokens) = extract_reward_tokens_unattended(
+                &ctx,
+                oracle_pool.get_local_datapoint_box_source(),
+                &request.rewards_address,
+                height,
+            )?;
+            Ok(json!({
+                "tx_id": tx_id.to_string(),
+                "reward_tokens": reward_tokens,
+            }))
+        },
+    )
+    .await
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct VoteUpdatePoolRequest {
+    new_pool_box_address_hash: String,
+    reward_token_id: Option<String>,
+    reward_token_amount: Option<u64>,
+    update_box_creation_height: u32,
+}
+
+async fn vote_update_pool(
+    State(state): State<AdminApiState>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    request: Result<Json<VoteUpdatePoolRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, AdminApiError> {
+    let request = parse_request(&state, remote_addr, "voteUpdatePool", request)?;
+    let ctx = state.ctx.load();
+    let oracle_pool = state.oracle_pool.clone();
+    let params = json!(request);
+    audited(&state, remote_addr, "voteUpdatePool", params, move || {
+        let new_pool_box_address_hash = Digest32::try_from(request.new_pool_box_address_hash)
+            .map_err(|e| AdminApiError::BadRequest(format!("new_pool_box_address_hash: {e}")))?;
+        let reward_token_opt =
+            parse_reward_token(request.reward_token_id, request.reward_token_amount)?;
+        let ballot_contract = BallotContract::checked_load(
+            &oracle_pool
+                .pool_config()
+                .ballot_box_wrapper_inputs
+                .contract_inputs,
+        )
+        .map_err(|e| AdminApiError::Internal(e.to_string()))?;
+        let height = BlockHeight(ctx.node_api.node.current_block_height()? as u32);
+        let tx_id = vote_update_pool_unattended(
+            &ctx,
+            oracle_pool.get_local_ballot_box_source(),
+            new_pool_box_address_hash,
+            reward_token_opt,
+            BlockHeight(request.update_box_creation_height),
+            height,
+            &ballot_contract,
+        )?;
+        Ok(json!({ "tx_id": tx_id.to_string() }))
+    })
+    .await
+}
+
+fn parse_reward_token(
+    reward_token_id: Option<String>,
+    reward_token_amount: Option<u64>,
+) -> Result<Option<SpecToken<RewardTokenId>>, AdminApiError> {
+    match (reward_token_id, reward_token_amount) {
+        (None, None) => Ok(None),
+        (Some(reward_token_id), Some(reward_token_amount)) => {
+            let token_id: TokenId = Digest32::try_from(reward_token_id)
+                .map_err(|e| AdminApiError::BadRequest(format!("reward_token_id: {e}")))?
+                .into();
+            let amount = TokenAmount::try_from(reward_token_amount)
+                .map_err(|e| AdminApiError::BadRequest(format!("reward_token_amount: {e}")))?;
+            Ok(Some(SpecToken {
+                token_id: RewardTokenId::from_token_id_unchecked(token_id),
+                amount,
+            }))
+        }
+        (Some(_), None) | (None, Some(_)) => Err(AdminApiError::BadRequest(
+            "reward_token_id and reward_token_amount must be set together".to_string(),
+        )),
+    }
+}
+
+/// Unwraps the request body, a malformed body is recorded in the audit log before it is rejected
+fn parse_request<T>(
+    state: &AdminApiState,
+    remote_addr: SocketAddr,
+    action: &str,
+    request: Result<Json<T>, JsonRejection>,
+) -> Result<T, AdminApiError> {
+    match request {
+        Ok(Json(request)) => Ok(request),
+        Err(rejection) => {
+            let reason = rejection.body_text();
+            state.audit_log.record(
+                Some(remote_addr.to_string()),
+                action,
+                serde_json::Value::Null,
+                &Err(AdminApiError::BadRequest(reason.clone())),
+            );
+            Err(AdminApiError::BadRequest(reason))
+        }
+    }
+}
+
+/// Runs the (blocking) admin action and records it in the audit log
+async fn audited<F>(
+    state: &AdminApiState,
+    remote_addr: SocketAddr,
+    action: &'static str,
+    params: serde_json::Value,
+    f: F,
+) -> Result<Json<serde_json::Value>, AdminApiError>
+where
+    F: FnOnce() -> Result<serde_json::Value, AdminApiError> + Send + 'static,
+{
+    let res = task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| Err(AdminApiError::Internal(e.to_string())));
+    state
+        .audit_log
+        .record(Some(remote_addr.to_string()), action, params, &res);
+    res.map(Json)
+}
+
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    timestamp: u64,
+    remote_addr: Option<String>,
+    action: &'a str,
+    params: serde_json::Value,
+    success: bool,
+    result: serde_json::Value,
+}
+
+/// Append-only JSON lines file with every admin call
+struct AuditLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn record(
+        &self,
+        remote_addr: Option<String>,
+        action: &str,
+        params: serde_json::Value,
+        res: &Result<serde_json::Value, AdminApiError>,
+    ) {
+        let entry = AuditEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            remote_addr,
+            action,
+            params,
+            success: res.is_ok(),
+            result: match res {
+                Ok(v) => v.clone(),
+                Err(e) => json!(e.to_string()),
+            },
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        log::info!("Admin API call: {}", line);
+        let _guard = self.lock.lock().unwrap();
+        if let Err(e) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+        {
+            log::error!(
+                "Failed to write to the audit log {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+enum AdminApiError {
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("bad request: {0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<anyhow::Error> for AdminApiError {
+    fn from(err: anyhow::Error) -> Self {
+        AdminApiError::Internal(format!("{:?}", err))
+    }
+}
+
+impl From<NodeError> for AdminApiError {
+    fn from(err: NodeError) -> Self {
+        AdminApiError::Internal(format!("NodeError: {}", err))
+    }
+}
+
+impl From<DataSourceError> for AdminApiError {
+    fn from(err: DataSourceError) -> Self {
+        AdminApiError::Internal(format!("DataSourceError: {}", err))
+    }
+}
+
+impl IntoResponse for AdminApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            AdminApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AdminApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AdminApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn api_key_auth() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, "secret"));
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("wrong"));
+        assert!(!is_authorized(&headers, "secret"));
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        assert!(is_authorized(&headers, "secret"));
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert!(is_authorized(&headers, "secret"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("secret"));
+        assert!(!is_authorized(&headers, "secret"));
+    }
+
+    #[test]
+    fn empty_api_key_is_rejected() {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static(""));
+        assert!(!is_authorized(&headers, "secret"));
+        assert!(!is_authorized(&headers, ""));
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer "));
+        assert!(!is_authorized(&headers, ""));
+    }
+
+    #[test]
+    fn reward_token_must_be_complete() {
+        assert!(parse_reward_token(None, None).unwrap().is_none());
+        assert!(matches!(
+            parse_reward_token(None, Some(1)),
+            Err(AdminApiError::BadRequest(_))
+        ));
+        assert!(matches!(
+            parse_reward_token(Some("not a digest".to_string()), Some(1)),
+            Err(AdminApiError::BadRequest(_))
+        ));
+    }
+}
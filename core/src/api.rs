@@ -1,26 +1,53 @@
 use std::convert::From;
-use std::net::SocketAddr;
+use std::convert::Infallible;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
-use crate::box_kind::PoolBox;
-use crate::monitor::{
-    check_oracle_health, check_pool_health, HealthStatus, OracleHealth, PoolHealth,
-};
-use crate::node_interface::node_api::{NodeApi, NodeApiError};
-use crate::oracle_config::{ORACLE_CONFIG, ORACLE_SECRETS};
+use crate::admin_api::admin_router;
+use crate::admin_api::is_authorized;
+use crate::context::SharedContext;
+use crate::datapoint_source::connector::PushDataPointSource;
+use crate::events::EventBus;
+use crate::http_server::serve;
+use crate::http_server::HttpServerConfig;
+use crate::monitor::HealthStatus;
+use crate::node_interface::node_api::NodeApiError;
 use crate::oracle_state::{DataSourceError, LocalDatapointState, OraclePool};
-use crate::pool_config::POOL_CONFIG;
+use crate::oracle_stats::{
+    get_oracle_stats, get_posted_datapoint_log_file_path, OracleStats, PostedDatapointLog,
+    DEFAULT_STATS_EPOCHS,
+};
+use crate::oracle_types::BlockHeight;
+use crate::pool_control::PoolControl;
+use crate::pool_history::{
+    chain_index, get_pool_history, PoolHistoryCache, PoolHistoryEntry, PoolHistoryError,
+    SharedPoolHistoryCache, MAX_HISTORY_EPOCHS,
+};
+use crate::snapshot::{PoolSnapshot, SnapshotCache};
+use crate::state::PoolState;
+use axum::extract::Query;
+use axum::http::HeaderMap;
 use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
+use axum::routing::post;
 use axum::{Json, Router};
-use crossbeam::channel::Receiver;
-use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder};
+use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder, NetworkPrefix};
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
 use ergo_node_interface::scanning::NodeError;
+use futures::Stream;
+use serde::Deserialize;
+use serde::Serialize;
 use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::task;
 use tower_http::cors::CorsLayer;
 
+const MAX_REPOST_WAIT_SECS: u64 = 60;
+
 /// Basic welcome endpoint
 async fn root() -> &'static str {
     "This is an Oracle Core. Please use one of the endpoints to interact with it:
@@ -28,14 +55,21 @@ async fn root() -> &'static str {
         /poolStatus - status of the oracle pool
         /oracleInfo - basic information about the oracle
         /oracleStatus - status of the oracle
-        /oracleHealth - returns OK if our collected datapoint box height is the same as the pool box height OR our posted datapoint box height is greater than the pool box height
-        /poolHealth - returns OK if the pool box height is greater or equal to (current height - epoch length)
+        /oracleHealth - status (Ok/Degraded/Down) of the oracle checks: our datapoint box age, node sync, wallet, wallet balance and time since our last successful action
+        /requireDatapointRepost - returns true if the Connector needs to push a new datapoint (long-poll with ?wait_secs=N)
+        /datapoint - (POST) datapoint pushed by the Connector
+        /poolHistory - past pool boxes: rate, oracles and collector of each epoch (?epochs=N, default 10)
+        /oracles/stats - per-oracle epochs participated, epochs filtered out as outliers, average deviation from the pool rate, collections and reward tokens earned (?epochs=N, default 100)
+        /refreshPreview - outcome of the next refresh from the posted datapoints: included and excluded oracles with their deviations and the resulting pool rate
+        /poolHealth - status (Ok/Degraded/Down) of the pool checks: pool box age, active oracles count vs min data points and reward tokens left in the pool box
+        /events - Server-Sent Events stream of the pool events observed by the oracle (new pool box, our datapoint, refresh attempts, health changes, ballot and update box changes)
         "
 }
 
 /// Basic oracle information
-async fn oracle_info() -> impl IntoResponse {
-    let conf = &ORACLE_CONFIG;
+async fn oracle_info(ctx: Arc<SharedContext>) -> impl IntoResponse {
+    let ctx = ctx.load();
+    let conf = &ctx.oracle_config;
     Json(json! ( {
         "oracle_address": conf.oracle_address.to_base58(),
         "base_fee": conf.base_fee,
@@ -43,16 +77,15 @@ async fn oracle_info() -> impl IntoResponse {
 }
 
 /// Status of the oracle
-async fn oracle_status(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
-    let json = task::spawn_blocking(|| oracle_status_sync(oracle_pool))
-        .await
-        .unwrap()?;
-    Ok(json)
-}
-
-fn oracle_status_sync(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
-    let live_epoch = oracle_pool.get_live_epoch_state()?;
-    if let Some(local_datapoint_box_state) = live_epoch.local_datapoint_box_state {
+async fn oracle_status(snapshot_cache: Arc<SnapshotCache>) -> Response {
+    let Some(snapshot) = snapshot_cache.load() else {
+        return snapshot_unavailable();
+    };
+    let local_datapoint_box_state = match &snapshot.pool_state {
+        PoolState::LiveEpoch(live_epoch) => live_epoch.local_datapoint_box_state.clone(),
+        PoolState::NeedsBootstrap => None,
+    };
+    let json = if let Some(local_datapoint_box_state) = local_datapoint_box_state {
         let json = match local_datapoint_box_state {
             LocalDatapointState::Collected { height } => json!( {
                 "status": "collected",
@@ -64,23 +97,25 @@ fn oracle_status_sync(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::V
                 "height": height,
             }),
         };
-        let oracle_health = oracle_health_sync(oracle_pool)?;
-        Ok(Json(json!({
+        json!({
                 "local_datapoint_box_state": json,
-                "oracle_health": oracle_health,
-        })))
+                "oracle_health": snapshot.oracle_health,
+                "fee_runway": snapshot.fee_runway,
+                "snapshot": snapshot.info(),
+        })
     } else {
-        Ok(Json(json!({
+        json!({
                 "local_datapoint_box_state": "No local datapoint box",
-        })))
-    }
+                "snapshot": snapshot.info(),
+        })
+    };
+    Json(json).into_response()
 }
 
 // Basic information about the oracle pool
-async fn pool_info() -> impl IntoResponse {
-    let conf = &POOL_CONFIG;
-    let network = &ORACLE_CONFIG.oracle_address.network();
-    let address_encoder = AddressEncoder::new(*network);
+async fn pool_info(oracle_pool: Arc<OraclePool>, network: NetworkPrefix) -> impl IntoResponse {
+    let conf = oracle_pool.pool_config();
+    let address_encoder = AddressEncoder::new(network);
     let pool_box_address = Address::P2S(
         conf.pool_box_wrapper_inputs
             .contract_inputs
@@ -120,190 +155,591 @@ async fn pool_info() -> impl IntoResponse {
 }
 
 /// Status of the oracle pool
-async fn pool_status(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
-    let json = task::spawn_blocking(|| pool_status_sync(oracle_pool))
-        .await
-        .unwrap()?;
-    Ok(json)
-}
-
-fn pool_status_sync(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
-    let node_api = NodeApi::new(
-        ORACLE_SECRETS.node_api_key.clone(),
-        ORACLE_SECRETS.wallet_password.clone(),
-        &ORACLE_CONFIG.node_url,
-    );
-    let current_height = node_api.node.current_block_height()? as u32;
-    let pool_box = oracle_pool.get_pool_box_source().get_pool_box()?;
-    let epoch_length = POOL_CONFIG
-        .refresh_box_wrapper_inputs
-        .contract_inputs
-        .contract_parameters()
-        .epoch_length();
-    let pool_box_height = pool_box.get_box().creation_height;
-    let epoch_end_height = pool_box_height + epoch_length.0 as u32;
-    let pool_health = pool_health_sync(oracle_pool)?;
-    let active_oracle_count = pool_health.details.active_oracle_boxes.len();
-    let json = Json(json!({
-        "latest_pool_datapoint": pool_box.rate(),
-        "latest_pool_box_height": pool_box_height,
-        "pool_box_epoch_id" : pool_box.epoch_counter(),
-        "current_block_height": current_height,
+async fn pool_status(snapshot_cache: Arc<SnapshotCache>) -> Response {
+    let Some(snapshot) = snapshot_cache.load() else {
+        return snapshot_unavailable();
+    };
+    let epoch_length = snapshot.pool_health.details.epoch_length;
+    let epoch_end_height = snapshot.pool_box_height.0 + epoch_length.0 as u32;
+    let active_oracle_count = snapshot.pool_health.details.active_oracle_boxes.len();
+    Json(json!({
+        "latest_pool_datapoint": snapshot.pool_box_rate,
+        "latest_pool_box_height": snapshot.pool_box_height,
+        "pool_box_epoch_id" : snapshot.pool_box_epoch_counter,
+        "current_block_height": snapshot.height,
         "epoch_end_height": epoch_end_height,
-        "reward_tokens_in_pool_box": pool_box.reward_token().amount.as_u64(),
+        "reward_tokens_in_pool_box": snapshot.pool_box_reward_tokens,
         "number_of_oracles": active_oracle_count,
-        "pool_health": pool_health,
-    }));
-    Ok(json)
+        "pool_health": snapshot.pool_health,
+        "snapshot": snapshot.info(),
+    }))
+    .into_response()
 }
 
-/// Block height of the Ergo blockchain
-async fn block_height() -> Result<impl IntoResponse, ApiError> {
-    let current_height = task::spawn_blocking(move || {
-        let node_api = NodeApi::new(
-            ORACLE_SECRETS.node_api_key.clone(),
-            ORACLE_SECRETS.wallet_password.clone(),
-            &ORACLE_CONFIG.node_url,
-        );
-        node_api.node.current_block_height()
-    })
-    .await
-    .unwrap()?;
-    Ok(format!("{}", current_height))
+#[derive(Debug, Deserialize)]
+struct PoolHistoryQuery {
+    epochs: Option<usize>,
 }
 
-/// Whether the Core requires the Connector to repost a new Datapoint
-async fn require_datapoint_repost(repost_receiver: Receiver<bool>) -> impl IntoResponse {
-    let mut response_text = "false".to_string();
-    if let Ok(b) = repost_receiver.try_recv() {
-        response_text = b.to_string();
+/// Last response of an endpoint that walks the chain back from the snapshot pool box, served
+/// again until the next snapshot instead of reading the node on every request
+struct SnapshotResponseCache<T> {
+    last: Mutex<Option<(SnapshotResponseKey, T)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SnapshotResponseKey {
+    height: BlockHeight,
+    pool_box_id: BoxId,
+    epochs: usize,
+}
+
+impl SnapshotResponseKey {
+    fn new(snapshot: &PoolSnapshot, epochs: usize) -> Self {
+        Self {
+            height: snapshot.height,
+            pool_box_id: snapshot.pool_box_id,
+            epochs,
+        }
     }
-    response_text
 }
 
-/// Return true if the our collected datapoint box height is the same as the pool box height
-/// and our posted datapoint box height is greater than the pool box height
-async fn oracle_health(oracle_pool: Arc<OraclePool>) -> impl IntoResponse {
-    let pool_health = match task::spawn_blocking(|| oracle_health_sync(oracle_pool))
-        .await
-        .unwrap()
-    {
-        Ok(v) => v,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(e.0))),
+impl<T: Clone> SnapshotResponseCache<T> {
+    fn new() -> Self {
+        Self {
+            last: Mutex::new(None),
+        }
+    }
+
+    fn get(&self, key: SnapshotResponseKey) -> Option<T> {
+        match &*self.last.lock().unwrap() {
+            Some((last_key, response)) if *last_key == key => Some(response.clone()),
+            _ => None,
+        }
+    }
+
+    fn store(&self, key: SnapshotResponseKey, response: T) {
+        *self.last.lock().unwrap() = Some((key, response));
+    }
+}
+
+/// Past pool boxes, newest first
+async fn pool_history(
+    ctx: Arc<SharedContext>,
+    oracle_pool: Arc<OraclePool>,
+    snapshot_cache: Arc<SnapshotCache>,
+    history_cache: Arc<Mutex<PoolHistoryCache>>,
+    response_cache: Arc<SnapshotResponseCache<Vec<PoolHistoryEntry>>>,
+    Query(query): Query<PoolHistoryQuery>,
+) -> Response {
+    let ctx = ctx.load();
+    let Some(snapshot) = snapshot_cache.load() else {
+        return snapshot_unavailable();
     };
-    // return 503 http error if pool_health.status is not ok
-    if pool_health.status != HealthStatus::Ok {
-        return (
+    let epochs = query.epochs.unwrap_or(10).min(MAX_HISTORY_EPOCHS);
+    let key = SnapshotResponseKey::new(&snapshot, epochs);
+    let history = match response_cache.get(key) {
+        Some(history) => history,
+        None => {
+            let pool_box_id = snapshot.pool_box_id;
+            let history = task::spawn_blocking(move || {
+                get_pool_history(
+                    chain_index(&ctx)?.as_ref(),
+                    &mut history_cache.lock().unwrap(),
+                    &oracle_pool.pool_config().token_ids,
+                    ctx.network_prefix,
+                    pool_box_id,
+                    epochs,
+                )
+            })
+            .await
+            .unwrap();
+            match history {
+                Ok(history) => {
+                    response_cache.store(key, history.clone());
+                    history
+                }
+                Err(e) => return ApiError::from(e).into_response(),
+            }
+        }
+    };
+    Json(json!({
+        "epochs": history,
+        "snapshot": snapshot.info(),
+    }))
+    .into_response()
+}
+
+/// Per-oracle participation and reliability over the last epochs
+async fn oracle_stats(
+    ctx: Arc<SharedContext>,
+    oracle_pool: Arc<OraclePool>,
+    snapshot_cache: Arc<SnapshotCache>,
+    history_cache: Arc<Mutex<PoolHistoryCache>>,
+    data_dir: PathBuf,
+    response_cache: Arc<SnapshotResponseCache<Vec<OracleStats>>>,
+    Query(query): Query<PoolHistoryQuery>,
+) -> Response {
+    let ctx = ctx.load();
+    let Some(snapshot) = snapshot_cache.load() else {
+        return snapshot_unavailable();
+    };
+    let epochs = query
+        .epochs
+        .unwrap_or(DEFAULT_STATS_EPOCHS)
+        .min(MAX_HISTORY_EPOCHS);
+    let key = SnapshotResponseKey::new(&snapshot, epochs);
+    let stats = match response_cache.get(key) {
+        Some(stats) => stats,
+        None => {
+            let pool_box_id = snapshot.pool_box_id;
+            let stats = task::spawn_blocking(move || {
+                let posted_log =
+                    PostedDatapointLog::load(&get_posted_datapoint_log_file_path(&data_dir));
+                get_oracle_stats(
+                    chain_index(&ctx)?.as_ref(),
+                    &mut history_cache.lock().unwrap(),
+                    &posted_log,
+                    &oracle_pool.pool_config().token_ids,
+                    ctx.network_prefix,
+                    pool_box_id,
+                    epochs,
+                )
+            })
+            .await
+            .unwrap();
+            match stats {
+                Ok(stats) => {
+                    response_cache.store(key, stats.clone());
+                    stats
+                }
+                Err(e) => return ApiError::from(e).into_response(),
+            }
+        }
+    };
+    Json(json!({
+        "epochs": epochs,
+        "oracles": stats,
+        "snapshot": snapshot.info(),
+    }))
+    .into_response()
+}
+
+/// Next refresh outcome from the posted datapoints read with the snapshot
+async fn refresh_preview(snapshot_cache: Arc<SnapshotCache>) -> Response {
+    let Some(snapshot) = snapshot_cache.load() else {
+        return snapshot_unavailable();
+    };
+    match &snapshot.refresh_preview {
+        Some(preview) => Json(json!({
+            "preview": preview,
+            "snapshot": snapshot.info(),
+        }))
+        .into_response(),
+        None => (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::to_value(pool_health).unwrap()),
-        );
+            Json(json!(
+                "the refresh preview is not available for this snapshot"
+            )),
+        )
+            .into_response(),
     }
+}
+
+/// Served until the main loop has taken the first snapshot
+fn snapshot_unavailable() -> Response {
     (
-        StatusCode::OK,
-        Json(serde_json::to_value(pool_health).unwrap()),
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!("the oracle state is not available yet")),
     )
+        .into_response()
 }
 
-fn oracle_health_sync(oracle_pool: Arc<OraclePool>) -> Result<OracleHealth, ApiError> {
-    let node_api = NodeApi::new(
-        ORACLE_SECRETS.node_api_key.clone(),
-        ORACLE_SECRETS.wallet_password.clone(),
-        &ORACLE_CONFIG.node_url,
-    );
-    let current_height = (node_api.node.current_block_height()? as u32).into();
-    let epoch_length = POOL_CONFIG
-        .refresh_box_wrapper_inputs
-        .contract_inputs
-        .contract_parameters()
-        .epoch_length()
-        .0
-        .into();
-    let pool_box_height = oracle_pool
-        .get_pool_box_source()
-        .get_pool_box()?
-        .get_box()
-        .creation_height
-        .into();
-    let oracle_health =
-        check_oracle_health(oracle_pool, pool_box_height, current_height, epoch_length)?;
-    Ok(oracle_health)
-}
-
-async fn pool_health(oracle_pool: Arc<OraclePool>) -> impl IntoResponse {
-    let pool_health = match task::spawn_blocking(|| pool_health_sync(oracle_pool))
+/// Block height of the Ergo blockchain
+async fn block_height(ctx: Arc<SharedContext>) -> Result<impl IntoResponse, ApiError> {
+    let ctx = ctx.load();
+    let current_height = task::spawn_blocking(move || ctx.node_api.node.current_block_height())
         .await
-        .unwrap()
-    {
-        Ok(v) => v,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(e.0))),
+        .unwrap()?;
+    Ok(format!("{}", current_height))
+}
+
+#[derive(Debug, Deserialize)]
+struct RepostQuery {
+    /// Long-poll: wait up to this many seconds for a repost request
+    wait_secs: Option<u64>,
+}
+
+/// Whether the Core requires the Connector to repost a new Datapoint
+async fn require_datapoint_repost(
+    push_source: Option<PushDataPointSource>,
+    Query(query): Query<RepostQuery>,
+) -> impl IntoResponse {
+    let required = match push_source {
+        Some(push_source) => match query.wait_secs {
+            Some(wait_secs) => {
+                let timeout = Duration::from_secs(wait_secs.min(MAX_REPOST_WAIT_SECS));
+                push_source.repost_signal().wait(timeout).await
+            }
+            None => push_source.repost_signal().take(),
+        },
+        None => false,
     };
-    // return 503 http error if pool_health.status is not ok
-    if pool_health.status != HealthStatus::Ok {
+    required.to_string()
+}
+
+/// Server-Sent Events stream of the events published by the main loop
+async fn events(events: Arc<EventBus>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = events.subscribe();
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(envelope) => {
+                    let event = Event::default()
+                        .event(envelope.event.name())
+                        .id(envelope.id.to_string())
+                        .json_data(envelope.as_ref());
+                    match event {
+                        Ok(event) => return Some((Ok(event), receiver)),
+                        Err(e) => log::error!("Failed to serialize event: {}", e),
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("/events client is too slow, skipped {skipped} events");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[derive(Debug, Deserialize)]
+struct PushDataPointRequest {
+    rate: i64,
+    /// Unix timestamp (secs) of the datapoint, the receive time is used if not set
+    timestamp: Option<u64>,
+}
+
+/// Datapoint pushed by the Connector
+async fn push_datapoint(
+    push_source: PushDataPointSource,
+    connector_api_key: Arc<String>,
+    headers: HeaderMap,
+    Json(request): Json<PushDataPointRequest>,
+) -> impl IntoResponse {
+    if !is_authorized(&headers, &connector_api_key) {
         return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::to_value(pool_health).unwrap()),
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "unauthorized" })),
         );
     }
-    (
-        StatusCode::OK,
-        Json(serde_json::to_value(pool_health).unwrap()),
+    match push_source.push(request.rate, request.timestamp) {
+        Ok(()) => {
+            log::info!("Connector pushed datapoint {}", request.rate);
+            (StatusCode::OK, Json(json!({ "accepted": true })))
+        }
+        Err(e) => {
+            log::warn!("Rejected datapoint {} from connector: {}", request.rate, e);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "accepted": false, "error": e.to_string() })),
+            )
+        }
+    }
+}
+
+/// Health checks of the oracle, responds with 503 if the oracle is Down
+async fn oracle_health(snapshot_cache: Arc<SnapshotCache>) -> Response {
+    let Some(snapshot) = snapshot_cache.load() else {
+        return snapshot_unavailable();
+    };
+    match &snapshot.oracle_health {
+        Some(oracle_health) => health_response(oracle_health.status, oracle_health, &snapshot),
+        None => snapshot_unavailable(),
+    }
+}
+
+/// Health checks of the pool, responds with 503 if the pool is Down
+async fn pool_health(snapshot_cache: Arc<SnapshotCache>) -> Response {
+    let Some(snapshot) = snapshot_cache.load() else {
+        return snapshot_unavailable();
+    };
+    health_response(
+        snapshot.pool_health.status,
+        &snapshot.pool_health,
+        &snapshot,
     )
 }
 
-fn pool_health_sync(oracle_pool: Arc<OraclePool>) -> Result<PoolHealth, ApiError> {
-    let node_api = NodeApi::new(
-        ORACLE_SECRETS.node_api_key.clone(),
-        ORACLE_SECRETS.wallet_password.clone(),
-        &ORACLE_CONFIG.node_url,
-    );
-    let current_height = (node_api.node.current_block_height()? as u32).into();
-    let pool_box = &oracle_pool.get_pool_box_source().get_pool_box()?;
-    let pool_box_height = pool_box.get_box().creation_height.into();
-    let network_prefix = node_api.get_change_address()?.network();
-    let pool_health = check_pool_health(
-        current_height,
-        pool_box_height,
-        pool_box.rate(),
+fn health_response<T: Serialize>(
+    status: HealthStatus,
+    health: &T,
+    snapshot: &PoolSnapshot,
+) -> Response {
+    let mut json = serde_json::to_value(health).unwrap();
+    json["snapshot"] = snapshot.info();
+    // return 503 http error if the status is Down, Degraded is still served with 200
+    let status_code = if status == HealthStatus::Down {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (status_code, Json(json)).into_response()
+}
+
+/// Pool served by the REST API, at the top level or under `/pools/<name>` in the multi-pool mode
+pub struct PoolRoutes {
+    pub name: String,
+    pub ctx: Arc<SharedContext>,
+    pub oracle_pool: Arc<OraclePool>,
+    pub events: Arc<EventBus>,
+    pub snapshot_cache: Arc<SnapshotCache>,
+    pub history_cache: SharedPoolHistoryCache,
+    /// Folder of the pool's data files (pool history and posted datapoints)
+    pub data_dir: PathBuf,
+}
+
+/// Routes of the pool and of our oracle in it
+fn pool_router(pool: PoolRoutes) -> Router {
+    let PoolRoutes {
+        ctx,
         oracle_pool,
-        network_prefix,
-    )?;
-    Ok(pool_health)
+        events: events_bus,
+        snapshot_cache,
+        history_cache,
+        data_dir,
+        ..
+    } = pool;
+    let snapshot_cache_clone = snapshot_cache.clone();
+    let snapshot_cache_clone2 = snapshot_cache.clone();
+    let snapshot_cache_clone3 = snapshot_cache.clone();
+    let snapshot_cache_clone4 = snapshot_cache.clone();
+    let snapshot_cache_clone5 = snapshot_cache.clone();
+    let oracle_pool_clone = oracle_pool.clone();
+    let network = ctx.load().network_prefix;
+    Router::new()
+        .route("/oracleStatus", get(|| oracle_status(snapshot_cache)))
+        .route(
+            "/poolInfo",
+            get(move || pool_info(oracle_pool_clone, network)),
+        )
+        .route("/poolStatus", get(|| pool_status(snapshot_cache_clone)))
+        .route(
+            "/oracleHealth",
+            get(|| oracle_health(snapshot_cache_clone2)),
+        )
+        .route("/poolHealth", get(|| pool_health(snapshot_cache_clone3)))
+        .route(
+            "/poolHistory",
+            get({
+                let ctx = ctx.clone();
+                let oracle_pool = oracle_pool.clone();
+                let snapshot_cache = snapshot_cache_clone4.clone();
+                let history_cache = history_cache.clone();
+                let response_cache = Arc::new(SnapshotResponseCache::new());
+                move |query| {
+                    pool_history(
+                        ctx,
+                        oracle_pool,
+                        snapshot_cache,
+                        history_cache,
+                        response_cache,
+                        query,
+                    )
+                }
+            }),
+        )
+        .route(
+            "/oracles/stats",
+            get({
+                let snapshot_cache = snapshot_cache_clone4;
+                let response_cache = Arc::new(SnapshotResponseCache::new());
+                move |query| {
+                    oracle_stats(
+                        ctx,
+                        oracle_pool,
+                        snapshot_cache,
+                        history_cache,
+                        data_dir,
+                        response_cache,
+                        query,
+                    )
+                }
+            }),
+        )
+        .route(
+            "/refreshPreview",
+            get(|| refresh_preview(snapshot_cache_clone5)),
+        )
+        .route("/events", get(|| events(events_bus)))
 }
 
 pub async fn start_rest_server(
-    repost_receiver: Receiver<bool>,
-    oracle_pool: Arc<OraclePool>,
+    pool: PoolRoutes,
+    push_source: Option<PushDataPointSource>,
+    pool_control: Arc<PoolControl>,
     api_port: u16,
 ) -> Result<(), anyhow::Error> {
-    let op_clone = oracle_pool.clone();
-    let op_clone2 = oracle_pool.clone();
-    let op_clone3 = oracle_pool.clone();
-    let app = Router::new()
+    let ctx = pool.ctx.clone();
+    let current_ctx = ctx.load();
+    let admin_router = if current_ctx.oracle_config.admin_api_enabled {
+        let api_key = current_ctx.secrets.admin_api_key.clone().ok_or_else(|| {
+            anyhow::anyhow!("admin API is enabled, but ORACLE_ADMIN_API_KEY is not set or empty")
+        })?;
+        Some(admin_router(
+            api_key,
+            ctx.clone(),
+            pool.oracle_pool.clone(),
+            pool_control,
+            pool.data_dir.join("admin_audit.log"),
+        ))
+    } else {
+        None
+    };
+    let mut app = Router::new()
         .route("/", get(root))
-        .route("/oracleInfo", get(oracle_info))
-        .route("/oracleStatus", get(|| oracle_status(oracle_pool)))
-        .route("/poolInfo", get(pool_info))
-        .route("/poolStatus", get(|| pool_status(op_clone)))
-        .route("/blockHeight", get(block_height))
-        .route("/oracleHealth", get(|| oracle_health(op_clone2)))
-        .route("/poolHealth", get(|| pool_health(op_clone3)))
+        .route(
+            "/oracleInfo",
+            get({
+                let ctx = ctx.clone();
+                move || oracle_info(ctx)
+            }),
+        )
+        .route(
+            "/blockHeight",
+            get({
+                let ctx = ctx.clone();
+                move || block_height(ctx)
+            }),
+        )
         .route(
             "/requireDatapointRepost",
-            get(|| require_datapoint_repost(repost_receiver)),
+            get({
+                let push_source = push_source.clone();
+                move |query| require_datapoint_repost(push_source, query)
+            }),
+        )
+        .merge(pool_router(pool));
+    if let Some(push_source) = push_source {
+        let connector_api_key = Arc::new(
+            current_ctx
+                .secrets
+                .connector_api_key
+                .clone()
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "data_point_source_push is set, but ORACLE_CONNECTOR_API_KEY is not set or empty"
+                    )
+                })?,
+        );
+        app = app.route(
+            "/datapoint",
+            post(move |headers, body| {
+                push_datapoint(push_source, connector_api_key, headers, body)
+            }),
+        );
+    }
+    if let Some(admin_router) = admin_router {
+        log::info!("Admin API is enabled");
+        app = app.nest("/admin", admin_router);
+    }
+    let app = app.layer(
+        CorsLayer::new()
+            .allow_origin(tower_http::cors::Any)
+            .allow_methods([axum::http::Method::GET]),
+    );
+    serve(
+        app,
+        api_port,
+        &current_ctx.oracle_config.rest_api,
+        "REST server",
+    )
+    .await?;
+    Ok(())
+}
+
+/// REST API of the multi-pool mode: the routes of each pool under `/pools/<name>`
+pub async fn start_pools_rest_server(
+    ctx: Arc<SharedContext>,
+    pools: Vec<PoolRoutes>,
+    api_port: u16,
+) -> Result<(), anyhow::Error> {
+    let pool_names: Vec<String> = pools.iter().map(|pool| pool.name.clone()).collect();
+    let mut app = Router::new()
+        .route("/", get(pools_root))
+        .route(
+            "/oracleInfo",
+            get({
+                let ctx = ctx.clone();
+                move || oracle_info(ctx)
+            }),
         )
+        .route(
+            "/blockHeight",
+            get({
+                let ctx = ctx.clone();
+                move || block_height(ctx)
+            }),
+        )
+        .route("/pools", get(move || async move { Json(pool_names) }));
+    for pool in pools {
+        let path = format!("/pools/{}", pool.name);
+        app = app.nest(&path, pool_router(pool));
+    }
+    let app = app.layer(
+        CorsLayer::new()
+            .allow_origin(tower_http::cors::Any)
+            .allow_methods([axum::http::Method::GET]),
+    );
+    let rest_api_config = ctx.load().oracle_config.rest_api.clone();
+    serve(app, api_port, &rest_api_config, "REST server").await?;
+    Ok(())
+}
+
+async fn pools_root() -> &'static str {
+    "This is an Oracle Core running several pools. Please use one of the endpoints:
+        /pools - names of the pools
+        /oracleInfo - basic information about the oracle
+        /blockHeight - current height of the node
+        /pools/<name>/poolInfo, /poolStatus, /poolHealth, /poolHistory, /oracleStatus, /oracleHealth, /oracles/stats, /refreshPreview, /events - the single pool endpoints of the pool <name>
+        "
+}
+
+/// REST API of the `monitor` command: the pool routes only, no oracle or admin routes
+pub async fn start_monitor_rest_server(
+    oracle_pool: Arc<OraclePool>,
+    events_bus: Arc<EventBus>,
+    snapshot_cache: Arc<SnapshotCache>,
+    network: NetworkPrefix,
+    api_port: u16,
+    server_config: HttpServerConfig,
+) -> Result<(), anyhow::Error> {
+    let snapshot_cache_clone = snapshot_cache.clone();
+    let app = Router::new()
+        .route("/", get(monitor_root))
+        .route("/poolInfo", get(move || pool_info(oracle_pool, network)))
+        .route("/poolStatus", get(|| pool_status(snapshot_cache)))
+        .route("/poolHealth", get(|| pool_health(snapshot_cache_clone)))
+        .route("/events", get(|| events(events_bus)))
         .layer(
             CorsLayer::new()
                 .allow_origin(tower_http::cors::Any)
                 .allow_methods([axum::http::Method::GET]),
         );
-    let addr = SocketAddr::from(([0, 0, 0, 0], api_port));
-    log::info!("Starting REST server on {}", addr);
-    axum::Server::try_bind(&addr)?
-        .serve(app.into_make_service())
-        .await?;
+    serve(app, api_port, &server_config, "REST server").await?;
     Ok(())
 }
 
+async fn monitor_root() -> &'static str {
+    "This is an Oracle Core observing a pool (monitor mode). Please use one of the endpoints:
+        /poolInfo - basic information about the oracle pool
+        /poolStatus - status of the oracle pool
+        /poolHealth - status (Ok/Degraded/Down) of the pool checks: pool box age, active oracles count vs min data points and reward tokens left in the pool box
+        /events - Server-Sent Events stream of the pool events (new pool box, health changes, ballot and update box changes)
+        "
+}
+
 struct ApiError(String);
 
 impl From<DataSourceError> for ApiError {
@@ -330,6 +766,12 @@ impl From<anyhow::Error> for ApiError {
     }
 }
 
+impl From<PoolHistoryError> for ApiError {
+    fn from(err: PoolHistoryError) -> Self {
+        ApiError(format!("PoolHistoryError: {}", err))
+    }
+}
+
 impl From<NodeApiError> for ApiError {
     fn from(err: NodeApiError) -> Self {
         ApiError(format!("NodeApiError: {:?}", err))
@@ -0,0 +1,1162 @@
+//! Command line of the `oracle-core` binary and its main loop
+use crate::{
+    action_report, actions, cli_commands, config_reload, context, datapoint_source, events,
+    explorer_api, fee_runway, http_server, logging, metrics, node_interface, node_sync, notifier,
+    observer, oracle_config, oracle_state, oracle_stats, oracle_types, pending_tx, pool_commands,
+    pool_config, pool_runtime, public_boxes, scans, snapshot, spec_token, state, wallet_monitor,
+};
+
+use action_report::PoolActionReport;
+use actions::PoolAction;
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use config_reload::ConfigReloadError;
+use config_reload::ConfigWatcher;
+use context::OracleContext;
+use datapoint_source::RuntimeDataPointSource;
+use ergo_lib::ergo_chain_types::Digest32;
+use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use ergo_lib::ergotree_ir::chain::token::TokenAmount;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use events::ConsensusFailure;
+use events::EventBus;
+use events::OracleEvent;
+use explorer_api::explorer_url::default_explorer_api_url;
+use fee_runway::FeeRunwayStatus;
+use http_server::HttpServerConfig;
+use log::error;
+use log::LevelFilter;
+use log4rs::Handle;
+use metrics::start_metrics_server;
+use metrics::update_node_metrics;
+use node_interface::node_api::NodeApi;
+use node_interface::try_ensure_wallet_unlocked;
+use node_sync::check_node_sync;
+use notifier::Alert;
+use notifier::AlertKind;
+use notifier::Notifier;
+use oracle_config::OracleSecrets;
+use oracle_state::OraclePool;
+use oracle_types::BlockHeight;
+use pending_tx::PendingTxKind;
+use pool_commands::build_action;
+use pool_commands::publish_datapoint::PublishDatapointActionError;
+use pool_commands::refresh::RefreshActionError;
+use pool_commands::PoolCommand;
+use pool_commands::PoolCommandError;
+use pool_config::PoolConfig;
+use pool_config::DEFAULT_POOL_CONFIG_FILE_NAME;
+use pool_runtime::PoolRuntime;
+use public_boxes::PublicChainReader;
+use reqwest::Url;
+use scans::get_scans_file_path;
+use scans::wait_for_node_rescan;
+use snapshot::PoolSnapshot;
+use spec_token::RewardTokenId;
+use spec_token::SpecToken;
+use spec_token::TokenIdKind;
+use state::process;
+use state::PoolState;
+use std::convert::TryFrom;
+use std::env;
+use std::net::IpAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wallet_monitor::WalletMonitor;
+
+use crate::actions::execute_action;
+use crate::address_util::pks_to_network_addresses;
+use crate::api::start_pools_rest_server;
+use crate::api::start_rest_server;
+use crate::box_kind::BallotBox;
+use crate::contracts::ballot::BallotContract;
+use crate::default_parameters::print_contract_hashes;
+use crate::migrate::check_migration_to_split_config;
+use crate::oracle_config::BoxSource;
+use crate::oracle_config::OracleConfig;
+use crate::oracle_config::DEFAULT_ORACLE_CONFIG_FILE_NAME;
+use crate::oracle_config::ORACLE_CONFIG_FILE_PATH;
+use crate::pool_config::POOL_CONFIG_FILE_PATH;
+use crate::scans::NodeScanRegistry;
+
+const APP_VERSION: &str = concat!(
+    "v",
+    env!("CARGO_PKG_VERSION"),
+    "+",
+    env!("GIT_COMMIT_HASH"),
+    " ",
+    env!("GIT_COMMIT_DATE")
+);
+
+#[derive(Debug, Parser)]
+#[clap(author, version = APP_VERSION, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+    /// Increase the logging verbosity
+    #[clap(short, long)]
+    verbose: bool,
+    /// Set path of oracle configuration file to use. Default is ./oracle_config.yaml
+    #[clap(long)]
+    oracle_config_file: Option<String>,
+    /// Set path of pool configuration file to use. Default is ./pool_config.yaml
+    #[clap(long)]
+    pool_config_file: Option<String>,
+    /// Set folder path for the data files (scanIDs.json, logs). Default is the current folder.
+    #[clap(short, long)]
+    data_dir: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate oracle_config.yaml with default settings.
+    GenerateOracleConfig,
+    /// Bootstrap a new oracle-pool or generate a bootstrap config template file using default
+    /// contract scripts and parameters.
+    Bootstrap {
+        /// The name of the bootstrap config file.
+        yaml_config_name: String,
+        #[clap(short, long)]
+        /// Set this flag to output a bootstrap config template file to the given filename. If
+        /// filename already exists, return error.
+        generate_config_template: bool,
+        /// Continue a bootstrap that failed halfway from its journal in the data folder: the txs
+        /// that are not in a block or in the mempool are submitted again
+        #[clap(long)]
+        resume: bool,
+    },
+
+    /// Run the oracle-pool
+    Run {
+        /// Run in read-only mode
+        #[clap(long)]
+        read_only: bool,
+        #[clap(long)]
+        /// Set this flag to enable the REST API. See `rest_api` in the oracle config for the bind
+        /// address, TLS and access control
+        enable_rest_api: bool,
+    },
+
+    /// Watch a pool without being one of its oracles: only pool_config.yaml is loaded, no oracle
+    /// config, node API key, wallet or oracle token is needed and nothing is ever signed. The
+    /// boxes are read through the node's blockchain indexer or the explorer API
+    Monitor {
+        /// Node with the blockchain indexer enabled (`extraIndex = true`), only its public
+        /// endpoints are used. The explorer API is used if not set
+        #[clap(long)]
+        node_url: Option<Url>,
+        /// Explorer API URL, the public explorer of the network by default
+        #[clap(long)]
+        explorer_url: Option<Url>,
+        /// The pool is on the testnet
+        #[clap(long)]
+        testnet: bool,
+        /// Serve the pool routes of the REST API on this port
+        #[clap(long)]
+        api_port: Option<u16>,
+        /// Serve the Prometheus metrics on this port
+        #[clap(long)]
+        metrics_port: Option<u16>,
+        /// Bind address of the REST API and metrics servers
+        #[clap(long, default_value = "0.0.0.0")]
+        bind_address: IpAddr,
+    },
+
+    /// Send reward tokens accumulated in the oracle box to a chosen address
+    ExtractRewardTokens {
+        /// Base58 encoded address to send reward tokens to
+        rewards_address: String,
+    },
+
+    /// Print the number of reward tokens earned by the oracle (in the last posted/collected oracle box)
+    PrintRewardTokens,
+
+    /// Print the wallet balance and the estimated number of epochs it can pay tx fees for
+    WalletStatus,
+
+    /// Print the past pool boxes (rate, oracles and collector of each epoch). Uses the node's
+    /// blockchain indexer if it is enabled, the explorer API otherwise
+    PoolHistory {
+        /// Number of epochs to print
+        #[clap(long, default_value_t = 10)]
+        epochs: usize,
+    },
+
+    /// Print the outcome of the next refresh from the currently posted datapoints: included and
+    /// excluded oracles with their deviations and the resulting pool rate. Nothing is sent
+    PreviewRefresh,
+
+    /// Print the per-oracle stats over the last epochs: epochs participated, epochs filtered out as
+    /// outliers, average deviation from the pool rate, collections and reward tokens earned
+    OracleStats {
+        /// Number of epochs to compute the stats over
+        #[clap(long, default_value_t = oracle_stats::DEFAULT_STATS_EPOCHS)]
+        epochs: usize,
+    },
+
+    /// Transfer an oracle token to a chosen address.
+    TransferOracleToken {
+        /// Base58 encoded address to send oracle token to
+        oracle_token_address: String,
+    },
+
+    /// Vote to update the oracle pool
+    VoteUpdatePool {
+        /// The base16-encoded blake2b hash of the serialized pool box contract for the new pool box.
+        new_pool_box_address_hash_str: String,
+        /// The creation height of the existing update box.
+        update_box_creation_height: u32,
+        /// The base16-encoded reward token id of the new pool box (if minted)
+        reward_token_id_str: Option<String>,
+        /// The reward token amount in the pool box at the time of update transaction is committed (if minted).
+        reward_token_amount: Option<u64>,
+    },
+    /// Initiate the Update Pool transaction.
+    /// Updated config file `pool_config_updated.yaml` is expected to be in the current directory
+    /// and must be created using --prepare-update command first
+    UpdatePool {
+        /// New reward token id (only if minted)
+        reward_token_id: Option<String>,
+        /// New reward token amount (only if minted)
+        reward_token_amount: Option<u64>,
+    },
+    /// Prepare updating oracle pool with new contracts/parameters.
+    /// Creates new refresh box and pool box if needed (e.g. if new reward tokens are minted)
+    PrepareUpdate {
+        /// Name of the parameters file (.yaml) with new contract parameters
+        update_file: String,
+    },
+
+    /// Print base 64 encodings of the blake2b hash of ergo-tree bytes of each contract
+    PrintContractHashes,
+
+    /// Check the setup step by step (configs, node, wallet, scans, pool boxes, oracle token,
+    /// datapoint source) and print a hint for each failed check
+    Doctor,
+
+    ImportPoolUpdate {
+        /// Name of the pool config file (.yaml) with new contract parameters
+        pool_config_file: String,
+    },
+}
+
+/// Entry point of the `oracle-core` binary
+pub fn run() {
+    let args = Args::parse();
+
+    ORACLE_CONFIG_FILE_PATH
+        .set(
+            PathBuf::from_str(
+                &args
+                    .oracle_config_file
+                    .unwrap_or_else(|| DEFAULT_ORACLE_CONFIG_FILE_NAME.to_string()),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    POOL_CONFIG_FILE_PATH
+        .set(
+            PathBuf::from_str(
+                &args
+                    .pool_config_file
+                    .unwrap_or_else(|| DEFAULT_POOL_CONFIG_FILE_NAME.to_string()),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+    let pool_config_path = POOL_CONFIG_FILE_PATH.get().unwrap();
+    let oracle_config_path = ORACLE_CONFIG_FILE_PATH.get().unwrap();
+
+    if !pool_config_path.exists() && oracle_config_path.exists() {
+        if let Err(e) = check_migration_to_split_config(oracle_config_path, pool_config_path) {
+            eprintln!("Failed to migrate to split config: {}", e);
+        }
+    }
+
+    let is_monitor = matches!(args.command, Command::Monitor { .. });
+    let is_doctor = matches!(args.command, Command::Doctor);
+    if !oracle_config_path.exists() && !is_monitor && !is_doctor {
+        OracleConfig::write_default_config_file(oracle_config_path);
+        println!(
+            "{} not found. Default config file is generated.",
+            oracle_config_path.display()
+        );
+        println!(
+            "Please, set the required parameters(node credentials, oracle_address) and run again"
+        );
+        return;
+    }
+
+    let cmdline_log_level = if args.verbose {
+        Some(LevelFilter::Debug)
+    } else {
+        None
+    };
+    let data_dir_path = if let Some(ref data_dir) = args.data_dir {
+        Path::new(&data_dir).to_path_buf()
+    } else {
+        env::current_dir().unwrap()
+    };
+
+    let oracle_config_res = OracleConfig::load();
+    let config_log_level = oracle_config_res
+        .as_ref()
+        .map(|c| c.log_level)
+        .ok()
+        .flatten();
+    let log_handle = logging::setup_log(cmdline_log_level, config_log_level, &data_dir_path);
+
+    scans::SCANS_DIR_PATH.set(data_dir_path.clone()).unwrap();
+
+    log_on_launch(oracle_config_res.as_ref().ok());
+    if let Command::Monitor {
+        node_url,
+        explorer_url,
+        testnet,
+        api_port,
+        metrics_port,
+        bind_address,
+    } = args.command
+    {
+        let network_prefix = if testnet {
+            NetworkPrefix::Testnet
+        } else {
+            NetworkPrefix::Mainnet
+        };
+        let reader = match node_url {
+            Some(node_url) => PublicChainReader::node_indexer(&node_url),
+            None => PublicChainReader::explorer(
+                explorer_url.unwrap_or_else(|| default_explorer_api_url(network_prefix)),
+            ),
+        };
+        let server_config = HttpServerConfig {
+            bind_address,
+            ..Default::default()
+        };
+        let pool_config = match PoolConfig::load_from_file(pool_config_path) {
+            Ok(pool_config) => pool_config,
+            Err(e) => {
+                error!("Failed to load the pool config: {:?}", e);
+                std::process::exit(exitcode::CONFIG);
+            }
+        };
+        observer::run_monitor(
+            pool_config,
+            reader,
+            network_prefix,
+            api_port,
+            metrics_port,
+            server_config,
+        );
+        return;
+    }
+    if is_doctor {
+        if !cli_commands::doctor::doctor(oracle_config_res, pool_config_path, &data_dir_path) {
+            std::process::exit(exitcode::CONFIG);
+        }
+        return;
+    }
+    let oracle_config = match oracle_config_res {
+        Ok(oracle_config) => Arc::new(oracle_config),
+        Err(e) => {
+            error!("Failed to load the oracle config: {:?}", e);
+            std::process::exit(exitcode::CONFIG);
+        }
+    };
+    let secrets = Arc::new(OracleSecrets::load());
+    let node_api = Arc::new(NodeApi::new(
+        secrets.node_api_key.clone(),
+        secrets.wallet_password.clone(),
+        &oracle_config.node_url,
+    ));
+    try_ensure_wallet_unlocked(&node_api);
+    if oracle_config.box_source == BoxSource::NodeScans {
+        wait_for_node_rescan(&node_api).unwrap();
+    }
+
+    let change_address = node_api
+        .get_change_address()
+        .expect("failed to get change address from the node");
+    let network_prefix = change_address.network();
+
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match args.command {
+        Command::GenerateOracleConfig => {
+            if !oracle_config_path.exists() {
+                OracleConfig::write_default_config_file(oracle_config_path);
+                println!("Default oracle_config.yaml file is generated.");
+                println!("Please, set the required parameters (node credentials, oracle_address)");
+            } else {
+                println!("oracle_config.yaml file already exists. Please, remove it and run again");
+            }
+        }
+        Command::Bootstrap {
+            yaml_config_name,
+            generate_config_template,
+            resume,
+        } => {
+            if let Err(e) = (|| -> Result<(), anyhow::Error> {
+                if generate_config_template {
+                    cli_commands::bootstrap::generate_bootstrap_config_template(yaml_config_name)?;
+                } else {
+                    cli_commands::bootstrap::bootstrap(
+                        yaml_config_name,
+                        &oracle_config,
+                        &node_api,
+                        BoxValue::try_from(oracle_config.base_fee)?,
+                        &data_dir_path,
+                        resume,
+                    )?;
+                }
+                Ok(())
+            })() {
+                {
+                    error!("Fatal advanced-bootstrap error: {:?}", e);
+                    std::process::exit(exitcode::SOFTWARE);
+                }
+            };
+        }
+        Command::PrintContractHashes => {
+            print_contract_hashes();
+        }
+        Command::Run {
+            read_only,
+            enable_rest_api,
+        } => {
+            let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
+            let mut pools = if oracle_config.pools.is_empty() {
+                let ctx =
+                    oracle_context(oracle_config.clone(), secrets, node_api, pool_config_path);
+                let datapoint_source = RuntimeDataPointSource::new(
+                    ctx.pool_config.data_point_source,
+                    oracle_config.data_point_source_custom_script.clone(),
+                    oracle_config.data_point_source_push.clone(),
+                )
+                .unwrap();
+                vec![
+                    PoolRuntime::new(String::new(), ctx, datapoint_source, data_dir_path.clone())
+                        .unwrap(),
+                ]
+            } else {
+                if oracle_config.data_point_source_push.is_some() {
+                    log::warn!(
+                        "data_point_source_push is not supported with several pools and is ignored"
+                    );
+                }
+                pool_runtime::load_pools(
+                    &oracle_config.pools,
+                    &oracle_config,
+                    &secrets,
+                    &node_api,
+                    &data_dir_path,
+                )
+                .unwrap()
+            };
+
+            // Start Oracle Core REST API Server
+            if enable_rest_api {
+                let core_api_port = oracle_config.core_api_port;
+                if oracle_config.pools.is_empty() {
+                    let pool = &pools[0];
+                    let pool_routes = pool.routes();
+                    let push_source = pool.datapoint_source.push_source();
+                    let pool_control_clone = pool.pool_control.clone();
+                    tokio_runtime.spawn(async move {
+                        if let Err(e) = start_rest_server(
+                            pool_routes,
+                            push_source,
+                            pool_control_clone,
+                            core_api_port,
+                        )
+                        .await
+                        {
+                            error!("An error occurred while starting the REST server: {}", e);
+                            std::process::exit(exitcode::SOFTWARE);
+                        }
+                    });
+                } else {
+                    let ctx = pools[0].ctx.clone();
+                    let pool_routes = pools.iter().map(PoolRuntime::routes).collect();
+                    tokio_runtime.spawn(async move {
+                        if let Err(e) =
+                            start_pools_rest_server(ctx, pool_routes, core_api_port).await
+                        {
+                            error!("An error occurred while starting the REST server: {}", e);
+                            std::process::exit(exitcode::SOFTWARE);
+                        }
+                    });
+                }
+            } else if oracle_config.data_point_source_push.is_some()
+                && oracle_config.pools.is_empty()
+            {
+                log::warn!("data_point_source_push is set, but the REST API is disabled (use --enable-rest-api), the connector cannot push datapoints");
+            }
+            if let Some(metrics_port) = oracle_config.metrics_port {
+                let metrics_server_config = oracle_config.metrics_server.clone();
+                tokio_runtime.spawn(async move {
+                    if let Err(e) = start_metrics_server(metrics_port, &metrics_server_config).await
+                    {
+                        error!("An error occurred while starting the metrics server: {}", e);
+                        std::process::exit(exitcode::SOFTWARE);
+                    }
+                });
+            }
+            let mut config_watcher = ConfigWatcher::new(oracle_config_path);
+            #[cfg(unix)]
+            {
+                let sighup_flag = config_watcher.sighup_flag();
+                tokio_runtime.spawn(async move {
+                    if let Err(e) = config_reload::listen_sighup(sighup_flag).await {
+                        error!("Failed to listen for SIGHUP: {}", e);
+                    }
+                });
+            }
+            let mut wallet_monitor = WalletMonitor::new();
+            loop {
+                if config_watcher.reload_requested() {
+                    reload_oracle_config(
+                        &mut pools,
+                        &log_handle,
+                        cmdline_log_level,
+                        &data_dir_path,
+                    );
+                }
+                // the pools share the wallet, their actions are built and sent one pool at a time
+                for pool in pools.iter_mut() {
+                    if let Err(e) = pool.verify_scans() {
+                        if pool.name.is_empty() {
+                            error!("failed to verify the scans: {:?}", e);
+                        } else {
+                            error!("pool {} failed to verify the scans: {:?}", pool.name, e);
+                        }
+                    }
+                    if let Err(e) = pool.check_reorg() {
+                        if pool.name.is_empty() {
+                            error!("failed to check for chain reorganizations: {:?}", e);
+                        } else {
+                            error!(
+                                "pool {} failed to check for chain reorganizations: {:?}",
+                                pool.name, e
+                            );
+                        }
+                    }
+                    if let Err(e) =
+                        main_loop_iteration(pool, read_only, &mut wallet_monitor, &change_address)
+                    {
+                        if pool.name.is_empty() {
+                            error!("error: {:?}", e);
+                        } else {
+                            error!("pool {} error: {:?}", pool.name, e);
+                        }
+                    }
+                }
+                // Delay loop restart
+                thread::sleep(Duration::new(30, 0));
+            }
+        }
+        oracle_command => {
+            let ctx = oracle_context(oracle_config, secrets, node_api, pool_config_path);
+            handle_pool_command(oracle_command, &ctx, network_prefix)
+        }
+    }
+}
+
+/// Context of the pool of `pool_config.yaml`, exits if the configs cannot be loaded
+fn oracle_context(
+    oracle_config: Arc<OracleConfig>,
+    secrets: Arc<OracleSecrets>,
+    node_api: Arc<NodeApi>,
+    pool_config_path: &Path,
+) -> Arc<OracleContext> {
+    let pool_config = match PoolConfig::load_from_file(pool_config_path) {
+        Ok(pool_config) => Arc::new(pool_config),
+        Err(e) => {
+            error!("Failed to load the pool config: {:?}", e);
+            std::process::exit(exitcode::CONFIG);
+        }
+    };
+    match OracleContext::new(oracle_config, pool_config, secrets, node_api) {
+        Ok(ctx) => Arc::new(ctx),
+        Err(e) => {
+            error!("Invalid oracle config: {}", e);
+            std::process::exit(exitcode::CONFIG);
+        }
+    }
+}
+
+/// Applies the reloaded `oracle_config.yaml` to all pools, or to none of them if it is invalid or
+/// changes fields that require a restart
+fn reload_oracle_config(
+    pools: &mut [PoolRuntime],
+    log_handle: &Handle,
+    cmdline_log_level: Option<LevelFilter>,
+    data_dir: &Path,
+) {
+    let current: Vec<Arc<OracleContext>> = pools.iter().map(|pool| pool.ctx.load()).collect();
+    let reloaded = OracleConfig::load()
+        .map_err(ConfigReloadError::Load)
+        .and_then(|new_config| config_reload::reload_contexts(&current, new_config));
+    let new_contexts = match reloaded {
+        Ok(new_contexts) => new_contexts,
+        Err(e) => {
+            error!("Oracle config not reloaded: {}", e);
+            return;
+        }
+    };
+    // the datapoint sources of the multi-pool mode and the push source are set on startup only
+    let mut new_datapoint_sources = Vec::new();
+    for (pool, ctx) in pools.iter().zip(&new_contexts) {
+        let script = ctx.oracle_config.data_point_source_custom_script.clone();
+        let script_changed = script
+            != pool
+                .ctx
+                .load()
+                .oracle_config
+                .data_point_source_custom_script;
+        if ctx.oracle_config.pools.is_empty()
+            && ctx.oracle_config.data_point_source_push.is_none()
+            && script_changed
+        {
+            match RuntimeDataPointSource::new(ctx.pool_config.data_point_source, script, None) {
+                Ok(source) => new_datapoint_sources.push(Some(source)),
+                Err(e) => {
+                    error!(
+                        "Oracle config not reloaded, invalid datapoint source: {:?}",
+                        e
+                    );
+                    return;
+                }
+            }
+        } else {
+            new_datapoint_sources.push(None);
+        }
+    }
+    for ((pool, ctx), datapoint_source) in pools
+        .iter_mut()
+        .zip(new_contexts)
+        .zip(new_datapoint_sources)
+    {
+        pool.notifier
+            .set_config(ctx.oracle_config.notifications.clone());
+        if let Some(datapoint_source) = datapoint_source {
+            pool.datapoint_source = datapoint_source;
+        }
+        pool.ctx.store(ctx);
+    }
+    if let Some(pool) = pools.first() {
+        logging::set_log_level(
+            log_handle,
+            cmdline_log_level,
+            pool.ctx.load().oracle_config.log_level,
+            data_dir,
+        );
+    }
+    log::info!("Oracle config reloaded");
+}
+
+/// Handle all other commands
+fn handle_pool_command(command: Command, ctx: &OracleContext, network_prefix: NetworkPrefix) {
+    let height = BlockHeight(ctx.node_api.node.current_block_height().unwrap() as u32);
+    let op = OraclePool::load(ctx).unwrap();
+    match command {
+        Command::ExtractRewardTokens { rewards_address } => {
+            if let Err(e) = cli_commands::extract_reward_tokens::extract_reward_tokens(
+                ctx,
+                op.get_local_datapoint_box_source(),
+                rewards_address,
+                height,
+            ) {
+                error!("Fatal extract-rewards-token error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::PrintRewardTokens => {
+            if let Err(e) = cli_commands::print_reward_tokens::print_reward_tokens(
+                op.get_local_datapoint_box_source(),
+            ) {
+                error!("Fatal print-rewards-token error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::WalletStatus => {
+            if let Err(e) = cli_commands::wallet_status::wallet_status(ctx, &op) {
+                error!("Fatal wallet-status error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::PoolHistory { epochs } => {
+            if let Err(e) = cli_commands::pool_history::print_pool_history(ctx, &op, epochs) {
+                error!("Fatal pool-history error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::PreviewRefresh => {
+            if let Err(e) = cli_commands::preview_refresh::print_refresh_preview(ctx, &op, height) {
+                error!("Fatal preview-refresh error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::OracleStats { epochs } => {
+            if let Err(e) = cli_commands::oracle_stats::print_oracle_stats(ctx, &op, epochs) {
+                error!("Fatal oracle-stats error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::TransferOracleToken {
+            oracle_token_address,
+        } => {
+            if let Err(e) = cli_commands::transfer_oracle_token::transfer_oracle_token(
+                ctx,
+                op.get_local_datapoint_box_source(),
+                oracle_token_address,
+                height,
+            ) {
+                error!("Fatal transfer-oracle-token error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::VoteUpdatePool {
+            new_pool_box_address_hash_str,
+            reward_token_id_str,
+            reward_token_amount,
+            update_box_creation_height,
+        } => {
+            let reward_token_opt = check_reward_token_opt(reward_token_id_str, reward_token_amount);
+            log::debug!(
+                "found ballot boxes: {:?}",
+                op.get_ballot_boxes_source()
+                    .get_ballot_boxes()
+                    .unwrap()
+                    .into_iter()
+                    .map(|b| (
+                        b.get_box().box_id(),
+                        b.ballot_token_owner_address(network_prefix).to_base58()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+            let ballot_contract = BallotContract::checked_load(
+                &ctx.pool_config.ballot_box_wrapper_inputs.contract_inputs,
+            )
+            .unwrap();
+            if let Err(e) = cli_commands::vote_update_pool::vote_update_pool(
+                ctx,
+                op.get_local_ballot_box_source(),
+                new_pool_box_address_hash_str,
+                reward_token_opt,
+                BlockHeight(update_box_creation_height),
+                height,
+                &ballot_contract,
+            ) {
+                error!("Fatal vote-update-pool error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+        Command::UpdatePool {
+            reward_token_id,
+            reward_token_amount,
+        } => {
+            let reward_token_opt = check_reward_token_opt(reward_token_id, reward_token_amount);
+            if let Err(e) =
+                cli_commands::update_pool::update_pool(ctx, &op, reward_token_opt, height)
+            {
+                error!("Fatal update-pool error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+        Command::PrepareUpdate { update_file } => {
+            if let Err(e) = cli_commands::prepare_update::prepare_update(update_file, ctx, height) {
+                error!("Fatal update error : {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+        Command::ImportPoolUpdate { pool_config_file } => {
+            if op.get_pool_box_source().get_pool_box().is_ok() {
+                error!("Seems like update-pool command is missing (pool box is found).");
+                std::process::exit(exitcode::SOFTWARE);
+            }
+            if let Err(e) = cli_commands::import_pool_update::import_pool_update(
+                pool_config_file,
+                ctx,
+                POOL_CONFIG_FILE_PATH.get().unwrap(),
+                op.get_local_datapoint_box_source(),
+                &get_scans_file_path(),
+                match ctx.oracle_config.box_source {
+                    BoxSource::NodeScans => Some(NodeScanRegistry::load().unwrap()),
+                    BoxSource::NodeIndexer => None,
+                },
+            ) {
+                error!("Fatal import pool update error : {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            } else {
+                log::info!("pool config update imported successfully. Please, restart the oracle");
+                std::process::exit(exitcode::OK);
+            }
+        }
+        Command::Bootstrap { .. }
+        | Command::PrintContractHashes
+        | Command::GenerateOracleConfig
+        | Command::Monitor { .. }
+        | Command::Doctor
+        | Command::Run { .. } => unreachable!(),
+    }
+}
+
+fn main_loop_iteration(
+    pool: &mut PoolRuntime,
+    read_only: bool,
+    wallet_monitor: &mut WalletMonitor,
+    change_address: &NetworkAddress,
+) -> std::result::Result<(), anyhow::Error> {
+    // the whole iteration uses the same context even if the config is reloaded meanwhile
+    let ctx = pool.ctx.load();
+    let PoolRuntime {
+        oracle_pool,
+        datapoint_source,
+        repost_requested_epoch,
+        pool_control,
+        events,
+        snapshot_cache,
+        metrics,
+        notifier,
+        pending_txs,
+        chain_observer,
+        seen_boxes,
+        oracle_stats_tracker,
+        report_storage,
+        rescan_started,
+        ..
+    } = pool;
+    let wallet_state = wallet_monitor
+        .ensure_unlocked(
+            &ctx.node_api,
+            ctx.oracle_config.node_sync.max_wallet_lag_blocks,
+        )
+        .context("Failed to get the wallet status")?;
+    if wallet_state.unlocked {
+        notifier.resolve(AlertKind::WalletLocked);
+    } else {
+        notifier.alert(Alert::WalletLocked);
+    }
+    let node_sync_status = check_node_sync(&ctx.node_api, &ctx.oracle_config.node_sync)
+        .context("Failed to get the node sync status")?;
+    // all the sources of the iteration read the same boxes, taken at a single height. Failing that,
+    // the boxes are read from the node as needed like before.
+    let oracle_pool = &match oracle_pool.snapshot(ctx.node_api.as_ref()) {
+        Ok(pool_snapshot) => Arc::new(pool_snapshot),
+        Err(e) => {
+            log::warn!("Failed to read the pool boxes at a single height: {}", e);
+            oracle_pool.clone()
+        }
+    };
+    // no action is taken in read-only mode, so the time since the last one is not checked
+    let secs_since_last_action =
+        (!read_only).then(|| report_storage.read().unwrap().secs_since_last_action());
+    // the snapshot only feeds the REST API, the metrics and the alerts, the pool actions go on
+    // without it
+    let snapshot = match PoolSnapshot::take(
+        &ctx,
+        oracle_pool.clone(),
+        &node_sync_status,
+        &wallet_state,
+        secs_since_last_action,
+    ) {
+        Ok(snapshot) => Some(snapshot_cache.store(snapshot)),
+        Err(e) => {
+            log::error!("Failed to take the pool snapshot: {:?}", e);
+            None
+        }
+    };
+    update_node_metrics(&node_sync_status, &wallet_state);
+    if let Some(snapshot) = &snapshot {
+        if let Some(fee_runway) = &snapshot.fee_runway {
+            if fee_runway.status != FeeRunwayStatus::Ok {
+                log::warn!(
+                    "Wallet balance is {:?}: {} nanoERG spendable, enough for {} epochs",
+                    fee_runway.status,
+                    fee_runway.spendable_nano_erg,
+                    fee_runway.epochs_left
+                );
+            }
+        }
+        seen_boxes.record(snapshot.pool_box_id, snapshot.local_box_id, snapshot.height);
+        metrics.update(snapshot);
+        notifier.pool_health(snapshot.pool_health.status);
+        if let Some(oracle_health) = &snapshot.oracle_health {
+            notifier.oracle_health(oracle_health.status);
+        }
+        notifier.pool_reward_tokens(snapshot.pool_box_reward_tokens);
+        chain_observer.health(
+            &snapshot.pool_health,
+            snapshot.oracle_health.as_ref(),
+            events,
+        );
+        if let Err(e) = chain_observer.observe(
+            snapshot,
+            oracle_pool.get_ballot_boxes_source(),
+            oracle_pool.get_update_box_source(),
+            events,
+        ) {
+            log::warn!("Failed to observe the pool boxes for events: {:?}", e);
+        }
+        if let Err(e) = oracle_stats_tracker.observe(&ctx, snapshot, metrics) {
+            log::warn!("Failed to update the oracle stats: {:?}", e);
+        }
+    }
+    if std::mem::take(rescan_started) {
+        log::warn!("Scans were registered again, skipping pool actions until the node wallet has rescanned");
+    } else if !wallet_state.is_ready() {
+        log::warn!(
+            "Wallet is not ready ({}), skipping pool actions",
+            wallet_state.reason.clone().unwrap_or_default()
+        );
+    } else if node_sync_status.is_synced {
+        let height = match (&snapshot, oracle_pool.snapshot_block()) {
+            (Some(snapshot), _) => snapshot.height,
+            (None, Some(block)) => block.height,
+            (None, None) => BlockHeight(
+                ctx.node_api
+                    .node
+                    .current_block_height()
+                    .context("Failed to get the current height")? as u32,
+            ),
+        };
+        let pending_tx_update =
+            pending_txs.update(ctx.node_api.as_ref(), height, notifier.stuck_tx_blocks())?;
+        for tx in pending_tx_update.confirmed {
+            if tx.kind == PendingTxKind::Refresh {
+                events.publish(OracleEvent::RefreshSucceeded {
+                    tx_id: tx.tx_id.to_string(),
+                    height,
+                });
+            }
+        }
+        for tx in pending_tx_update.dropped {
+            log::warn!("Tx {} was dropped by the node", tx.tx_id);
+            if tx.kind == PendingTxKind::Refresh {
+                events.publish(OracleEvent::RefreshFailed {
+                    height,
+                    reason: format!("tx {} was dropped by the node", tx.tx_id),
+                    consensus: None,
+                });
+            }
+        }
+        for stuck_tx in pending_tx_update.stuck {
+            let blocks = height.0.saturating_sub(stuck_tx.submitted_height.0);
+            log::warn!(
+                "Tx {} is still unconfirmed after {blocks} blocks",
+                stuck_tx.tx_id
+            );
+            notifier.alert(Alert::StuckTx {
+                tx_id: stuck_tx.tx_id,
+                blocks,
+            });
+        }
+        let pool_state = &match &snapshot {
+            Some(snapshot) => snapshot.pool_state.clone(),
+            None => match oracle_pool.get_live_epoch_state() {
+                Ok(live_epoch_state) => PoolState::LiveEpoch(live_epoch_state),
+                Err(error) => {
+                    log::error!("error getting live epoch state: {:?}", error);
+                    PoolState::NeedsBootstrap
+                }
+            },
+        };
+        let epoch_length = oracle_pool
+            .pool_config()
+            .refresh_box_wrapper_inputs
+            .contract_inputs
+            .contract_parameters()
+            .epoch_length();
+        let cmd = process(pool_state.clone(), epoch_length, height);
+        if let (
+            Some(PoolCommand::PublishSubsequentDataPoint { .. }),
+            PoolState::LiveEpoch(live_epoch),
+        ) = (&cmd, pool_state)
+        {
+            // a push-only connector needs to know a datapoint for the new epoch is due, it is told
+            // once per epoch
+            if *repost_requested_epoch != Some(live_epoch.pool_box_epoch_id) {
+                *repost_requested_epoch = Some(live_epoch.pool_box_epoch_id);
+                datapoint_source.request_repost();
+            }
+        }
+        if let Some(cmd) = pool_control.apply(cmd, pool_state) {
+            log::debug!("Height {height}. Building action for command: {:?}", cmd);
+            let is_refresh = matches!(cmd, PoolCommand::Refresh);
+            if is_refresh {
+                events.publish(OracleEvent::RefreshAttempted { height });
+            }
+            let build_action_tuple_res = build_action(
+                cmd,
+                &ctx,
+                oracle_pool,
+                ctx.node_api.as_ref(),
+                height,
+                change_address.address(),
+                datapoint_source,
+            );
+            if let Some((action, report)) = log_and_continue_if_non_fatal(
+                change_address.network(),
+                build_action_tuple_res,
+                height,
+                notifier,
+                events,
+            )? {
+                if !read_only {
+                    let tx_id = match execute_action(action, &ctx) {
+                        Ok(tx_id) => tx_id,
+                        Err(e) => {
+                            if is_refresh {
+                                events.publish(OracleEvent::RefreshFailed {
+                                    height,
+                                    reason: e.to_string(),
+                                    consensus: None,
+                                });
+                            }
+                            return Err(e);
+                        }
+                    };
+                    let kind = if is_refresh {
+                        PendingTxKind::Refresh
+                    } else {
+                        PendingTxKind::PublishDatapoint
+                    };
+                    match tx_id {
+                        Some(tx_id) => {
+                            if is_refresh {
+                                events.publish(OracleEvent::RefreshSubmitted {
+                                    tx_id: tx_id.to_string(),
+                                    height,
+                                });
+                            }
+                            pending_txs.add(tx_id, kind, height);
+                        }
+                        None if is_refresh => events.publish(OracleEvent::RefreshFailed {
+                            height,
+                            reason: "tx rejected by the node, its inputs are already spent"
+                                .to_string(),
+                            consensus: None,
+                        }),
+                        None => (),
+                    }
+                    report_storage.write().unwrap().add(report);
+                }
+            };
+        }
+    } else {
+        log::warn!(
+            "Node is not synced ({}), skipping pool actions",
+            node_sync_status.reason.clone().unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+fn log_and_continue_if_non_fatal(
+    network_prefix: NetworkPrefix,
+    res: Result<(PoolAction, PoolActionReport), PoolCommandError>,
+    height: BlockHeight,
+    notifier: &mut Notifier,
+    events: &EventBus,
+) -> Result<Option<(PoolAction, PoolActionReport)>, anyhow::Error> {
+    match res {
+        Ok(tuple) => {
+            match tuple.0 {
+                PoolAction::Refresh(_) => notifier.resolve(AlertKind::ConsensusFailure),
+                PoolAction::PublishDatapoint(_) => {
+                    notifier.resolve(AlertKind::DatapointSourceFailure)
+                }
+            }
+            Ok(Some(tuple))
+        }
+        Err(PoolCommandError::RefreshActionError(RefreshActionError::FailedToReachConsensus {
+            expected,
+            found_public_keys,
+            found_num,
+        })) => {
+            let found_addresses: Vec<String> =
+                pks_to_network_addresses(found_public_keys, network_prefix)
+                    .into_iter()
+                    .map(|net_addr| net_addr.to_base58())
+                    .collect();
+            let found_oracle_addresses = found_addresses.join(", ");
+            events.publish(OracleEvent::RefreshFailed {
+                height,
+                reason: "not enough datapoints within the deviation range".to_string(),
+                consensus: Some(ConsensusFailure {
+                    expected,
+                    found_num,
+                    found_oracle_addresses: found_addresses,
+                }),
+            });
+            log::error!("Refresh failed, not enough datapoints. The minimum number of datapoints within the deviation range: required minumum {expected}, found {found_num} from addresses {found_oracle_addresses},");
+            notifier.alert(Alert::ConsensusFailure {
+                expected,
+                found_num,
+                found_oracle_addresses,
+            });
+            Ok(None)
+        }
+        Err(PoolCommandError::PublishDatapointActionError(
+            PublishDatapointActionError::DataPointSource(e),
+        )) => {
+            log::error!("Failed to get datapoint with error: {}", e);
+            notifier.alert(Alert::DatapointSourceFailure(e.to_string()));
+            Ok(None)
+        }
+        Err(PoolCommandError::RefreshActionError(e)) => {
+            events.publish(OracleEvent::RefreshFailed {
+                height,
+                reason: e.to_string(),
+                consensus: None,
+            });
+            Err(PoolCommandError::RefreshActionError(e).into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn log_on_launch(oracle_config: Option<&OracleConfig>) {
+    log::info!("{}", APP_VERSION);
+    if let Some(oracle_config) = oracle_config {
+        let oracle_address = &oracle_config.oracle_address;
+        // log::info!("Token ids: {:?}", config.token_ids);
+        log::info!("Oracle address: {}", oracle_address.to_base58());
+    }
+}
+
+fn check_reward_token_opt(
+    reward_token_id_str: Option<String>,
+    reward_token_amount: Option<u64>,
+) -> Option<SpecToken<RewardTokenId>> {
+    match (reward_token_id_str, reward_token_amount) {
+        (None, None) => None,
+        (None, Some(_)) => {
+            panic!("reward_token_amount is set, but reward_token_id is not set")
+        }
+        (Some(_), None) => {
+            panic!("reward_token_id is set, but reward_token_amount is not set")
+        }
+        (Some(reward_token_id_str), Some(reward_token_amount)) => Some({
+            let reward_token_id: TokenId = Digest32::try_from(reward_token_id_str).unwrap().into();
+            SpecToken {
+                token_id: RewardTokenId::from_token_id_unchecked(reward_token_id),
+                amount: TokenAmount::try_from(reward_token_amount).unwrap(),
+            }
+        }),
+    }
+}
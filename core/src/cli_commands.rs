@@ -1,8 +1,13 @@
 pub mod bootstrap;
+pub mod doctor;
 pub mod extract_reward_tokens;
 pub mod import_pool_update;
+pub mod oracle_stats;
+pub mod pool_history;
 pub mod prepare_update;
+pub mod preview_refresh;
 pub mod print_reward_tokens;
 pub mod transfer_oracle_token;
 pub mod update_pool;
 pub mod vote_update_pool;
+pub mod wallet_status;
@@ -1,5 +1,5 @@
 //! Bootstrap a new oracle pool
-use std::{convert::TryInto, io::Write, path::Path};
+use std::{convert::TryInto, io::Write, path::Path, path::PathBuf};
 
 use ergo_lib::{
     chain::{
@@ -44,9 +44,10 @@ use crate::{
     explorer_api::wait_for_txs_confirmation,
     node_interface::{
         node_api::{NodeApi, NodeApiError},
-        try_ensure_wallet_unlocked, SignTransactionWithInputs, SubmitTransaction,
+        try_ensure_wallet_unlocked, GetBoxStatus, GetTxStatus, SignTransactionWithInputs,
+        SubmitTransaction,
     },
-    oracle_config::{BASE_FEE, ORACLE_CONFIG, ORACLE_SECRETS},
+    oracle_config::OracleConfig,
     oracle_types::{BlockHeight, EpochCounter},
     pool_config::{
         PoolConfig, PoolConfigError, PredefinedDataPointSource, TokenIds,
@@ -60,44 +61,100 @@ use crate::{
     wallet::{WalletDataError, WalletDataSource},
 };
 
+pub mod journal;
+
+use journal::{BootstrapJournal, BootstrapStep, BOOTSTRAP_JOURNAL_FILE_NAME};
+
 /// Loads bootstrap configuration file and performs the chain-transactions for minting of tokens and
 /// box creations. An oracle configuration file is then created which contains the `TokenId`s of the
 /// minted tokens.
-pub fn bootstrap(config_file_name: String) -> Result<(), anyhow::Error> {
-    let oracle_config = &ORACLE_CONFIG;
+/// There is no pool config yet, so the oracle config and node client are passed directly instead of
+/// an `OracleContext`.
+/// The txs are recorded in a journal in `data_dir`. With `resume` the txs of the journal that are
+/// not in a block or in the mempool are submitted again instead of planning new ones. If the node
+/// rejects a tx whose inputs are spent meanwhile, the journal cannot be resumed and the error says
+/// so.
+pub fn bootstrap(
+    config_file_name: String,
+    oracle_config: &OracleConfig,
+    node_api: &NodeApi,
+    tx_fee: BoxValue,
+    data_dir: &Path,
+    resume: bool,
+) -> Result<(), anyhow::Error> {
     let s = std::fs::read_to_string(config_file_name)?;
     let config: BootstrapConfig = serde_yaml::from_str(&s)?;
+    let journal_path = data_dir.join(BOOTSTRAP_JOURNAL_FILE_NAME);
 
-    let node_api = NodeApi::new(
-        ORACLE_SECRETS.node_api_key.clone(),
-        ORACLE_SECRETS.wallet_password.clone(),
-        &oracle_config.node_url,
-    );
-    try_ensure_wallet_unlocked(&node_api);
-    let change_address = node_api.get_change_address()?;
-    debug!("Change address: {:?}", change_address);
-    let erg_value_per_box = config.oracle_contract_parameters.min_storage_rent;
-    let input = BootstrapInput {
-        oracle_address: oracle_config.oracle_address.clone(),
-        config,
-        wallet: &node_api as &dyn WalletDataSource,
-        tx_signer: &node_api.node as &dyn SignTransactionWithInputs,
-        submit_tx: &node_api.node as &dyn SubmitTransaction,
-        tx_fee: *BASE_FEE,
-        erg_value_per_box,
-        change_address: change_address.address(),
-        height: BlockHeight(node_api.node.current_block_height()? as u32),
+    try_ensure_wallet_unlocked(node_api);
+    let mut journal = if resume {
+        let mut journal = BootstrapJournal::load(&journal_path)?;
+        info!("Resuming the bootstrap of {}", journal_path.display());
+        journal.refresh(node_api as &dyn GetTxStatus)?;
+        journal
+    } else {
+        BootstrapJournal::check_no_unfinished(&journal_path)?;
+        let change_address = node_api.get_change_address()?;
+        debug!("Change address: {:?}", change_address);
+        let erg_value_per_box = config.oracle_contract_parameters.min_storage_rent;
+        let input = BootstrapInput {
+            oracle_address: oracle_config.oracle_address.clone(),
+            config: config.clone(),
+            wallet: node_api as &dyn WalletDataSource,
+            tx_signer: &node_api.node as &dyn SignTransactionWithInputs,
+            submit_tx: &node_api.node as &dyn SubmitTransaction,
+            tx_fee,
+            erg_value_per_box,
+            change_address: change_address.address(),
+            height: BlockHeight(node_api.node.current_block_height()? as u32),
+        };
+        let journal = plan_bootstrap_chained_transaction(input)?.with_path(journal_path.clone());
+        journal.save()?;
+        info!(
+            "Bootstrap txs planned, journal saved to {}",
+            journal_path.display()
+        );
+        journal
     };
-    let (oracle_config, submitted_tx_ids) = perform_bootstrap_chained_transaction(input)?;
-    info!("Bootstrap chain-transaction complete");
-    let s = serde_yaml::to_string(&oracle_config)?;
+    let submitted = journal.submit(&node_api.node as &dyn SubmitTransaction);
+    if let Err(e) = &submitted {
+        log::error!(
+            "Bootstrap stopped: {}. Tokens minted so far: {:?}",
+            e,
+            journal.minted_tokens()
+        );
+        // resuming would submit the same rejected tx again
+        if let Some(step) = journal.first_stale_step(node_api as &dyn GetBoxStatus)? {
+            return Err(BootstrapError::StaleJournal {
+                step,
+                journal: journal_path,
+            }
+            .into());
+        }
+        if !journal.tokens_minted() {
+            return Err(anyhow::anyhow!(
+                "{e}, run `bootstrap --resume` to continue from the journal {}",
+                journal_path.display()
+            ));
+        }
+    }
+    let pool_config = PoolConfig::create(config, journal.token_ids.clone())?;
+    let s = serde_yaml::to_string(&pool_config)?;
     let mut file = std::fs::File::create(DEFAULT_POOL_CONFIG_FILE_NAME)?;
     file.write_all(s.as_bytes())?;
     info!(
         "Pool configuration file created: {}",
         DEFAULT_POOL_CONFIG_FILE_NAME
     );
-    wait_for_txs_confirmation(submitted_tx_ids);
+    if let Err(e) = submitted {
+        return Err(anyhow::anyhow!(
+            "all tokens are minted and the pool config is written, but {e}. Run `bootstrap --resume` to create the missing boxes from the journal {}",
+            journal_path.display()
+        ));
+    }
+    info!("Bootstrap chain-transaction complete");
+    journal.complete()?;
+    wait_for_txs_confirmation(oracle_config, journal.unconfirmed_tx_ids());
     Ok(())
 }
 
@@ -137,12 +194,26 @@ pub struct BootstrapInput<'a> {
 pub(crate) fn perform_bootstrap_chained_transaction(
     input: BootstrapInput,
 ) -> Result<(PoolConfig, Vec<TxId>), BootstrapError> {
+    let config = input.config.clone();
+    let submit_tx = input.submit_tx;
+    let mut journal = plan_bootstrap_chained_transaction(input)?;
+    journal.submit(submit_tx)?;
+    info!("Minted tokens: {:?}", journal.token_ids);
+    Ok((
+        PoolConfig::create(config, journal.token_ids.clone())?,
+        journal.unconfirmed_tx_ids(),
+    ))
+}
+
+/// Builds and signs the txs of the bootstrap chained-transaction, without submitting them
+pub(crate) fn plan_bootstrap_chained_transaction(
+    input: BootstrapInput,
+) -> Result<BootstrapJournal, BootstrapError> {
     let BootstrapInput {
         oracle_address,
         config,
         wallet,
         tx_signer: wallet_sign,
-        submit_tx,
         tx_fee,
         erg_value_per_box,
         change_address,
@@ -515,36 +586,28 @@ pub(crate) fn perform_bootstrap_chained_transaction(
     let signed_refresh_box_tx =
         wallet_sign.sign_transaction_with_inputs(&refresh_box_tx, inputs, None)?;
 
-    // ---------------------------------------------------------------------------------------------
-    let mut submitted_tx_ids = vec![];
-    let tx_id = submit_tx.submit_transaction(&signed_mint_pool_nft_tx)?;
-    submitted_tx_ids.push(signed_mint_pool_nft_tx.id());
-    info!("Minted pool NFT TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_refresh_nft_tx)?;
-    submitted_tx_ids.push(signed_mint_refresh_nft_tx.id());
-    info!("Minted refresh NFT TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_ballot_tokens_tx)?;
-    submitted_tx_ids.push(signed_mint_ballot_tokens_tx.id());
-    info!("Minted ballot tokens TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_update_nft_tx)?;
-    submitted_tx_ids.push(signed_mint_update_nft_tx.id());
-    info!("Minted update NFT TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_oracle_tokens_tx)?;
-    submitted_tx_ids.push(signed_mint_oracle_tokens_tx.id());
-    info!("Minted oracle tokens TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_reward_tokens_tx)?;
-    submitted_tx_ids.push(signed_mint_reward_tokens_tx.id());
-    info!("Minted reward tokens TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_pool_box_tx)?;
-    submitted_tx_ids.push(signed_pool_box_tx.id());
-    info!("Created initial pool box TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_refresh_box_tx)?;
-    submitted_tx_ids.push(signed_refresh_box_tx.id());
-    info!("Created initial refresh box TxId: {}", tx_id);
-
-    info!("Minted tokens: {:?}", token_ids);
-
-    Ok((PoolConfig::create(config, token_ids)?, submitted_tx_ids))
+    Ok(BootstrapJournal::new(
+        token_ids,
+        vec![
+            (BootstrapStep::MintPoolNft, signed_mint_pool_nft_tx),
+            (BootstrapStep::MintRefreshNft, signed_mint_refresh_nft_tx),
+            (
+                BootstrapStep::MintBallotTokens,
+                signed_mint_ballot_tokens_tx,
+            ),
+            (BootstrapStep::MintUpdateNft, signed_mint_update_nft_tx),
+            (
+                BootstrapStep::MintOracleTokens,
+                signed_mint_oracle_tokens_tx,
+            ),
+            (
+                BootstrapStep::MintRewardTokens,
+                signed_mint_reward_tokens_tx,
+            ),
+            (BootstrapStep::CreatePoolBox, signed_pool_box_tx),
+            (BootstrapStep::CreateRefreshBox, signed_refresh_box_tx),
+        ],
+    ))
 }
 
 /// An instance of this struct is created from an operator-provided YAML file.
@@ -665,6 +728,22 @@ pub enum BootstrapError {
     PoolContractError(#[from] PoolContractError),
     #[error("WalletData error: {0}")]
     WalletData(#[from] WalletDataError),
+    #[error("bootstrap journal error: {0}")]
+    Journal(#[from] serde_json::Error),
+    #[error("no bootstrap journal found at {0}, nothing to resume")]
+    NoJournal(PathBuf),
+    #[error("a previous bootstrap did not finish (journal {0}), run `bootstrap --resume` to continue it")]
+    UnfinishedBootstrap(PathBuf),
+    #[error("failed to submit the {step} tx: {source}")]
+    SubmitFailed {
+        step: BootstrapStep,
+        source: NodeError,
+    },
+    #[error("the {step} tx of the journal {journal} spends a box that is already spent, this bootstrap cannot be resumed. Move the journal away and run `bootstrap` again, the tokens minted so far will not be used")]
+    StaleJournal {
+        step: BootstrapStep,
+        journal: PathBuf,
+    },
 }
 
 #[cfg(test)]
@@ -674,18 +753,23 @@ pub(crate) mod tests {
         ergotree_interpreter::sigma_protocol::private_input::DlogProverInput,
         ergotree_ir::chain::{
             address::{AddressEncoder, NetworkAddress, NetworkPrefix},
-            ergo_box::{ErgoBox, NonMandatoryRegisters},
+            ergo_box::{BoxId, ErgoBox, NonMandatoryRegisters},
         },
-        wallet::Wallet,
+        wallet::{tx_builder::SUGGESTED_TX_FEE, Wallet},
     };
     use sigma_test_util::force_any_val;
 
+    use super::journal::StepStatus;
     use super::*;
+    use crate::node_interface::TxStatus;
     use crate::pool_commands::test_utils::{LocalTxSigner, WalletDataMock};
     use std::cell::RefCell;
+
     #[derive(Default)]
     pub(crate) struct SubmitTxMock {
         transactions: RefCell<Vec<ergo_lib::chain::transaction::Transaction>>,
+        /// Rejects the txs once this number of txs is submitted
+        fail_after: Option<usize>,
     }
 
     impl SubmitTransaction for SubmitTxMock {
@@ -693,11 +777,204 @@ pub(crate) mod tests {
             &self,
             tx: &ergo_lib::chain::transaction::Transaction,
         ) -> crate::node_interface::Result<TxId> {
+            if self.fail_after == Some(self.transactions.borrow().len()) {
+                return Err(NodeError::BadRequest("tx rejected".to_string()));
+            }
             self.transactions.borrow_mut().push(tx.clone());
             Ok(tx.id())
         }
     }
 
+    /// Node knowing the status of the given txs only
+    struct TxStatusMock(Vec<(TxId, TxStatus)>);
+
+    impl GetTxStatus for TxStatusMock {
+        fn get_tx_status(&self, tx_id: &TxId) -> Result<TxStatus, NodeApiError> {
+            Ok(self
+                .0
+                .iter()
+                .find(|(id, _)| id == tx_id)
+                .map(|(_, status)| *status)
+                .unwrap_or(TxStatus::Unknown))
+        }
+    }
+
+    /// Node knowing the given unspent boxes only
+    struct BoxStatusMock(Vec<BoxId>);
+
+    impl GetBoxStatus for BoxStatusMock {
+        fn is_box_unspent(&self, box_id: &BoxId) -> Result<bool, NodeApiError> {
+            Ok(self.0.contains(box_id))
+        }
+    }
+
+    fn planned_journal() -> BootstrapJournal {
+        let ctx = force_any_val::<ErgoStateContext>();
+        let height = ctx.pre_header.height;
+        let secret = force_any_val::<DlogProverInput>();
+        let address = NetworkAddress::new(
+            NetworkPrefix::Mainnet,
+            &Address::P2Pk(secret.public_image()),
+        );
+        let wallet = Wallet::from_secrets(vec![secret.into()]);
+        let unspent_boxes = vec![ErgoBox::new(
+            SUGGESTED_TX_FEE().checked_mul_u32(10000).unwrap(),
+            address.address().script().unwrap(),
+            None,
+            NonMandatoryRegisters::empty(),
+            height - 9,
+            force_any_val::<TxId>(),
+            0,
+        )
+        .unwrap()];
+        plan_bootstrap_chained_transaction(BootstrapInput {
+            oracle_address: address.clone(),
+            config: BootstrapConfig::default(),
+            wallet: &WalletDataMock {
+                unspent_boxes,
+                change_address: address.clone(),
+            },
+            tx_signer: &LocalTxSigner {
+                ctx: &ctx,
+                wallet: &wallet,
+            },
+            submit_tx: &SubmitTxMock::default(),
+            tx_fee: SUGGESTED_TX_FEE(),
+            erg_value_per_box: SUGGESTED_TX_FEE(),
+            change_address: address.address(),
+            height: BlockHeight(height),
+        })
+        .unwrap()
+    }
+
+    fn statuses(journal: &BootstrapJournal) -> Vec<StepStatus> {
+        journal.steps.iter().map(|s| s.status).collect()
+    }
+
+    #[test]
+    fn bootstrap_resumes_from_journal() {
+        let mut journal = planned_journal();
+        let submit_tx = SubmitTxMock {
+            fail_after: Some(2),
+            ..Default::default()
+        };
+        assert!(matches!(
+            journal.submit(&submit_tx),
+            Err(BootstrapError::SubmitFailed {
+                step: BootstrapStep::MintBallotTokens,
+                ..
+            })
+        ));
+        assert!(!journal.tokens_minted());
+        assert_eq!(
+            journal.minted_tokens(),
+            vec![
+                (
+                    BootstrapStep::MintPoolNft,
+                    journal.token_ids.pool_nft_token_id.token_id()
+                ),
+                (
+                    BootstrapStep::MintRefreshNft,
+                    journal.token_ids.refresh_nft_token_id.token_id()
+                ),
+            ]
+        );
+        // restart
+        let loaded: BootstrapJournal =
+            serde_json::from_str(&serde_json::to_string(&journal).unwrap()).unwrap();
+        assert_eq!(loaded, journal);
+        let mut journal = loaded;
+        // the first tx is confirmed meanwhile and the second one was dropped from the mempool
+        journal
+            .refresh(&TxStatusMock(vec![(
+                journal.steps[0].tx_id,
+                TxStatus::Confirmed,
+            )]))
+            .unwrap();
+        assert_eq!(
+            statuses(&journal)[..3],
+            [
+                StepStatus::Confirmed,
+                StepStatus::Planned,
+                StepStatus::Planned
+            ]
+        );
+        let submit_tx = SubmitTxMock::default();
+        journal.submit(&submit_tx).unwrap();
+        assert!(journal.all_submitted());
+        let submitted: Vec<TxId> = submit_tx
+            .transactions
+            .borrow()
+            .iter()
+            .map(|tx| tx.id())
+            .collect();
+        let expected: Vec<TxId> = journal.steps[1..].iter().map(|s| s.tx_id).collect();
+        assert_eq!(submitted, expected);
+        assert_eq!(journal.unconfirmed_tx_ids(), expected);
+    }
+
+    #[test]
+    fn resume_after_rejected_tx_with_spent_inputs() {
+        let mut journal = planned_journal();
+        let submit_tx = SubmitTxMock {
+            fail_after: Some(1),
+            ..Default::default()
+        };
+        assert!(journal.submit(&submit_tx).is_err());
+        // the node rejected the second tx, its input (an output of the first tx) is still
+        // unspent, resuming submits it again
+        let inputs: Vec<BoxId> = journal.steps[1]
+            .tx
+            .inputs
+            .iter()
+            .map(|input| input.box_id)
+            .collect();
+        assert_eq!(
+            journal.first_stale_step(&BoxStatusMock(inputs)).unwrap(),
+            None
+        );
+        // meanwhile the first tx is confirmed and the wallet spent its change box
+        journal
+            .refresh(&TxStatusMock(vec![(
+                journal.steps[0].tx_id,
+                TxStatus::Confirmed,
+            )]))
+            .unwrap();
+        assert_eq!(
+            journal.first_stale_step(&BoxStatusMock(vec![])).unwrap(),
+            Some(BootstrapStep::MintRefreshNft)
+        );
+        // nothing left to submit
+        let mut journal = planned_journal();
+        let submit_tx = SubmitTxMock::default();
+        journal.submit(&submit_tx).unwrap();
+        assert_eq!(
+            journal.first_stale_step(&BoxStatusMock(vec![])).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn pool_config_from_minted_tokens() {
+        let mut journal = planned_journal();
+        let submit_tx = SubmitTxMock {
+            fail_after: Some(6),
+            ..Default::default()
+        };
+        assert!(matches!(
+            journal.submit(&submit_tx),
+            Err(BootstrapError::SubmitFailed {
+                step: BootstrapStep::CreatePoolBox,
+                ..
+            })
+        ));
+        assert!(journal.tokens_minted());
+        assert!(!journal.all_submitted());
+        let pool_config =
+            PoolConfig::create(BootstrapConfig::default(), journal.token_ids.clone()).unwrap();
+        assert_eq!(pool_config.token_ids, journal.token_ids);
+    }
+
     #[test]
     fn test_bootstrap() {
         let ctx = force_any_val::<ErgoStateContext>();
@@ -710,7 +987,7 @@ pub(crate) mod tests {
         let wallet = Wallet::from_secrets(vec![secret.clone().into()]);
         let ergo_tree = address.address().script().unwrap();
 
-        let value = BASE_FEE.checked_mul_u32(10000).unwrap();
+        let value = SUGGESTED_TX_FEE().checked_mul_u32(10000).unwrap();
         let unspent_boxes = vec![ErgoBox::new(
             value,
             ergo_tree.clone(),
@@ -742,8 +1019,8 @@ pub(crate) mod tests {
                 wallet: &wallet,
             },
             submit_tx: &submit_tx,
-            tx_fee: *BASE_FEE,
-            erg_value_per_box: *BASE_FEE,
+            tx_fee: SUGGESTED_TX_FEE(),
+            erg_value_per_box: SUGGESTED_TX_FEE(),
             change_address: change_address.address(),
             height,
         })
@@ -0,0 +1,248 @@
+//! Journal of the bootstrap chain-transaction. The signed txs are planned all at once, so the
+//! journal knows the minted token IDs before anything is submitted. It is kept in the data folder
+//! and updated after every submitted tx, so that a failed bootstrap can be continued with
+//! `bootstrap --resume`.
+use std::path::Path;
+use std::path::PathBuf;
+
+use ergo_lib::chain::transaction::Transaction;
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use log::info;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::BootstrapError;
+use crate::node_interface::GetBoxStatus;
+use crate::node_interface::GetTxStatus;
+use crate::node_interface::SubmitTransaction;
+use crate::node_interface::TxStatus;
+use crate::pool_config::TokenIds;
+use crate::spec_token::TokenIdKind;
+
+pub const BOOTSTRAP_JOURNAL_FILE_NAME: &str = "bootstrap_journal.json";
+
+/// Txs of the bootstrap chain-transaction, in submission order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapStep {
+    MintPoolNft,
+    MintRefreshNft,
+    MintBallotTokens,
+    MintUpdateNft,
+    MintOracleTokens,
+    MintRewardTokens,
+    CreatePoolBox,
+    CreateRefreshBox,
+}
+
+impl BootstrapStep {
+    /// Token minted by the step, `None` for the pool and refresh box txs
+    pub fn minted_token_id(self, token_ids: &TokenIds) -> Option<TokenId> {
+        match self {
+            BootstrapStep::MintPoolNft => Some(token_ids.pool_nft_token_id.token_id()),
+            BootstrapStep::MintRefreshNft => Some(token_ids.refresh_nft_token_id.token_id()),
+            BootstrapStep::MintBallotTokens => Some(token_ids.ballot_token_id.token_id()),
+            BootstrapStep::MintUpdateNft => Some(token_ids.update_nft_token_id.token_id()),
+            BootstrapStep::MintOracleTokens => Some(token_ids.oracle_token_id.token_id()),
+            BootstrapStep::MintRewardTokens => Some(token_ids.reward_token_id.token_id()),
+            BootstrapStep::CreatePoolBox | BootstrapStep::CreateRefreshBox => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BootstrapStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            BootstrapStep::MintPoolNft => "mint pool NFT",
+            BootstrapStep::MintRefreshNft => "mint refresh NFT",
+            BootstrapStep::MintBallotTokens => "mint ballot tokens",
+            BootstrapStep::MintUpdateNft => "mint update NFT",
+            BootstrapStep::MintOracleTokens => "mint oracle tokens",
+            BootstrapStep::MintRewardTokens => "mint reward tokens",
+            BootstrapStep::CreatePoolBox => "create pool box",
+            BootstrapStep::CreateRefreshBox => "create refresh box",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Signed but not submitted (or dropped by the node since)
+    Planned,
+    /// In the mempool
+    Submitted,
+    Confirmed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalStep {
+    pub step: BootstrapStep,
+    pub tx_id: TxId,
+    pub status: StepStatus,
+    pub tx: Transaction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BootstrapJournal {
+    /// File the journal is saved to after every change, not saved if `None`
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    pub token_ids: TokenIds,
+    pub steps: Vec<JournalStep>,
+    /// Set once the pool config file is written
+    #[serde(default)]
+    pub completed: bool,
+}
+
+impl BootstrapJournal {
+    /// Journal of the planned (signed, not submitted) txs
+    pub fn new(token_ids: TokenIds, txs: Vec<(BootstrapStep, Transaction)>) -> Self {
+        Self {
+            path: None,
+            token_ids,
+            steps: txs
+                .into_iter()
+                .map(|(step, tx)| JournalStep {
+                    step,
+                    tx_id: tx.id(),
+                    status: StepStatus::Planned,
+                    tx,
+                })
+                .collect(),
+            completed: false,
+        }
+    }
+
+    /// Saves the journal to `path` from now on
+    pub fn with_path(self, path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..self
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, BootstrapError> {
+        if !path.exists() {
+            return Err(BootstrapError::NoJournal(path.to_path_buf()));
+        }
+        let journal: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(journal.with_path(path.to_path_buf()))
+    }
+
+    /// Fails if the journal at `path` is of a bootstrap that did not finish, so that the tokens
+    /// are not minted twice
+    pub fn check_no_unfinished(path: &Path) -> Result<(), BootstrapError> {
+        if path.exists() && !Self::load(path)?.completed {
+            return Err(BootstrapError::UnfinishedBootstrap(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), BootstrapError> {
+        if let Some(path) = &self.path {
+            std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+
+    fn set_status(&mut self, index: usize, status: StepStatus) -> Result<(), BootstrapError> {
+        if self.steps[index].status != status {
+            self.steps[index].status = status;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Marks the bootstrap as finished
+    pub fn complete(&mut self) -> Result<(), BootstrapError> {
+        self.completed = true;
+        self.save()
+    }
+
+    /// Whether every tx is in the mempool or in a block
+    pub fn all_submitted(&self) -> bool {
+        self.steps.iter().all(|s| s.status != StepStatus::Planned)
+    }
+
+    /// Whether every token minting tx is in the mempool or in a block, so that the pool config can
+    /// be written even if the pool or refresh box was not created
+    pub fn tokens_minted(&self) -> bool {
+        self.steps
+            .iter()
+            .filter(|s| s.step.minted_token_id(&self.token_ids).is_some())
+            .all(|s| s.status != StepStatus::Planned)
+    }
+
+    /// Tokens of the submitted minting txs
+    pub fn minted_tokens(&self) -> Vec<(BootstrapStep, TokenId)> {
+        self.steps
+            .iter()
+            .filter(|s| s.status != StepStatus::Planned)
+            .filter_map(|s| Some((s.step, s.step.minted_token_id(&self.token_ids)?)))
+            .collect()
+    }
+
+    /// Submitted txs that are not known to be confirmed
+    pub fn unconfirmed_tx_ids(&self) -> Vec<TxId> {
+        self.steps
+            .iter()
+            .filter(|s| s.status == StepStatus::Submitted)
+            .map(|s| s.tx_id)
+            .collect()
+    }
+
+    /// Updates the status of the not yet confirmed txs from the node. The txs the node does not
+    /// know (never submitted or dropped from the mempool) are planned for submission again.
+    pub fn refresh(&mut self, tx_status: &dyn GetTxStatus) -> Result<(), BootstrapError> {
+        for i in 0..self.steps.len() {
+            if self.steps[i].status == StepStatus::Confirmed {
+                continue;
+            }
+            let status = match tx_status.get_tx_status(&self.steps[i].tx_id)? {
+                TxStatus::Confirmed => StepStatus::Confirmed,
+                TxStatus::Unconfirmed => StepStatus::Submitted,
+                TxStatus::Unknown => StepStatus::Planned,
+            };
+            info!("{}: {:?}", self.steps[i].step, status);
+            self.set_status(i, status)?;
+        }
+        Ok(())
+    }
+
+    /// First planned tx with an input that is spent (e.g. the wallet spent a box of the chain, or a
+    /// dropped tx was double spent). The node can never accept it, nor the txs after it, which
+    /// spend its outputs.
+    pub fn first_stale_step(
+        &self,
+        box_status: &dyn GetBoxStatus,
+    ) -> Result<Option<BootstrapStep>, BootstrapError> {
+        let Some(step) = self.steps.iter().find(|s| s.status == StepStatus::Planned) else {
+            return Ok(None);
+        };
+        for input in step.tx.inputs.iter() {
+            if !box_status.is_box_unspent(&input.box_id)? {
+                return Ok(Some(step.step));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Submits the planned txs in order, stops on the first one rejected by the node
+    pub fn submit(&mut self, submit_tx: &dyn SubmitTransaction) -> Result<(), BootstrapError> {
+        for i in 0..self.steps.len() {
+            if self.steps[i].status != StepStatus::Planned {
+                continue;
+            }
+            let step = self.steps[i].step;
+            let tx_id = submit_tx
+                .submit_transaction(&self.steps[i].tx)
+                .map_err(|source| BootstrapError::SubmitFailed { step, source })?;
+            info!("{} TxId: {}", step, tx_id);
+            self.set_status(i, StepStatus::Submitted)?;
+        }
+        Ok(())
+    }
+}
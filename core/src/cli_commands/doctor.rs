@@ -0,0 +1,520 @@
+//! `doctor` command: checks the oracle setup step by step, from the config files to the
+//! datapoint source, and prints a hint for every failed check. A check is skipped when one it
+//! depends on has failed.
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+
+use crate::context::OracleContext;
+use crate::datapoint_source::DataPointSource;
+use crate::datapoint_source::RuntimeDataPointSource;
+use crate::node_interface::node_api::NodeApi;
+use crate::node_sync::check_node_sync;
+use crate::oracle_config::BoxSource;
+use crate::oracle_config::OracleConfig;
+use crate::oracle_config::OracleSecrets;
+use crate::oracle_state::OraclePool;
+use crate::pool_config::PoolConfig;
+use crate::public_boxes::PublicChainReader;
+use crate::scans::scan_problems;
+use crate::scans::NodeScanRegistry;
+use crate::spec_token::TokenIdKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+/// Prints the checks as they complete and counts the failures
+#[derive(Debug, Default)]
+pub struct DoctorReport {
+    failed: usize,
+}
+
+impl DoctorReport {
+    pub fn pass(&mut self, check: &str, detail: impl std::fmt::Display) {
+        self.print(CheckStatus::Pass, check, detail, None);
+    }
+
+    pub fn fail(&mut self, check: &str, detail: impl std::fmt::Display, hint: &str) {
+        self.failed += 1;
+        self.print(CheckStatus::Fail, check, detail, Some(hint));
+    }
+
+    pub fn skip(&mut self, check: &str, reason: &str) {
+        self.print(CheckStatus::Skip, check, reason, None);
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0
+    }
+
+    fn print(
+        &self,
+        status: CheckStatus,
+        check: &str,
+        detail: impl std::fmt::Display,
+        hint: Option<&str>,
+    ) {
+        let status = match status {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "SKIP",
+        };
+        println!("[{status}] {check}: {detail}");
+        if let Some(hint) = hint {
+            println!("       hint: {hint}");
+        }
+    }
+}
+
+/// Pool checked by the doctor: the pool of `pool_config.yaml` or each pool of the multi-pool mode
+struct DoctorPool {
+    /// Empty in the single-pool mode
+    name: String,
+    pool_config_file: PathBuf,
+    scans_file: PathBuf,
+    data_point_source_custom_script: Option<String>,
+}
+
+/// Runs all checks, returns `false` if any of them failed
+pub fn doctor(
+    oracle_config: Result<OracleConfig, anyhow::Error>,
+    pool_config_file: &Path,
+    data_dir: &Path,
+) -> bool {
+    let mut report = DoctorReport::default();
+    let oracle_config = match oracle_config {
+        Ok(oracle_config) => {
+            report.pass("Oracle config", "parsed, oracle address is P2PK");
+            Arc::new(oracle_config)
+        }
+        Err(e) => {
+            report.fail(
+                "Oracle config",
+                format!("{e:?}"),
+                "fix oracle_config.yaml (`generate-oracle-config` writes a template), oracle_address must be a P2PK address of the node wallet",
+            );
+            report.skip("Remaining checks", "the oracle config is needed");
+            return false;
+        }
+    };
+    let pools = doctor_pools(&oracle_config, pool_config_file, data_dir);
+    let mut pool_configs = Vec::new();
+    for pool in &pools {
+        let check = pool_check_name(pool, "Pool config");
+        match PoolConfig::load_from_file(&pool.pool_config_file) {
+            Ok(pool_config) => {
+                report.pass(
+                    &check,
+                    format!("{} parsed", pool.pool_config_file.display()),
+                );
+                pool_configs.push(Some(Arc::new(pool_config)));
+            }
+            Err(e) => {
+                report.fail(
+                    &check,
+                    format!("{e:?}"),
+                    "get the pool config file from the pool operators or set --pool-config-file",
+                );
+                pool_configs.push(None);
+            }
+        }
+    }
+
+    let Some((secrets, node_api)) = check_node(&mut report, &oracle_config) else {
+        report.skip("Remaining checks", "the node is needed");
+        return false;
+    };
+    check_wallet(&mut report, &node_api);
+
+    for (pool, pool_config) in pools.iter().zip(pool_configs) {
+        let Some(pool_config) = pool_config else {
+            report.skip(
+                &pool_check_name(pool, "Pool checks"),
+                "the pool config is needed",
+            );
+            continue;
+        };
+        let ctx = match OracleContext::new(
+            oracle_config.clone(),
+            pool_config,
+            secrets.clone(),
+            node_api.clone(),
+        ) {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                report.fail(
+                    &pool_check_name(pool, "Oracle context"),
+                    e,
+                    "check base_fee and oracle_address in oracle_config.yaml",
+                );
+                continue;
+            }
+        };
+        check_pool(&mut report, pool, &ctx);
+    }
+    report.is_ok()
+}
+
+fn doctor_pools(
+    oracle_config: &OracleConfig,
+    pool_config_file: &Path,
+    data_dir: &Path,
+) -> Vec<DoctorPool> {
+    if oracle_config.pools.is_empty() {
+        vec![DoctorPool {
+            name: String::new(),
+            pool_config_file: pool_config_file.to_path_buf(),
+            scans_file: data_dir.join("scanIDs.json"),
+            data_point_source_custom_script: oracle_config.data_point_source_custom_script.clone(),
+        }]
+    } else {
+        oracle_config
+            .pools
+            .iter()
+            .map(|entry| DoctorPool {
+                name: entry.name.clone(),
+                pool_config_file: entry.pool_config_file.clone(),
+                scans_file: data_dir.join(&entry.name).join("scanIDs.json"),
+                data_point_source_custom_script: entry.data_point_source_custom_script.clone(),
+            })
+            .collect()
+    }
+}
+
+fn pool_check_name(pool: &DoctorPool, check: &str) -> String {
+    if pool.name.is_empty() {
+        check.to_string()
+    } else {
+        format!("[{}] {check}", pool.name)
+    }
+}
+
+/// Node API key, node reachable and synced, change address on the network of the oracle address
+fn check_node(
+    report: &mut DoctorReport,
+    oracle_config: &OracleConfig,
+) -> Option<(Arc<OracleSecrets>, Arc<NodeApi>)> {
+    if std::env::var("ORACLE_NODE_API_KEY").is_err() {
+        report.fail(
+            "Node API key",
+            "ORACLE_NODE_API_KEY is not set",
+            "export ORACLE_NODE_API_KEY with the node's API key (the one hashed in apiKeyHash of the node config)",
+        );
+        return None;
+    }
+    let secrets = Arc::new(OracleSecrets::load());
+    let node_api = Arc::new(NodeApi::new(
+        secrets.node_api_key.clone(),
+        secrets.wallet_password.clone(),
+        &oracle_config.node_url,
+    ));
+    match check_node_sync(&node_api, &oracle_config.node_sync) {
+        Ok(status) if status.is_synced => report.pass(
+            "Node",
+            format!(
+                "{} reachable and synced at height {}",
+                oracle_config.node_url, status.full_height
+            ),
+        ),
+        Ok(status) => report.fail(
+            "Node",
+            format!(
+                "{} is not synced: {}",
+                oracle_config.node_url,
+                status.reason.unwrap_or_default()
+            ),
+            "wait for the node to sync (see node_sync in oracle_config.yaml for the thresholds)",
+        ),
+        Err(e) => {
+            report.fail(
+                "Node",
+                format!("{} is not reachable: {e}", oracle_config.node_url),
+                "check node_url in oracle_config.yaml and that the node is running",
+            );
+            return None;
+        }
+    }
+    match node_api.get_change_address() {
+        Ok(change_address) if change_address.network() == oracle_config.oracle_address.network() => {
+            report.pass("Change address", change_address.to_base58())
+        }
+        Ok(change_address) => report.fail(
+            "Change address",
+            format!(
+                "{} is not on the network of the oracle address {}",
+                change_address.to_base58(),
+                oracle_config.oracle_address.to_base58()
+            ),
+            "use a node of the pool's network (mainnet/testnet) and an oracle address of that network",
+        ),
+        Err(e) => report.fail(
+            "Change address",
+            e,
+            "initialize the node wallet and check the API key (ORACLE_NODE_API_KEY)",
+        ),
+    }
+    Some((secrets, node_api))
+}
+
+fn check_wallet(report: &mut DoctorReport, node_api: &NodeApi) {
+    let unlocked = match node_api.node.wallet_status() {
+        Ok(status) => status.unlocked,
+        Err(e) => {
+            report.fail(
+                "Wallet",
+                e,
+                "initialize the node wallet and check the API key (ORACLE_NODE_API_KEY)",
+            );
+            return;
+        }
+    };
+    if unlocked {
+        report.pass("Wallet", "unlocked");
+        return;
+    }
+    match &node_api.wallet_pass {
+        Some(wallet_pass) => match node_api.wallet_unlock(wallet_pass) {
+            Ok(_) => report.pass("Wallet", "unlocked with ORACLE_NODE_WALLET_PASSWORD"),
+            Err(e) => report.fail(
+                "Wallet",
+                format!("failed to unlock: {e}"),
+                "check ORACLE_NODE_WALLET_PASSWORD",
+            ),
+        },
+        None => report.fail(
+            "Wallet",
+            "locked and ORACLE_NODE_WALLET_PASSWORD is not set",
+            "unlock the node wallet or export ORACLE_NODE_WALLET_PASSWORD",
+        ),
+    }
+}
+
+/// Box source, on-chain pool boxes, oracle token and datapoint source of the pool
+fn check_pool(report: &mut DoctorReport, pool: &DoctorPool, ctx: &OracleContext) {
+    let op = match ctx.oracle_config.box_source {
+        BoxSource::NodeScans => {
+            check_scans(report, pool, ctx).map(|registry| OraclePool::new(ctx, &registry))
+        }
+        BoxSource::NodeIndexer => check_node_indexer(report, pool, ctx).map(|reader| {
+            OraclePool::with_token_boxes(
+                ctx.pool_config.clone(),
+                Arc::new(reader).pool_token_boxes(&ctx.pool_config),
+                Some(ctx.oracle_pk()),
+            )
+        }),
+    };
+
+    let boxes_check = pool_check_name(pool, "Pool boxes");
+    let oracle_token_check = pool_check_name(pool, "Oracle token");
+    match &op {
+        Some(op) => {
+            let pool_boxes = op
+                .get_pool_box_source()
+                .get_pool_box()
+                .map_err(|e| format!("pool box: {e}"))
+                .and_then(|_| {
+                    op.get_refresh_box_source()
+                        .get_refresh_box()
+                        .map_err(|e| format!("refresh box: {e}"))
+                })
+                .and_then(|_| {
+                    op.get_update_box_source()
+                        .get_update_box()
+                        .map_err(|e| format!("update box: {e}"))
+                });
+            match pool_boxes {
+                Ok(_) => report.pass(
+                    &boxes_check,
+                    "pool, refresh and update boxes match the pool config contracts",
+                ),
+                Err(e) => report.fail(
+                    &boxes_check,
+                    e,
+                    "wait for the node rescan to finish, or get the current pool config from the pool operators (`import-pool-update` after an update)",
+                ),
+            }
+            check_oracle_token(report, &oracle_token_check, ctx, op);
+        }
+        None => {
+            report.skip(&boxes_check, "the box source is needed");
+            report.skip(&oracle_token_check, "the box source is needed");
+        }
+    }
+
+    check_datapoint_source(report, pool, ctx);
+}
+
+/// Scans file of the pool and its scans on the node, the registry if the file was loaded
+fn check_scans(
+    report: &mut DoctorReport,
+    pool: &DoctorPool,
+    ctx: &OracleContext,
+) -> Option<NodeScanRegistry> {
+    let check = pool_check_name(pool, "Scans");
+    let registry = match NodeScanRegistry::load_from_file(&pool.scans_file) {
+        Ok(registry) => registry,
+        Err(e) => {
+            report.fail(
+                &check,
+                format!("{}: {e}", pool.scans_file.display()),
+                "`run` registers the scans on its first start, or check --data-dir",
+            );
+            return None;
+        }
+    };
+    match ctx.node_api.list_scans() {
+        Ok(node_scans) => {
+            let problems: Vec<String> =
+                scan_problems(&registry.token_scans(&ctx.pool_config), &node_scans)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+            if problems.is_empty() {
+                report.pass(&check, "registered on the node, tracking the pool tokens");
+            } else {
+                report.fail(
+                    &check,
+                    problems.join("; "),
+                    "`run` registers the lost scans again on start and every few minutes",
+                );
+            }
+        }
+        Err(e) => report.fail(
+            &check,
+            format!("failed to list the node scans: {e}"),
+            "check the API key (ORACLE_NODE_API_KEY)",
+        ),
+    }
+    Some(registry)
+}
+
+/// Blockchain indexer of the node (`box_source: node_indexer`) enabled and caught up
+fn check_node_indexer(
+    report: &mut DoctorReport,
+    pool: &DoctorPool,
+    ctx: &OracleContext,
+) -> Option<PublicChainReader> {
+    let check = pool_check_name(pool, "Node indexer");
+    let reader = match PublicChainReader::oracle_node_indexer(ctx) {
+        Ok(reader) => reader,
+        Err(e) => {
+            report.fail(
+                &check,
+                e,
+                "set extraIndex = true in the node config, or box_source: node_scans in oracle_config.yaml",
+            );
+            return None;
+        }
+    };
+    let heights = ctx
+        .node_api
+        .get_indexed_height()
+        .and_then(|indexed| Ok((indexed, ctx.node_api.node.current_block_height()? as u32)));
+    match heights {
+        Ok((Some(indexed_height), height)) if indexed_height >= height => {
+            report.pass(&check, format!("indexed up to height {indexed_height}"))
+        }
+        Ok((indexed_height, height)) => report.fail(
+            &check,
+            format!(
+                "indexed up to height {} of {height}",
+                indexed_height.unwrap_or(0)
+            ),
+            "wait for the node to finish indexing",
+        ),
+        Err(e) => report.fail(&check, e, "check the node"),
+    }
+    Some(reader)
+}
+
+fn check_oracle_token(
+    report: &mut DoctorReport,
+    check: &str,
+    ctx: &OracleContext,
+    op: &OraclePool,
+) {
+    let oracle_token_id = ctx.pool_config.token_ids.oracle_token_id.token_id();
+    match ctx.node_api.node.unspent_boxes() {
+        Ok(wallet_boxes) if holds_token(&wallet_boxes, &oracle_token_id) => {
+            report.pass(check, "held by the wallet");
+            return;
+        }
+        Ok(_) => (),
+        Err(e) => {
+            report.fail(
+                check,
+                format!("failed to read the wallet boxes: {e}"),
+                "check the node wallet",
+            );
+            return;
+        }
+    }
+    match op
+        .get_local_datapoint_box_source()
+        .get_local_oracle_datapoint_box()
+    {
+        Ok(Some(_)) => report.pass(check, "in the oracle box"),
+        Ok(None) => report.fail(
+            check,
+            format!(
+                "not in the wallet and no oracle box of {}",
+                ctx.oracle_config.oracle_address.to_base58()
+            ),
+            "ask the pool operators for an oracle token sent to the oracle address",
+        ),
+        Err(e) => report.fail(
+            check,
+            format!("failed to read the oracle box: {e}"),
+            "wait for the node rescan to finish",
+        ),
+    }
+}
+
+fn check_datapoint_source(report: &mut DoctorReport, pool: &DoctorPool, ctx: &OracleContext) {
+    let check = pool_check_name(pool, "Datapoint source");
+    if pool.name.is_empty() && ctx.oracle_config.data_point_source_push.is_some() {
+        report.skip(
+            &check,
+            "datapoints are pushed by the connector to the running oracle",
+        );
+        return;
+    }
+    let datapoint_source = match RuntimeDataPointSource::new(
+        ctx.pool_config.data_point_source,
+        pool.data_point_source_custom_script.clone(),
+        None,
+    ) {
+        Ok(datapoint_source) => datapoint_source,
+        Err(e) => {
+            report.fail(
+                &check,
+                e,
+                "set data_point_source in the pool config or data_point_source_custom_script in the oracle config",
+            );
+            return;
+        }
+    };
+    match datapoint_source.get_datapoint() {
+        Ok(rate) => report.pass(&check, format!("returned {rate}")),
+        Err(e) => report.fail(
+            &check,
+            e,
+            "check the network access to the price APIs or run the custom script by hand",
+        ),
+    }
+}
+
+fn holds_token(boxes: &[ErgoBox], token_id: &TokenId) -> bool {
+    boxes.iter().any(|b| {
+        b.tokens.as_ref().map_or(false, |tokens| {
+            tokens.iter().any(|t| t.token_id == *token_id)
+        })
+    })
+}
@@ -3,12 +3,13 @@ use std::convert::TryInto;
 use ergo_lib::{
     chain::{
         ergo_box::box_builder::{ErgoBoxCandidateBuilder, ErgoBoxCandidateBuilderError},
-        transaction::unsigned::UnsignedTransaction,
+        transaction::{unsigned::UnsignedTransaction, TxId},
     },
     ergotree_interpreter::sigma_protocol::prover::ContextExtension,
     ergotree_ir::{
         chain::{
             address::{Address, AddressEncoder, AddressEncoderError},
+            ergo_box::box_value::BoxValue,
             token::Token,
         },
         serialization::SigmaParsingError,
@@ -25,9 +26,9 @@ use crate::{
     box_kind::{
         make_collected_oracle_box_candidate, make_oracle_box_candidate, OracleBox, OracleBoxWrapper,
     },
+    context::OracleContext,
     explorer_api::ergo_explorer_transaction_link,
     node_interface::{SignTransaction, SubmitTransaction},
-    oracle_config::BASE_FEE,
     oracle_state::{DataSourceError, LocalDatapointBoxSource},
     oracle_types::BlockHeight,
     spec_token::SpecToken,
@@ -65,25 +66,17 @@ pub enum ExtractRewardTokensActionError {
 }
 
 pub fn extract_reward_tokens(
-    wallet: &dyn WalletDataSource,
-    tx_signer: &dyn SignTransaction,
-    tx_submit: &dyn SubmitTransaction,
+    ctx: &OracleContext,
     local_datapoint_box_source: &dyn LocalDatapointBoxSource,
     rewards_destination_str: String,
     height: BlockHeight,
 ) -> Result<(), anyhow::Error> {
-    let rewards_destination =
-        AddressEncoder::unchecked_parse_network_address_from_str(&rewards_destination_str)?;
-    let network_prefix = rewards_destination.network();
-    let change_address = wallet
-        .get_change_address()
-        .map_err(ExtractRewardTokensActionError::WalletData)?;
-    let (unsigned_tx, num_reward_tokens) = build_extract_reward_tokens_tx(
+    let (unsigned_tx, num_reward_tokens) = build_tx_for_destination(
+        ctx.node_api.as_ref(),
         local_datapoint_box_source,
-        wallet,
-        rewards_destination.address(),
+        &rewards_destination_str,
         height,
-        change_address.address(),
+        ctx.base_fee,
     )?;
 
     println!(
@@ -93,12 +86,12 @@ pub fn extract_reward_tokens(
     let mut input = String::new();
     std::io::stdin().read_line(&mut input)?;
     if input.trim() == "YES" {
-        let signed_tx = tx_signer.sign_transaction(&unsigned_tx)?;
-        let tx_id = tx_submit.submit_transaction(&signed_tx)?;
-        crate::explorer_api::wait_for_tx_confirmation(signed_tx.id());
+        let signed_tx = ctx.node_api.node.sign_transaction(&unsigned_tx)?;
+        let tx_id = ctx.node_api.node.submit_transaction(&signed_tx)?;
+        crate::explorer_api::wait_for_tx_confirmation(&ctx.oracle_config, signed_tx.id());
         println!(
             "Transaction made. Check status here: {}",
-            ergo_explorer_transaction_link(tx_id, network_prefix)
+            ergo_explorer_transaction_link(&ctx.oracle_config, tx_id)
         );
     } else {
         println!("Aborting the transaction.")
@@ -106,12 +99,55 @@ pub fn extract_reward_tokens(
     Ok(())
 }
 
+/// Same as `extract_reward_tokens` but without the confirmation prompt and without waiting for
+/// the tx confirmation. Returns the submitted tx id and the number of extracted reward tokens.
+pub fn extract_reward_tokens_unattended(
+    ctx: &OracleContext,
+    local_datapoint_box_source: &dyn LocalDatapointBoxSource,
+    rewards_destination_str: &str,
+    height: BlockHeight,
+) -> Result<(TxId, u64), anyhow::Error> {
+    let (unsigned_tx, num_reward_tokens) = build_tx_for_destination(
+        ctx.node_api.as_ref(),
+        local_datapoint_box_source,
+        rewards_destination_str,
+        height,
+        ctx.base_fee,
+    )?;
+    let signed_tx = ctx.node_api.node.sign_transaction(&unsigned_tx)?;
+    let tx_id = ctx.node_api.node.submit_transaction(&signed_tx)?;
+    Ok((tx_id, num_reward_tokens))
+}
+
+fn build_tx_for_destination(
+    wallet: &dyn WalletDataSource,
+    local_datapoint_box_source: &dyn LocalDatapointBoxSource,
+    rewards_destination_str: &str,
+    height: BlockHeight,
+    tx_fee: BoxValue,
+) -> Result<(UnsignedTransaction, u64), anyhow::Error> {
+    let rewards_destination =
+        AddressEncoder::unchecked_parse_network_address_from_str(rewards_destination_str)?;
+    let change_address = wallet
+        .get_change_address()
+        .map_err(ExtractRewardTokensActionError::WalletData)?;
+    Ok(build_extract_reward_tokens_tx(
+        local_datapoint_box_source,
+        wallet,
+        rewards_destination.address(),
+        height,
+        change_address.address(),
+        tx_fee,
+    )?)
+}
+
 fn build_extract_reward_tokens_tx(
     local_datapoint_box_source: &dyn LocalDatapointBoxSource,
     wallet: &dyn WalletDataSource,
     rewards_destination: Address,
     height: BlockHeight,
     change_address: Address,
+    tx_fee: BoxValue,
 ) -> Result<(UnsignedTransaction, u64), ExtractRewardTokensActionError> {
     let in_oracle_box = local_datapoint_box_source
         .get_local_oracle_datapoint_box()?
@@ -154,7 +190,7 @@ fn build_extract_reward_tokens_tx(
 
         // Build box to hold extracted tokens
         let mut builder =
-            ErgoBoxCandidateBuilder::new(*BASE_FEE, rewards_destination.script()?, height.0);
+            ErgoBoxCandidateBuilder::new(tx_fee, rewards_destination.script()?, height.0);
 
         let extracted_reward_tokens = Token {
             token_id: in_oracle_box.reward_token().token_id(),
@@ -166,8 +202,8 @@ fn build_extract_reward_tokens_tx(
 
         let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
 
-        // `BASE_FEE` each for the fee and the box holding the extracted reward tokens.
-        let target_balance = BASE_FEE.checked_mul_u32(2).unwrap();
+        // `tx_fee` each for the fee and the box holding the extracted reward tokens.
+        let target_balance = tx_fee.checked_mul_u32(2).unwrap();
 
         let box_selector = SimpleBoxSelector::new();
         let selection = box_selector.select(unspent_boxes, target_balance, &[])?;
@@ -181,7 +217,7 @@ fn build_extract_reward_tokens_tx(
             box_selection,
             vec![oracle_box_candidate, reward_box_candidate],
             height.0,
-            *BASE_FEE,
+            tx_fee,
             change_address,
         );
         // The following context value ensures that `outIndex` in the oracle contract is properly set.
@@ -213,6 +249,7 @@ mod tests {
     use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
     use ergo_lib::ergotree_ir::chain::address::AddressEncoder;
     use ergo_lib::wallet::signing::TransactionContext;
+    use ergo_lib::wallet::tx_builder::SUGGESTED_TX_FEE;
     use ergo_lib::wallet::Wallet;
     use sigma_test_util::force_any_val;
 
@@ -236,7 +273,7 @@ mod tests {
                 200,
                 EpochCounter(1),
                 &token_ids,
-                BASE_FEE.checked_mul_u32(100).unwrap(),
+                SUGGESTED_TX_FEE().checked_mul_u32(100).unwrap(),
                 BlockHeight(height.0),
                 num_reward_tokens_in_box,
             ),
@@ -252,7 +289,7 @@ mod tests {
 
         let wallet_unspent_box = make_wallet_unspent_box(
             secret.public_image(),
-            BASE_FEE.checked_mul_u32(10000).unwrap(),
+            SUGGESTED_TX_FEE().checked_mul_u32(10000).unwrap(),
             None,
         );
         let wallet_mock = WalletDataMock {
@@ -265,6 +302,7 @@ mod tests {
             change_address.address(),
             height,
             change_address.address(),
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -0,0 +1,48 @@
+use crate::context::OracleContext;
+use crate::oracle_state::OraclePool;
+use crate::oracle_stats::get_oracle_stats;
+use crate::oracle_stats::get_posted_datapoint_log_file_path;
+use crate::oracle_stats::PostedDatapointLog;
+use crate::pool_history::chain_index;
+use crate::pool_history::get_pool_history_file_path;
+use crate::pool_history::PoolHistoryCache;
+use crate::scans::SCANS_DIR_PATH;
+
+pub fn print_oracle_stats(
+    ctx: &OracleContext,
+    op: &OraclePool,
+    epochs: usize,
+) -> Result<(), anyhow::Error> {
+    let pool_box = op.get_pool_box_source().get_pool_box()?;
+    let data_dir = SCANS_DIR_PATH.get().unwrap();
+    let mut history_cache = PoolHistoryCache::load(&get_pool_history_file_path(data_dir));
+    let posted_log = PostedDatapointLog::load(&get_posted_datapoint_log_file_path(data_dir));
+    let stats = get_oracle_stats(
+        chain_index(ctx)?.as_ref(),
+        &mut history_cache,
+        &posted_log,
+        &op.pool_config().token_ids,
+        ctx.network_prefix,
+        pool_box.get_box().box_id(),
+        epochs,
+    )?;
+    println!(
+        "{:<52} {:>12} {:>12} {:>10} {:>11} {:>8}",
+        "Oracle", "Participated", "Filtered out", "Deviation", "Collections", "Rewards"
+    );
+    for oracle in stats {
+        println!(
+            "{:<52} {:>12} {:>12} {:>10} {:>11} {:>8}",
+            oracle.address,
+            oracle.epochs_participated,
+            oracle.epochs_filtered_out,
+            oracle
+                .average_deviation_percent
+                .map(|d| format!("{d:.2}%"))
+                .unwrap_or_else(|| "-".to_string()),
+            oracle.collections,
+            oracle.reward_tokens_earned
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use crate::box_kind::PoolBox;
+use crate::context::OracleContext;
+use crate::oracle_state::OraclePool;
+use crate::pool_history::chain_index;
+use crate::pool_history::get_pool_history;
+use crate::pool_history::get_pool_history_file_path;
+use crate::pool_history::PoolHistoryCache;
+use crate::scans::SCANS_DIR_PATH;
+
+pub fn print_pool_history(
+    ctx: &OracleContext,
+    op: &OraclePool,
+    epochs: usize,
+) -> Result<(), anyhow::Error> {
+    let pool_box = op.get_pool_box_source().get_pool_box()?;
+    let mut cache =
+        PoolHistoryCache::load(&get_pool_history_file_path(SCANS_DIR_PATH.get().unwrap()));
+    let history = get_pool_history(
+        chain_index(ctx)?.as_ref(),
+        &mut cache,
+        &op.pool_config().token_ids,
+        ctx.network_prefix,
+        pool_box.get_box().box_id(),
+        epochs,
+    )?;
+    println!(
+        "{:>8} {:>10} {:>20} {:>8}  {:<64}  {}",
+        "Epoch", "Height", "Rate", "Oracles", "Tx id", "Collector"
+    );
+    for entry in history {
+        println!(
+            "{:>8} {:>10} {:>20} {:>8}  {:<64}  {}",
+            entry.epoch_counter.0,
+            entry.height.0,
+            entry.rate,
+            entry.oracles.len(),
+            entry.tx_id,
+            entry.collector.unwrap_or_else(|| "-".to_string())
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use crate::context::OracleContext;
+use crate::oracle_state::OraclePool;
+use crate::oracle_types::BlockHeight;
+use crate::pool_commands::preview_refresh;
+use crate::pool_commands::refresh::PreviewDatapoint;
+
+pub fn print_refresh_preview(
+    ctx: &OracleContext,
+    op: &OraclePool,
+    height: BlockHeight,
+) -> Result<(), anyhow::Error> {
+    let preview = preview_refresh(ctx, op, height)?;
+    println!(
+        "Epoch {}, current rate {}, refresh possible after height {} (now {})",
+        preview.pool_box_epoch_counter.0,
+        preview.current_rate,
+        preview.epoch_end_height,
+        preview.height
+    );
+    println!(
+        "Max deviation {}%, min datapoints {}",
+        preview.max_deviation_percent, preview.min_data_points.0
+    );
+    println!();
+    println!(
+        "{:<52} {:>20} {:>10} {:>8}  {}",
+        "Oracle", "Rate", "Deviation", "Epoch", "Status"
+    );
+    let print_datapoint = |d: &PreviewDatapoint| {
+        println!(
+            "{:<52} {:>20} {:>9.2}% {:>8}  {}",
+            d.address,
+            d.rate,
+            d.deviation_percent,
+            d.epoch_counter.0,
+            d.exclusion_reason
+                .map(|r| format!("excluded ({r:?})"))
+                .unwrap_or_else(|| "included".to_string())
+        );
+    };
+    preview.included.iter().for_each(print_datapoint);
+    preview.excluded.iter().for_each(print_datapoint);
+    println!();
+    match (&preview.failure, preview.rate) {
+        (None, Some(rate)) => println!("Refresh would succeed, new pool rate: {rate}"),
+        (Some(failure), _) => println!("Refresh would fail: {failure}"),
+        (None, None) => println!("Refresh would fail: no datapoints"),
+    }
+    if !preview.can_collect {
+        println!("Our datapoint is not included, this oracle can't send the refresh");
+    }
+    Ok(())
+}
@@ -3,11 +3,11 @@ use std::convert::{TryFrom, TryInto};
 use ergo_lib::{
     chain::{
         ergo_box::box_builder::ErgoBoxCandidateBuilderError,
-        transaction::unsigned::UnsignedTransaction,
+        transaction::{unsigned::UnsignedTransaction, TxId},
     },
     ergo_chain_types::{Digest32, DigestNError, EcPoint},
     ergotree_interpreter::sigma_protocol::prover::ContextExtension,
-    ergotree_ir::chain::address::Address,
+    ergotree_ir::chain::{address::Address, ergo_box::box_value::BoxValue},
     wallet::{
         box_selector::{BoxSelection, BoxSelector, BoxSelectorError, SimpleBoxSelector},
         tx_builder::{TxBuilder, TxBuilderError},
@@ -17,15 +17,15 @@ use ergo_node_interface::node_interface::NodeError;
 
 use crate::{
     box_kind::{make_local_ballot_box_candidate, BallotBox, BallotBoxWrapper},
+    context::OracleContext,
     contracts::ballot::{
         BallotContract, BallotContractError, BallotContractInputs, BallotContractParameters,
     },
     explorer_api::ergo_explorer_transaction_link,
     node_interface::{SignTransaction, SubmitTransaction},
-    oracle_config::{BASE_FEE, ORACLE_CONFIG},
     oracle_state::{DataSourceError, LocalBallotBoxSource},
     oracle_types::BlockHeight,
-    pool_config::{TokenIds, POOL_CONFIG},
+    pool_config::TokenIds,
     spec_token::{RewardTokenId, SpecToken, TokenIdKind},
     wallet::{WalletDataError, WalletDataSource},
 };
@@ -45,8 +45,6 @@ pub enum VoteUpdatePoolError {
     TxBuilder(#[from] TxBuilderError),
     #[error("Vote update pool: Node doesn't have a change address set")]
     NoChangeAddressSetInNode,
-    #[error("Vote update pool: Ballot token owner address not P2PK")]
-    IncorrectBallotTokenOwnerAddress,
     #[error("Vote update pool: IO error {0}")]
     Io(#[from] std::io::Error),
     #[error("Vote update pool: Digest32 error {0}")]
@@ -59,9 +57,7 @@ pub enum VoteUpdatePoolError {
 
 #[allow(clippy::too_many_arguments)]
 pub fn vote_update_pool(
-    wallet: &dyn WalletDataSource,
-    tx_signer: &dyn SignTransaction,
-    tx_submit: &dyn SubmitTransaction,
+    ctx: &OracleContext,
     local_ballot_box_source: &dyn LocalBallotBoxSource,
     new_pool_box_address_hash_str: String,
     reward_token_opt: Option<SpecToken<RewardTokenId>>,
@@ -69,49 +65,16 @@ pub fn vote_update_pool(
     height: BlockHeight,
     ballot_contract: &BallotContract,
 ) -> Result<(), anyhow::Error> {
-    let change_network_address = wallet.get_change_address()?;
-    let network_prefix = change_network_address.network();
     let new_pool_box_address_hash = Digest32::try_from(new_pool_box_address_hash_str)?;
-    let ballot_token_owner =
-        if let Address::P2Pk(ballot_token_owner) = ORACLE_CONFIG.oracle_address.address() {
-            ballot_token_owner.h
-        } else {
-            return Err(VoteUpdatePoolError::IncorrectBallotTokenOwnerAddress.into());
-        };
-    let unsigned_tx = if let Some(local_ballot_box) = local_ballot_box_source.get_ballot_box()? {
-        log::debug!("Found local ballot box");
-        // Note: the ballot box contains the ballot token, but the box is guarded by the contract,
-        // which stipulates that the address in R4 is the 'owner' of the token
-        build_tx_with_existing_ballot_box(
-            &local_ballot_box,
-            ballot_contract,
-            wallet,
-            new_pool_box_address_hash,
-            reward_token_opt.clone(),
-            update_box_creation_height,
-            height,
-            change_network_address.address(),
-            ballot_token_owner.as_ref(),
-        )?
-    } else {
-        log::debug!("Not found local ballot box, looking for a ballot token in the wallet");
-        // Note: the ballot box contains the ballot token, but the box is guarded by the contract,
-        // Ballot token is assumed to be in some unspent box of the node's wallet.
-        build_tx_for_first_ballot_box(
-            wallet,
-            new_pool_box_address_hash,
-            reward_token_opt.clone(),
-            update_box_creation_height,
-            ballot_token_owner.as_ref(),
-            POOL_CONFIG
-                .ballot_box_wrapper_inputs
-                .contract_inputs
-                .contract_parameters(),
-            &POOL_CONFIG.token_ids,
-            height,
-            change_network_address.address(),
-        )?
-    };
+    let unsigned_tx = build_vote_tx(
+        ctx,
+        local_ballot_box_source,
+        new_pool_box_address_hash,
+        reward_token_opt.clone(),
+        update_box_creation_height,
+        height,
+        ballot_contract,
+    )?;
     println!(
         "YOU WILL BE CASTING A VOTE FOR THE FOLLOWING ITEMS:\
            - Hash of new pool box contract: {}",
@@ -133,16 +96,16 @@ pub fn vote_update_pool(
             "Signing vote tx: {:?} ",
             &serde_json::to_string_pretty(&unsigned_tx)
         );
-        let signed_tx = tx_signer.sign_transaction(&unsigned_tx)?;
+        let signed_tx = ctx.node_api.node.sign_transaction(&unsigned_tx)?;
         log::debug!(
             "Submitting signed vote tx: {:?} ",
             &serde_json::to_string_pretty(&signed_tx)
         );
-        let tx_id_str = tx_submit.submit_transaction(&signed_tx)?;
-        crate::explorer_api::wait_for_tx_confirmation(signed_tx.id());
+        let tx_id_str = ctx.node_api.node.submit_transaction(&signed_tx)?;
+        crate::explorer_api::wait_for_tx_confirmation(&ctx.oracle_config, signed_tx.id());
         println!(
             "Transaction made. Check status here: {}",
-            ergo_explorer_transaction_link(tx_id_str, network_prefix)
+            ergo_explorer_transaction_link(&ctx.oracle_config, tx_id_str)
         );
     } else {
         println!("Aborting the transaction.")
@@ -150,6 +113,82 @@ pub fn vote_update_pool(
     Ok(())
 }
 
+/// Same as `vote_update_pool` but without the confirmation prompt and without waiting for the tx
+/// confirmation
+#[allow(clippy::too_many_arguments)]
+pub fn vote_update_pool_unattended(
+    ctx: &OracleContext,
+    local_ballot_box_source: &dyn LocalBallotBoxSource,
+    new_pool_box_address_hash: Digest32,
+    reward_token_opt: Option<SpecToken<RewardTokenId>>,
+    update_box_creation_height: BlockHeight,
+    height: BlockHeight,
+    ballot_contract: &BallotContract,
+) -> Result<TxId, anyhow::Error> {
+    let unsigned_tx = build_vote_tx(
+        ctx,
+        local_ballot_box_source,
+        new_pool_box_address_hash,
+        reward_token_opt,
+        update_box_creation_height,
+        height,
+        ballot_contract,
+    )?;
+    let signed_tx = ctx.node_api.node.sign_transaction(&unsigned_tx)?;
+    Ok(ctx.node_api.node.submit_transaction(&signed_tx)?)
+}
+
+fn build_vote_tx(
+    ctx: &OracleContext,
+    local_ballot_box_source: &dyn LocalBallotBoxSource,
+    new_pool_box_address_hash: Digest32,
+    reward_token_opt: Option<SpecToken<RewardTokenId>>,
+    update_box_creation_height: BlockHeight,
+    height: BlockHeight,
+    ballot_contract: &BallotContract,
+) -> Result<UnsignedTransaction, anyhow::Error> {
+    let wallet = ctx.node_api.as_ref();
+    let change_network_address = wallet.get_change_address()?;
+    let ballot_token_owner = ctx.oracle_pk().h;
+    let unsigned_tx = if let Some(local_ballot_box) = local_ballot_box_source.get_ballot_box()? {
+        log::debug!("Found local ballot box");
+        // Note: the ballot box contains the ballot token, but the box is guarded by the contract,
+        // which stipulates that the address in R4 is the 'owner' of the token
+        build_tx_with_existing_ballot_box(
+            &local_ballot_box,
+            ballot_contract,
+            wallet,
+            new_pool_box_address_hash,
+            reward_token_opt,
+            update_box_creation_height,
+            height,
+            change_network_address.address(),
+            ballot_token_owner.as_ref(),
+            ctx.base_fee,
+        )?
+    } else {
+        log::debug!("Not found local ballot box, looking for a ballot token in the wallet");
+        // Note: the ballot box contains the ballot token, but the box is guarded by the contract,
+        // Ballot token is assumed to be in some unspent box of the node's wallet.
+        build_tx_for_first_ballot_box(
+            wallet,
+            new_pool_box_address_hash,
+            reward_token_opt,
+            update_box_creation_height,
+            ballot_token_owner.as_ref(),
+            ctx.pool_config
+                .ballot_box_wrapper_inputs
+                .contract_inputs
+                .contract_parameters(),
+            &ctx.pool_config.token_ids,
+            height,
+            change_network_address.address(),
+            ctx.base_fee,
+        )?
+    };
+    Ok(unsigned_tx)
+}
+
 #[allow(clippy::too_many_arguments)]
 fn build_tx_with_existing_ballot_box(
     in_ballot_box: &BallotBoxWrapper,
@@ -161,6 +200,7 @@ fn build_tx_with_existing_ballot_box(
     height: BlockHeight,
     change_address: Address,
     ballot_token_owner_pk: &EcPoint,
+    tx_fee: BoxValue,
 ) -> Result<UnsignedTransaction, VoteUpdatePoolError> {
     let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
     #[allow(clippy::todo)]
@@ -175,7 +215,7 @@ fn build_tx_with_existing_ballot_box(
         height,
     )?;
     let box_selector = SimpleBoxSelector::new();
-    let selection = box_selector.select(unspent_boxes, *BASE_FEE, &[])?;
+    let selection = box_selector.select(unspent_boxes, tx_fee, &[])?;
     let mut input_boxes = vec![in_ballot_box.get_box().clone()];
     input_boxes.append(selection.boxes.as_vec().clone().as_mut());
     let box_selection = BoxSelection {
@@ -186,7 +226,7 @@ fn build_tx_with_existing_ballot_box(
         box_selection,
         vec![ballot_box_candidate],
         height.0,
-        *BASE_FEE,
+        tx_fee,
         change_address,
     );
     // The following context value ensures that `outIndex` in the ballot contract is properly set.
@@ -209,6 +249,7 @@ fn build_tx_for_first_ballot_box(
     token_ids: &TokenIds,
     height: BlockHeight,
     change_address: Address,
+    tx_fee: BoxValue,
 ) -> Result<UnsignedTransaction, VoteUpdatePoolError> {
     let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
     let out_ballot_box_value = ballot_contract_parameters.min_storage_rent();
@@ -232,7 +273,7 @@ fn build_tx_for_first_ballot_box(
         height,
     )?;
     let box_selector = SimpleBoxSelector::new();
-    let selection_target_balance = out_ballot_box_value.checked_add(&BASE_FEE).unwrap();
+    let selection_target_balance = out_ballot_box_value.checked_add(&tx_fee).unwrap();
     let selection = box_selector.select(
         unspent_boxes,
         selection_target_balance,
@@ -246,7 +287,7 @@ fn build_tx_for_first_ballot_box(
         box_selection,
         vec![ballot_box_candidate],
         height.0,
-        *BASE_FEE,
+        tx_fee,
         change_address,
     );
     // The following context value ensures that `outIndex` in the ballot contract is properly set.
@@ -271,14 +312,13 @@ mod tests {
             ergo_box::{box_value::BoxValue, BoxTokens, ErgoBox},
             token::{Token, TokenId},
         },
-        wallet::{signing::TransactionContext, Wallet},
+        wallet::{signing::TransactionContext, tx_builder::SUGGESTED_TX_FEE, Wallet},
     };
     use sigma_test_util::force_any_val;
 
     use crate::{
         box_kind::{make_local_ballot_box_candidate, BallotBoxWrapper, BallotBoxWrapperInputs},
         contracts::ballot::{BallotContract, BallotContractInputs, BallotContractParameters},
-        oracle_config::BASE_FEE,
         oracle_types::{BlockHeight, EpochLength},
         pool_commands::test_utils::{
             find_input_boxes, generate_token_ids, make_wallet_unspent_box, WalletDataMock,
@@ -315,7 +355,7 @@ mod tests {
         };
         let wallet_unspent_box = make_wallet_unspent_box(
             secret.public_image(),
-            BASE_FEE.checked_mul_u32(100_000_000).unwrap(),
+            SUGGESTED_TX_FEE().checked_mul_u32(100_000_000).unwrap(),
             Some(BoxTokens::from_vec(vec![ballot_token]).unwrap()),
         );
         let wallet_mock = WalletDataMock {
@@ -344,6 +384,7 @@ mod tests {
             &token_ids,
             height,
             change_address.address(),
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -407,7 +448,7 @@ mod tests {
         let ballot_box = BallotBoxWrapper::new(in_ballot_box.clone(), &inputs).unwrap();
         let wallet_unspent_box = make_wallet_unspent_box(
             secret.public_image(),
-            BASE_FEE.checked_mul_u32(100_000_000).unwrap(),
+            SUGGESTED_TX_FEE().checked_mul_u32(100_000_000).unwrap(),
             None,
         );
         let wallet_mock = WalletDataMock {
@@ -427,6 +468,7 @@ mod tests {
             height,
             change_address.address(),
             secret.public_image().h.as_ref(),
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -0,0 +1,30 @@
+use crate::context::OracleContext;
+use crate::fee_runway::check_fee_runway;
+use crate::oracle_state::OraclePool;
+use crate::wallet_monitor::WalletState;
+
+pub fn wallet_status(ctx: &OracleContext, op: &OraclePool) -> Result<(), anyhow::Error> {
+    let wallet_state = WalletState::fetch(
+        &ctx.node_api,
+        ctx.oracle_config.node_sync.max_wallet_lag_blocks,
+    )?;
+    println!("Wallet unlocked: {}", wallet_state.unlocked);
+    println!("Wallet rescanning: {}", wallet_state.rescanning);
+    if let Some(balance) = wallet_state.balance_nano_erg {
+        println!("Wallet balance: {} nanoERG", balance);
+    }
+    let fee_runway = check_fee_runway(ctx, op)?;
+    println!(
+        "Spendable: {} nanoERG (reserved for the oracle box: {} nanoERG)",
+        fee_runway.spendable_nano_erg, fee_runway.reserved_nano_erg
+    );
+    println!(
+        "Estimated cost per epoch: {} nanoERG",
+        fee_runway.cost_per_epoch_nano_erg
+    );
+    println!(
+        "Enough for {} epochs ({} blocks), status: {:?}",
+        fee_runway.epochs_left, fee_runway.blocks_left, fee_runway.status
+    );
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! Hot reload of `oracle_config.yaml` by the `Run` loop, when the file changes on disk or on SIGHUP.
+//! The new config is validated and then applied to all pools at once, on the next loop iteration.
+//! Changes of the fields in [`RESTART_REQUIRED_FIELDS`] reject the whole reload.
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use thiserror::Error;
+
+use crate::context::OracleContext;
+use crate::context::OracleContextError;
+use crate::oracle_config::OracleConfig;
+
+/// Fields of the oracle config that are only read on startup
+pub const RESTART_REQUIRED_FIELDS: &[&str] = &[
+    "node_url",
+    "oracle_address",
+    "scan_start_height",
+    "box_source",
+    "explorer_fallback",
+    "core_api_port",
+    "metrics_port",
+    "admin_api_enabled",
+    "rest_api",
+    "metrics_server",
+    "data_point_source_push",
+    "pools",
+];
+
+#[derive(Debug, Error)]
+pub enum ConfigReloadError {
+    #[error("failed to load the oracle config: {0:?}")]
+    Load(anyhow::Error),
+    #[error("changing {} requires a restart", .0.join(", "))]
+    RestartRequired(Vec<&'static str>),
+    #[error("invalid oracle config: {0}")]
+    Context(#[from] OracleContextError),
+}
+
+/// Fields of `new` that differ from `old` and cannot be changed without a restart
+pub fn restart_required_changes(old: &OracleConfig, new: &OracleConfig) -> Vec<&'static str> {
+    let old = serde_yaml::to_value(old).unwrap();
+    let new = serde_yaml::to_value(new).unwrap();
+    RESTART_REQUIRED_FIELDS
+        .iter()
+        .copied()
+        .filter(|field| old.get(field) != new.get(field))
+        .collect()
+}
+
+/// New contexts of the pools with `new_config` (same pool configs, secrets and node). Fails without
+/// returning any context if `new_config` cannot be applied to one of the pools.
+pub fn reload_contexts(
+    current: &[Arc<OracleContext>],
+    new_config: OracleConfig,
+) -> Result<Vec<Arc<OracleContext>>, ConfigReloadError> {
+    let new_config = Arc::new(new_config);
+    if let Some(ctx) = current.first() {
+        let changes = restart_required_changes(&ctx.oracle_config, &new_config);
+        if !changes.is_empty() {
+            return Err(ConfigReloadError::RestartRequired(changes));
+        }
+    }
+    current
+        .iter()
+        .map(|ctx| {
+            Ok(Arc::new(OracleContext::new(
+                new_config.clone(),
+                ctx.pool_config.clone(),
+                ctx.secrets.clone(),
+                ctx.node_api.clone(),
+            )?))
+        })
+        .collect()
+}
+
+/// Tells the `Run` loop when to reload the oracle config
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    sighup: Arc<AtomicBool>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified_time(path),
+            sighup: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Set by the SIGHUP listener
+    pub fn sighup_flag(&self) -> Arc<AtomicBool> {
+        self.sighup.clone()
+    }
+
+    /// Whether the config file was modified or SIGHUP was received since the last call
+    pub fn reload_requested(&mut self) -> bool {
+        let sighup = self.sighup.swap(false, Ordering::SeqCst);
+        let modified = modified_time(&self.path);
+        let file_changed = modified != self.modified;
+        self.modified = modified;
+        sighup || file_changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Sets `flag` on every SIGHUP
+#[cfg(unix)]
+pub async fn listen_sighup(flag: Arc<AtomicBool>) -> Result<(), std::io::Error> {
+    use tokio::signal::unix::signal;
+    use tokio::signal::unix::SignalKind;
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        log::info!("SIGHUP received, reloading the oracle config");
+        flag.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::*;
+
+    #[test]
+    fn reloadable_fields_are_accepted() {
+        let old = OracleConfig::default();
+        let new = OracleConfig {
+            base_fee: old.base_fee * 2,
+            log_level: Some(log::LevelFilter::Debug),
+            data_point_source_custom_script: Some("./rate.sh".to_string()),
+            explorer_url: None,
+            ..old.clone()
+        };
+        assert!(restart_required_changes(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn restart_required_fields_are_rejected() {
+        let old = OracleConfig::default();
+        let new = OracleConfig {
+            node_url: Url::parse("http://10.0.0.1:9053").unwrap(),
+            core_api_port: old.core_api_port + 1,
+            ..old.clone()
+        };
+        assert_eq!(
+            restart_required_changes(&old, &new),
+            vec!["node_url", "core_api_port"]
+        );
+    }
+
+    #[test]
+    fn sighup_requests_reload_once() {
+        let mut watcher = ConfigWatcher::new(Path::new("does_not_exist.yaml"));
+        assert!(!watcher.reload_requested());
+        watcher.sighup_flag().store(true, Ordering::SeqCst);
+        assert!(watcher.reload_requested());
+        assert!(!watcher.reload_requested());
+    }
+}
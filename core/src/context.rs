@@ -0,0 +1,98 @@
+//! Configs and node client of a pool run by the oracle, passed explicitly to the pool commands,
+//! scans, REST API handlers and CLI commands instead of being read from the config globals
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValueError;
+use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+use thiserror::Error;
+
+use crate::explorer_api::explorer_url::default_explorer_api_url;
+use crate::explorer_api::ExplorerApi;
+use crate::node_interface::node_api::NodeApi;
+use crate::oracle_config::OracleConfig;
+use crate::oracle_config::OracleConfigFileError;
+use crate::oracle_config::OracleSecrets;
+use crate::pool_config::PoolConfig;
+
+#[derive(Debug, Error)]
+pub enum OracleContextError {
+    #[error("oracle config: {0}")]
+    OracleConfig(#[from] OracleConfigFileError),
+    #[error("invalid base_fee: {0}")]
+    BaseFee(#[from] BoxValueError),
+}
+
+/// Everything a pool command needs besides the chain state. In the multi-pool mode each pool has its
+/// own context sharing the oracle config, secrets and node client.
+pub struct OracleContext {
+    pub oracle_config: Arc<OracleConfig>,
+    pub pool_config: Arc<PoolConfig>,
+    pub secrets: Arc<OracleSecrets>,
+    pub node_api: Arc<NodeApi>,
+    /// Network of the oracle address
+    pub network_prefix: NetworkPrefix,
+    /// Fee of the txs built by the oracle (`base_fee` of the oracle config)
+    pub base_fee: BoxValue,
+}
+
+impl OracleContext {
+    pub fn new(
+        oracle_config: Arc<OracleConfig>,
+        pool_config: Arc<PoolConfig>,
+        secrets: Arc<OracleSecrets>,
+        node_api: Arc<NodeApi>,
+    ) -> Result<Self, OracleContextError> {
+        // fail early on a non-P2PK oracle address
+        let _ = oracle_config.oracle_address_p2pk()?;
+        let base_fee = BoxValue::try_from(oracle_config.base_fee)?;
+        Ok(Self {
+            network_prefix: oracle_config.oracle_address.network(),
+            oracle_config,
+            pool_config,
+            secrets,
+            node_api,
+            base_fee,
+        })
+    }
+
+    pub fn oracle_pk(&self) -> ProveDlog {
+        // checked in `new`
+        self.oracle_config.oracle_address_p2pk().unwrap()
+    }
+
+    /// Explorer API of the oracle config, the public explorer of the network if not set
+    pub fn explorer_api(&self) -> ExplorerApi {
+        ExplorerApi::new(
+            self.oracle_config
+                .explorer_url
+                .clone()
+                .unwrap_or_else(|| default_explorer_api_url(self.network_prefix)),
+        )
+    }
+}
+
+/// Context of a pool shared by the main loop and the REST API, replaced when `oracle_config.yaml`
+/// is reloaded
+pub struct SharedContext {
+    current: RwLock<Arc<OracleContext>>,
+}
+
+impl SharedContext {
+    pub fn new(ctx: Arc<OracleContext>) -> Self {
+        Self {
+            current: RwLock::new(ctx),
+        }
+    }
+
+    pub fn load(&self) -> Arc<OracleContext> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn store(&self, ctx: Arc<OracleContext>) {
+        *self.current.write().unwrap() = ctx;
+    }
+}
@@ -5,6 +5,7 @@ mod assets_exchange_rate;
 mod bitpanda;
 mod coincap;
 mod coingecko;
+pub mod connector;
 mod custom_ext_script;
 mod erg_btc;
 mod erg_usd;
@@ -14,6 +15,8 @@ mod predef;
 use crate::oracle_types::Rate;
 use crate::pool_config::PredefinedDataPointSource;
 
+use self::connector::PushDataPointConfig;
+use self::connector::PushDataPointSource;
 use self::custom_ext_script::ExternalScript;
 use self::custom_ext_script::ExternalScriptError;
 use self::predef::sync_fetch_predef_source_aggregated;
@@ -37,19 +40,29 @@ pub enum DataPointSourceError {
     JsonMissingField { field: String, json: String },
     #[error("No datapoints from any source")]
     NoDataPoints,
+    #[error("No datapoint pushed by the connector yet")]
+    NoPushedDataPoint,
+    #[error("Pushed datapoint is {age_secs}s old (max {max_age_secs}s)")]
+    StalePushedDataPoint { age_secs: u64, max_age_secs: u64 },
 }
 
 pub enum RuntimeDataPointSource {
     Predefined(PredefinedDataPointSource),
     ExternalScript(ExternalScript),
+    Push(PushDataPointSource),
 }
 
 impl RuntimeDataPointSource {
     pub fn new(
         predef_datapoint_source: Option<PredefinedDataPointSource>,
         custom_datapoint_source_shell_cmd: Option<String>,
+        push_datapoint_config: Option<PushDataPointConfig>,
     ) -> Result<RuntimeDataPointSource, anyhow::Error> {
-        if let Some(external_script_name) = custom_datapoint_source_shell_cmd.clone() {
+        if let Some(push_datapoint_config) = push_datapoint_config {
+            Ok(RuntimeDataPointSource::Push(PushDataPointSource::new(
+                push_datapoint_config,
+            )))
+        } else if let Some(external_script_name) = custom_datapoint_source_shell_cmd.clone() {
             Ok(RuntimeDataPointSource::ExternalScript(ExternalScript::new(
                 external_script_name.clone(),
             )))
@@ -62,6 +75,24 @@ impl RuntimeDataPointSource {
             }
         }
     }
+
+    /// Handle to the datapoint source fed by the connector (if used)
+    pub fn push_source(&self) -> Option<PushDataPointSource> {
+        match self {
+            RuntimeDataPointSource::Push(push) => Some(push.clone()),
+            RuntimeDataPointSource::Predefined(_) | RuntimeDataPointSource::ExternalScript(_) => {
+                None
+            }
+        }
+    }
+
+    /// Asks the connector for a fresh datapoint (new epoch), no-op for the other sources which
+    /// are read on demand
+    pub fn request_repost(&self) {
+        if let RuntimeDataPointSource::Push(push) = self {
+            push.repost_signal().request();
+        }
+    }
 }
 
 impl DataPointSource for RuntimeDataPointSource {
@@ -71,6 +102,7 @@ impl DataPointSource for RuntimeDataPointSource {
                 sync_fetch_predef_source_aggregated(predef)
             }
             RuntimeDataPointSource::ExternalScript(script) => script.get_datapoint(),
+            RuntimeDataPointSource::Push(push) => push.get_datapoint(),
         }
     }
 }
@@ -3,26 +3,16 @@ pub trait Asset: Clone + Copy + Send + Sync {}
 #[derive(Debug, Clone, Copy)]
 pub struct NanoErg {}
 
-#[derive(Debug, Clone, Copy)]
-pub struct Erg {}
-
 #[derive(Debug, Clone, Copy)]
 pub struct Usd {}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Btc {}
 
-impl Asset for Erg {}
 impl Asset for NanoErg {}
 impl Asset for Usd {}
 impl Asset for Btc {}
 
-impl Erg {
-    pub fn to_nanoerg(erg: f64) -> f64 {
-        erg * 1_000_000_000.0
-    }
-}
-
 impl NanoErg {
     /// Number of nanoErgs in a single Erg
     pub fn from_erg(erg: f64) -> f64 {
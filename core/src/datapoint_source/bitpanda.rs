@@ -4,9 +4,6 @@ use super::assets_exchange_rate::Usd;
 use super::erg_xau::KgAu;
 use super::DataPointSourceError;
 
-#[derive(Debug, Clone)]
-pub struct BitPanda {}
-
 #[cfg(not(test))]
 pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
     let url = "https://api.bitpanda.com/v1/ticker";
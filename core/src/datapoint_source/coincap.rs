@@ -4,9 +4,6 @@ use super::assets_exchange_rate::NanoErg;
 use super::assets_exchange_rate::Usd;
 use super::DataPointSourceError;
 
-#[derive(Debug, Clone)]
-pub struct CoinCap;
-
 #[cfg(not(test))]
 pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
     // see https://coincap.io/assets/ergo
@@ -0,0 +1,216 @@
+//! Connector protocol: an external connector pushes datapoints through the REST API and the core
+//! signals (via `/requireDatapointRepost`) when it needs a fresh one
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::Notify;
+
+use crate::oracle_types::Rate;
+
+use super::DataPointSource;
+use super::DataPointSourceError;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PushDataPointConfig {
+    /// Pushed datapoints older than this are not published
+    pub max_age_secs: u64,
+    /// Pushed datapoints with a timestamp further in the future than this are rejected
+    pub max_clock_skew_secs: u64,
+}
+
+impl Default for PushDataPointConfig {
+    fn default() -> Self {
+        Self {
+            max_age_secs: 300,
+            max_clock_skew_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PushDataPointError {
+    #[error("datapoint must be positive, got {0}")]
+    NonPositiveRate(i64),
+    #[error("datapoint is {age_secs}s old (max {max_age_secs}s)")]
+    TooOld { age_secs: u64, max_age_secs: u64 },
+    #[error("datapoint timestamp is {0}s in the future")]
+    InFuture(u64),
+}
+
+/// Repost request flag, consumed by the connector polling `/requireDatapointRepost`
+#[derive(Debug, Default)]
+pub struct RepostSignal {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl RepostSignal {
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+
+    /// Returns true (once) if a repost was requested
+    pub fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+
+    /// Long-poll: waits until a repost is requested or the timeout expires
+    pub async fn wait(&self, timeout: Duration) -> bool {
+        if self.take() {
+            return true;
+        }
+        let _ = tokio::time::timeout(timeout, self.notify.notified()).await;
+        self.take()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PushedDataPoint {
+    rate: Rate,
+    /// Unix timestamp (secs) of the datapoint
+    timestamp: u64,
+}
+
+/// Datapoint source fed by an external connector. Cloned handles share the latest datapoint.
+#[derive(Debug, Clone)]
+pub struct PushDataPointSource {
+    config: PushDataPointConfig,
+    latest: Arc<Mutex<Option<PushedDataPoint>>>,
+    repost_signal: Arc<RepostSignal>,
+}
+
+impl PushDataPointSource {
+    pub fn new(config: PushDataPointConfig) -> Self {
+        Self {
+            config,
+            latest: Arc::new(Mutex::new(None)),
+            repost_signal: Arc::new(RepostSignal::default()),
+        }
+    }
+
+    pub fn repost_signal(&self) -> &RepostSignal {
+        &self.repost_signal
+    }
+
+    /// Accepts a datapoint from the connector. The receive time is used if `timestamp` is not set.
+    pub fn push(&self, rate: i64, timestamp: Option<u64>) -> Result<(), PushDataPointError> {
+        self.push_at(rate, timestamp, unix_now())
+    }
+
+    fn push_at(
+        &self,
+        rate: i64,
+        timestamp: Option<u64>,
+        now: u64,
+    ) -> Result<(), PushDataPointError> {
+        if rate <= 0 {
+            return Err(PushDataPointError::NonPositiveRate(rate));
+        }
+        let timestamp = timestamp.unwrap_or(now);
+        if timestamp > now + self.config.max_clock_skew_secs {
+            return Err(PushDataPointError::InFuture(timestamp - now));
+        }
+        let age_secs = now.saturating_sub(timestamp);
+        if age_secs > self.config.max_age_secs {
+            return Err(PushDataPointError::TooOld {
+                age_secs,
+                max_age_secs: self.config.max_age_secs,
+            });
+        }
+        *self.latest.lock().unwrap() = Some(PushedDataPoint {
+            rate: rate.into(),
+            timestamp,
+        });
+        Ok(())
+    }
+
+    fn get_datapoint_at(&self, now: u64) -> Result<Rate, DataPointSourceError> {
+        let latest = *self.latest.lock().unwrap();
+        let res = match latest {
+            None => Err(DataPointSourceError::NoPushedDataPoint),
+            Some(datapoint) => {
+                let age_secs = now.saturating_sub(datapoint.timestamp);
+                if age_secs > self.config.max_age_secs {
+                    Err(DataPointSourceError::StalePushedDataPoint {
+                        age_secs,
+                        max_age_secs: self.config.max_age_secs,
+                    })
+                } else {
+                    Ok(datapoint.rate)
+                }
+            }
+        };
+        if res.is_err() {
+            self.repost_signal.request();
+        }
+        res
+    }
+}
+
+impl DataPointSource for PushDataPointSource {
+    fn get_datapoint(&self) -> Result<Rate, DataPointSourceError> {
+        self.get_datapoint_at(unix_now())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_validation() {
+        let source = PushDataPointSource::new(PushDataPointConfig::default());
+        assert_eq!(
+            source.push_at(0, None, 1000),
+            Err(PushDataPointError::NonPositiveRate(0))
+        );
+        assert_eq!(
+            source.push_at(1, Some(1100), 1000),
+            Err(PushDataPointError::InFuture(100))
+        );
+        assert_eq!(
+            source.push_at(1, Some(600), 1000),
+            Err(PushDataPointError::TooOld {
+                age_secs: 400,
+                max_age_secs: 300
+            })
+        );
+        assert!(source.push_at(1, Some(1030), 1000).is_ok());
+    }
+
+    #[test]
+    fn stale_datapoint_requests_repost() {
+        let source = PushDataPointSource::new(PushDataPointConfig::default());
+        assert!(matches!(
+            source.get_datapoint_at(1000),
+            Err(DataPointSourceError::NoPushedDataPoint)
+        ));
+        assert!(source.repost_signal().take());
+        assert!(!source.repost_signal().take());
+        source.push_at(42, None, 1000).unwrap();
+        assert_eq!(source.get_datapoint_at(1200).unwrap(), Rate::from(42));
+        assert!(!source.repost_signal().take());
+        assert!(matches!(
+            source.get_datapoint_at(1301),
+            Err(DataPointSourceError::StalePushedDataPoint { .. })
+        ));
+        assert!(source.repost_signal().take());
+    }
+}
@@ -0,0 +1,461 @@
+//! Typed events observed by the main loop, streamed to the clients of the `/events` endpoint
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::box_kind::BallotBox;
+use crate::monitor::HealthCheck;
+use crate::monitor::HealthStatus;
+use crate::monitor::OracleHealth;
+use crate::monitor::PoolHealth;
+use crate::oracle_state::LocalDatapointState;
+use crate::oracle_state::UpdateBoxSource;
+use crate::oracle_state::VoteBallotBoxesSource;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::oracle_types::Rate;
+use crate::snapshot::PoolSnapshot;
+use crate::state::PoolState;
+
+/// Events buffered per subscriber, a slow client skips the older ones
+const EVENT_BUFFER_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsensusFailure {
+    pub expected: i32,
+    pub found_num: i32,
+    pub found_oracle_addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OracleEvent {
+    NewPoolBox {
+        box_id: String,
+        epoch_counter: EpochCounter,
+        rate: Rate,
+        height: BlockHeight,
+    },
+    DatapointPosted {
+        epoch_id: EpochCounter,
+        height: BlockHeight,
+    },
+    DatapointCollected {
+        height: BlockHeight,
+    },
+    RefreshAttempted {
+        height: BlockHeight,
+    },
+    /// The refresh tx was accepted into the node's mempool
+    RefreshSubmitted {
+        tx_id: String,
+        height: BlockHeight,
+    },
+    /// The refresh tx was confirmed
+    RefreshSucceeded {
+        tx_id: String,
+        height: BlockHeight,
+    },
+    RefreshFailed {
+        height: BlockHeight,
+        reason: String,
+        consensus: Option<ConsensusFailure>,
+    },
+    PoolHealthChanged {
+        status: HealthStatus,
+        checks: Vec<HealthCheck>,
+    },
+    OracleHealthChanged {
+        status: HealthStatus,
+        checks: Vec<HealthCheck>,
+    },
+    BallotBoxesChanged {
+        ballot_box_ids: Vec<String>,
+    },
+    UpdateBoxChanged {
+        box_id: String,
+        height: BlockHeight,
+    },
+}
+
+impl OracleEvent {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            OracleEvent::NewPoolBox { .. } => "new_pool_box",
+            OracleEvent::DatapointPosted { .. } => "datapoint_posted",
+            OracleEvent::DatapointCollected { .. } => "datapoint_collected",
+            OracleEvent::RefreshAttempted { .. } => "refresh_attempted",
+            OracleEvent::RefreshSubmitted { .. } => "refresh_submitted",
+            OracleEvent::RefreshSucceeded { .. } => "refresh_succeeded",
+            OracleEvent::RefreshFailed { .. } => "refresh_failed",
+            OracleEvent::PoolHealthChanged { .. } => "pool_health_changed",
+            OracleEvent::OracleHealthChanged { .. } => "oracle_health_changed",
+            OracleEvent::BallotBoxesChanged { .. } => "ballot_boxes_changed",
+            OracleEvent::UpdateBoxChanged { .. } => "update_box_changed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventEnvelope {
+    pub id: u64,
+    /// Unix timestamp (secs) of the observation
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: OracleEvent,
+}
+
+/// Fan-out of the main loop events to the `/events` subscribers
+#[derive(Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<EventEnvelope>>,
+    next_id: AtomicU64,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        Self {
+            sender,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn publish(&self, event: OracleEvent) {
+        log::debug!("Event: {:?}", event);
+        let envelope = EventEnvelope {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            event,
+        };
+        // no subscribers is not an error
+        let _ = self.sender.send(Arc::new(envelope));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<EventEnvelope>> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Remembers what the main loop saw on the previous iteration and emits events for the changes.
+/// The first observation of each kind is the baseline and is not reported.
+#[derive(Debug, Default)]
+pub struct ChainObserver {
+    pool_box_id: Option<String>,
+    local_datapoint: Option<Option<LocalDatapointState>>,
+    ballot_box_ids: Option<BTreeSet<String>>,
+    update_box_id: Option<String>,
+    pool_health: Option<HealthStatus>,
+    oracle_health: Option<HealthStatus>,
+}
+
+impl ChainObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compares the snapshot and the ballot and update boxes with the previous observation and
+    /// publishes the changes. The boxes are read before any change is recorded, so a failed read
+    /// leaves the previous observation to compare with on the next call.
+    pub fn observe(
+        &mut self,
+        snapshot: &PoolSnapshot,
+        ballot_boxes_source: &dyn VoteBallotBoxesSource,
+        update_box_source: &dyn UpdateBoxSource,
+        events: &EventBus,
+    ) -> anyhow::Result<()> {
+        let ballot_box_ids = ballot_boxes_source
+            .get_ballot_boxes()?
+            .iter()
+            .map(|b| b.get_box().box_id().into())
+            .collect();
+        let update_box = update_box_source.get_update_box()?;
+        let new_pool_box = self.pool_box(
+            snapshot.pool_box_id.into(),
+            snapshot.pool_box_epoch_counter,
+            snapshot.pool_box_rate,
+            snapshot.pool_box_height,
+        );
+        let local_datapoint = match &snapshot.pool_state {
+            PoolState::LiveEpoch(live_epoch) => {
+                self.local_datapoint(live_epoch.local_datapoint_box_state.clone())
+            }
+            PoolState::NeedsBootstrap => None,
+        };
+        let ballot_boxes = self.ballot_boxes(ballot_box_ids);
+        let update_box = self.update_box(
+            update_box.get_box().box_id().into(),
+            BlockHeight(update_box.get_box().creation_height),
+        );
+        [new_pool_box, local_datapoint, ballot_boxes, update_box]
+            .into_iter()
+            .flatten()
+            .for_each(|event| events.publish(event));
+        Ok(())
+    }
+
+    /// `oracle_health` is `None` when observing the pool without being one of its oracles
+    pub fn health(
+        &mut self,
+        pool_health: &PoolHealth,
+        oracle_health: Option<&OracleHealth>,
+        events: &EventBus,
+    ) {
+        if changed(&mut self.pool_health, pool_health.status) {
+            events.publish(OracleEvent::PoolHealthChanged {
+                status: pool_health.status,
+                checks: pool_health.checks.clone(),
+            });
+        }
+        if let Some(oracle_health) = oracle_health {
+            if changed(&mut self.oracle_health, oracle_health.status) {
+                events.publish(OracleEvent::OracleHealthChanged {
+                    status: oracle_health.status,
+                    checks: oracle_health.checks.clone(),
+                });
+            }
+        }
+    }
+
+    fn pool_box(
+        &mut self,
+        box_id: String,
+        epoch_counter: EpochCounter,
+        rate: Rate,
+        height: BlockHeight,
+    ) -> Option<OracleEvent> {
+        changed(&mut self.pool_box_id, box_id.clone()).then_some(OracleEvent::NewPoolBox {
+            box_id,
+            epoch_counter,
+            rate,
+            height,
+        })
+    }
+
+    fn local_datapoint(&mut self, state: Option<LocalDatapointState>) -> Option<OracleEvent> {
+        if !changed(&mut self.local_datapoint, state.clone()) {
+            return None;
+        }
+        match state {
+            Some(LocalDatapointState::Posted { epoch_id, height }) => {
+                Some(OracleEvent::DatapointPosted { epoch_id, height })
+            }
+            Some(LocalDatapointState::Collected { height }) => {
+                Some(OracleEvent::DatapointCollected { height })
+            }
+            None => None,
+        }
+    }
+
+    fn ballot_boxes(&mut self, ballot_box_ids: BTreeSet<String>) -> Option<OracleEvent> {
+        changed(&mut self.ballot_box_ids, ballot_box_ids.clone()).then(|| {
+            OracleEvent::BallotBoxesChanged {
+                ballot_box_ids: ballot_box_ids.into_iter().collect(),
+            }
+        })
+    }
+
+    fn update_box(&mut self, box_id: String, height: BlockHeight) -> Option<OracleEvent> {
+        changed(&mut self.update_box_id, box_id.clone())
+            .then_some(OracleEvent::UpdateBoxChanged { box_id, height })
+    }
+}
+
+/// Stores the new value and returns true if it differs from a previously stored one
+fn changed<T: PartialEq>(last: &mut Option<T>, value: T) -> bool {
+    let changed = matches!(last, Some(last_value) if *last_value != value);
+    *last = Some(value);
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::Instant;
+
+    use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+    use sigma_test_util::force_any_val;
+
+    use crate::box_kind::UpdateBoxWrapper;
+    use crate::monitor::PoolHealthDetails;
+    use crate::oracle_state::DataSourceError;
+    use crate::oracle_types::EpochLength;
+    use crate::oracle_types::MinDatapoints;
+    use crate::pool_commands::test_utils::generate_token_ids;
+    use crate::pool_commands::test_utils::make_update_box;
+    use crate::pool_commands::test_utils::BallotBoxesMock;
+    use crate::pool_commands::test_utils::UpdateBoxMock;
+
+    use super::*;
+
+    /// Fails to find the update box on the first read
+    struct FlakyUpdateBoxSource {
+        update_box: UpdateBoxWrapper,
+        failed: Cell<bool>,
+    }
+
+    impl UpdateBoxSource for FlakyUpdateBoxSource {
+        fn get_update_box(&self) -> crate::oracle_state::Result<UpdateBoxWrapper> {
+            if self.failed.replace(true) {
+                Ok(self.update_box.clone())
+            } else {
+                Err(DataSourceError::UpdateBoxNotFoundError)
+            }
+        }
+    }
+
+    fn snapshot(pool_box_id: BoxId, height: BlockHeight) -> PoolSnapshot {
+        PoolSnapshot {
+            height,
+            taken_at: Instant::now(),
+            pool_box_id,
+            pool_box_height: height,
+            pool_box_epoch_counter: EpochCounter(1),
+            pool_box_rate: Rate::from(10),
+            pool_box_reward_tokens: 100,
+            pool_state: PoolState::NeedsBootstrap,
+            local_box_id: None,
+            local_reward_tokens: None,
+            buyback_reward_tokens: None,
+            posted_datapoints: vec![],
+            pool_health: PoolHealth {
+                status: HealthStatus::Ok,
+                checks: vec![],
+                details: PoolHealthDetails {
+                    pool_box_height: height,
+                    current_height: height,
+                    epoch_length: EpochLength(30),
+                    all_oracle_boxes: vec![],
+                    active_oracle_boxes: vec![],
+                    min_data_points: MinDatapoints(4),
+                    total_oracle_token_count: 0,
+                },
+            },
+            oracle_health: None,
+            fee_runway: None,
+            refresh_preview: None,
+        }
+    }
+
+    #[test]
+    fn observer_reports_changes_after_baseline() {
+        let mut observer = ChainObserver::new();
+        assert!(observer
+            .pool_box(
+                "a".to_string(),
+                EpochCounter(1),
+                Rate::from(10),
+                BlockHeight(100)
+            )
+            .is_none());
+        assert!(observer
+            .pool_box(
+                "a".to_string(),
+                EpochCounter(1),
+                Rate::from(10),
+                BlockHeight(100)
+            )
+            .is_none());
+        assert!(matches!(
+            observer.pool_box(
+                "b".to_string(),
+                EpochCounter(2),
+                Rate::from(11),
+                BlockHeight(130)
+            ),
+            Some(OracleEvent::NewPoolBox {
+                epoch_counter: EpochCounter(2),
+                ..
+            })
+        ));
+
+        assert!(observer.local_datapoint(None).is_none());
+        assert!(matches!(
+            observer.local_datapoint(Some(LocalDatapointState::Posted {
+                epoch_id: EpochCounter(2),
+                height: BlockHeight(131)
+            })),
+            Some(OracleEvent::DatapointPosted { .. })
+        ));
+        assert!(matches!(
+            observer.local_datapoint(Some(LocalDatapointState::Collected {
+                height: BlockHeight(140)
+            })),
+            Some(OracleEvent::DatapointCollected { .. })
+        ));
+    }
+
+    #[test]
+    fn change_is_reported_after_failed_box_read() {
+        let bus = EventBus::new();
+        let mut receiver = bus.subscribe();
+        let ballot_boxes = BallotBoxesMock {
+            ballot_boxes: vec![],
+        };
+        let update_box = make_update_box(BlockHeight(90), &generate_token_ids());
+        let mut observer = ChainObserver::new();
+        observer
+            .observe(
+                &snapshot(force_any_val::<BoxId>(), BlockHeight(100)),
+                &ballot_boxes,
+                &UpdateBoxMock {
+                    update_box: update_box.clone(),
+                },
+                &bus,
+            )
+            .unwrap();
+
+        let new_snapshot = snapshot(force_any_val::<BoxId>(), BlockHeight(130));
+        let flaky_update_box = FlakyUpdateBoxSource {
+            update_box,
+            failed: Cell::new(false),
+        };
+        assert!(observer
+            .observe(&new_snapshot, &ballot_boxes, &flaky_update_box, &bus)
+            .is_err());
+        assert!(receiver.try_recv().is_err());
+        observer
+            .observe(&new_snapshot, &ballot_boxes, &flaky_update_box, &bus)
+            .unwrap();
+        let envelope = receiver.try_recv().unwrap();
+        assert!(matches!(
+            envelope.event,
+            OracleEvent::NewPoolBox {
+                height: BlockHeight(130),
+                ..
+            }
+        ));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn events_are_tagged() {
+        let bus = EventBus::new();
+        let mut receiver = bus.subscribe();
+        bus.publish(OracleEvent::RefreshAttempted {
+            height: BlockHeight(100),
+        });
+        let envelope = receiver.try_recv().unwrap();
+        assert_eq!(envelope.event.name(), "refresh_attempted");
+        let json = serde_json::to_value(envelope.as_ref()).unwrap();
+        assert_eq!(json["type"], "refresh_attempted");
+        assert_eq!(json["height"], 100);
+        assert_eq!(json["id"], 0);
+    }
+}
@@ -2,15 +2,19 @@ use std::time::Duration;
 
 use ergo_lib::chain::transaction::Transaction;
 use ergo_lib::chain::transaction::TxId;
-use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
 use reqwest::blocking::RequestBuilder;
 use reqwest::blocking::Response;
 use reqwest::header::CONTENT_TYPE;
 use reqwest::Url;
+use serde::Deserialize;
 use thiserror::Error;
 use url::ParseError;
 
-use crate::oracle_config::ORACLE_CONFIG;
+use crate::node_interface::node_api::IndexedTransaction;
+use crate::oracle_config::OracleConfig;
 
 use self::explorer_url::default_explorer_api_url;
 use self::explorer_url::default_explorer_url;
@@ -64,13 +68,66 @@ impl ExplorerApi {
         log::debug!("get_transaction_v1 response: {}", text);
         Ok(serde_json::from_str(&text)?)
     }
+
+    /// GET /api/v1/transactions/{id}, only the fields of `IndexedTransaction`
+    pub fn get_indexed_transaction_v1(
+        &self,
+        tx_id: &TxId,
+    ) -> Result<IndexedTransaction, ExplorerApiError> {
+        let endpoint = "/api/v1/transactions/".to_owned() + &tx_id.to_string();
+        let response = self.send_get_req(&endpoint)?;
+        Ok(serde_json::from_str(&response.text()?)?)
+    }
+
+    /// GET /api/v1/boxes/unspent/byTokenId/{id}
+    pub fn get_unspent_boxes_by_token_id_v1(
+        &self,
+        token_id: &TokenId,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ErgoBox>, ExplorerApiError> {
+        let endpoint = format!(
+            "/api/v1/boxes/unspent/byTokenId/{}?offset={offset}&limit={limit}",
+            String::from(*token_id)
+        );
+        let response = self.send_get_req(&endpoint)?;
+        let items: Items<ErgoBox> = serde_json::from_str(&response.text()?)?;
+        Ok(items.items)
+    }
+
+    /// GET /api/v1/networkState, the height of the last block
+    pub fn get_height_v1(&self) -> Result<u32, ExplorerApiError> {
+        let response = self.send_get_req("/api/v1/networkState")?;
+        let network_state: NetworkState = serde_json::from_str(&response.text()?)?;
+        Ok(network_state.height)
+    }
+
+    /// GET /api/v1/boxes/{id}
+    pub fn get_box_v1(&self, box_id: &BoxId) -> Result<ErgoBox, ExplorerApiError> {
+        let endpoint = "/api/v1/boxes/".to_owned() + &String::from(*box_id);
+        let response = self.send_get_req(&endpoint)?;
+        let text = response.text()?;
+        log::debug!("get_box_v1 response: {}", text);
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+/// Paged list response
+#[derive(Debug, Deserialize)]
+struct Items<T> {
+    items: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NetworkState {
+    height: u32,
 }
 
-pub(crate) fn ergo_explorer_transaction_link(tx_id: TxId, prefix: NetworkPrefix) -> String {
-    let url = ORACLE_CONFIG
+pub(crate) fn ergo_explorer_transaction_link(oracle_config: &OracleConfig, tx_id: TxId) -> String {
+    let url = oracle_config
         .explorer_url
         .clone()
-        .unwrap_or_else(|| default_explorer_url(prefix));
+        .unwrap_or_else(|| default_explorer_url(oracle_config.oracle_address.network()));
     let tx_id_str = String::from(tx_id);
     url.join("en/transactions/")
         .unwrap()
@@ -79,14 +136,14 @@ pub(crate) fn ergo_explorer_transaction_link(tx_id: TxId, prefix: NetworkPrefix)
         .to_string()
 }
 
-pub fn wait_for_tx_confirmation(tx_id: TxId) {
-    wait_for_txs_confirmation(vec![tx_id]);
+pub fn wait_for_tx_confirmation(oracle_config: &OracleConfig, tx_id: TxId) {
+    wait_for_txs_confirmation(oracle_config, vec![tx_id]);
 }
 
-pub fn wait_for_txs_confirmation(tx_ids: Vec<TxId>) {
-    let network = ORACLE_CONFIG.oracle_address.network();
+pub fn wait_for_txs_confirmation(oracle_config: &OracleConfig, tx_ids: Vec<TxId>) {
+    let network = oracle_config.oracle_address.network();
     let timeout = Duration::from_secs(1200);
-    let explorer_url = ORACLE_CONFIG
+    let explorer_url = oracle_config
         .explorer_url
         .clone()
         .unwrap_or_else(|| default_explorer_api_url(network));
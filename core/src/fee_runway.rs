@@ -0,0 +1,156 @@
+//! Forecast of how long the oracle wallet can keep paying for publish and refresh transactions
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::context::OracleContext;
+use crate::oracle_state::OraclePool;
+use crate::oracle_types::EpochLength;
+use crate::wallet::WalletDataSource;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FeeRunwayConfig {
+    /// Warn when the wallet can pay for fewer epochs than this
+    pub warn_epochs: u64,
+    /// Report the runway as critical when the wallet can pay for fewer epochs than this
+    pub critical_epochs: u64,
+    /// Expected number of refresh txs paid by this oracle per epoch (1.0 is the worst case when
+    /// this oracle does every refresh)
+    pub expected_refreshes_per_epoch: f64,
+}
+
+impl Default for FeeRunwayConfig {
+    fn default() -> Self {
+        Self {
+            warn_epochs: 100,
+            critical_epochs: 20,
+            expected_refreshes_per_epoch: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Copy, Clone, PartialEq, Eq)]
+pub enum FeeRunwayStatus {
+    Ok,
+    Low,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeRunway {
+    pub status: FeeRunwayStatus,
+    pub spendable_nano_erg: u64,
+    /// ERG kept aside for the oracle box (min storage rent) if it is not created yet
+    pub reserved_nano_erg: u64,
+    pub cost_per_epoch_nano_erg: u64,
+    pub epochs_left: u64,
+    pub blocks_left: u64,
+}
+
+impl FeeRunway {
+    pub fn estimate(
+        spendable_nano_erg: u64,
+        reserved_nano_erg: u64,
+        tx_fee_nano_erg: u64,
+        epoch_length: EpochLength,
+        config: &FeeRunwayConfig,
+    ) -> Self {
+        let refresh_cost =
+            (tx_fee_nano_erg as f64 * config.expected_refreshes_per_epoch.max(0.0)).ceil() as u64;
+        let cost_per_epoch_nano_erg = (tx_fee_nano_erg + refresh_cost).max(1);
+        let epochs_left =
+            spendable_nano_erg.saturating_sub(reserved_nano_erg) / cost_per_epoch_nano_erg;
+        let status = if epochs_left < config.critical_epochs {
+            FeeRunwayStatus::Critical
+        } else if epochs_left < config.warn_epochs {
+            FeeRunwayStatus::Low
+        } else {
+            FeeRunwayStatus::Ok
+        };
+        FeeRunway {
+            status,
+            spendable_nano_erg,
+            reserved_nano_erg,
+            cost_per_epoch_nano_erg,
+            epochs_left,
+            blocks_left: epochs_left.saturating_mul(epoch_length.0.max(0) as u64),
+        }
+    }
+}
+
+pub fn check_fee_runway(
+    ctx: &OracleContext,
+    oracle_pool: &OraclePool,
+) -> Result<FeeRunway, anyhow::Error> {
+    let spendable_nano_erg = ctx
+        .node_api
+        .get_unspent_wallet_boxes()?
+        .iter()
+        .map(|b| *b.value.as_u64())
+        .sum::<u64>();
+    let reserved_nano_erg = if oracle_pool
+        .get_local_datapoint_box_source()
+        .get_local_oracle_datapoint_box()?
+        .is_none()
+    {
+        *oracle_pool
+            .pool_config()
+            .oracle_box_wrapper_inputs
+            .contract_inputs
+            .contract_parameters()
+            .min_storage_rent
+            .as_u64()
+    } else {
+        0
+    };
+    let epoch_length = oracle_pool
+        .pool_config()
+        .refresh_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters()
+        .epoch_length();
+    Ok(FeeRunway::estimate(
+        spendable_nano_erg,
+        reserved_nano_erg,
+        *ctx.base_fee.as_u64(),
+        epoch_length,
+        &ctx.oracle_config.fee_runway,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_runway() {
+        let runway = FeeRunway::estimate(
+            1_000_000_000,
+            0,
+            1_000_000,
+            EpochLength(30),
+            &FeeRunwayConfig::default(),
+        );
+        assert_eq!(runway.cost_per_epoch_nano_erg, 2_000_000);
+        assert_eq!(runway.epochs_left, 500);
+        assert_eq!(runway.blocks_left, 15000);
+        assert_eq!(runway.status, FeeRunwayStatus::Ok);
+    }
+
+    #[test]
+    fn estimate_runway_with_reserve_and_thresholds() {
+        let config = FeeRunwayConfig {
+            expected_refreshes_per_epoch: 0.5,
+            ..Default::default()
+        };
+        let runway =
+            FeeRunway::estimate(110_000_000, 20_000_000, 1_000_000, EpochLength(30), &config);
+        assert_eq!(runway.cost_per_epoch_nano_erg, 1_500_000);
+        assert_eq!(runway.epochs_left, 60);
+        assert_eq!(runway.status, FeeRunwayStatus::Low);
+        let runway =
+            FeeRunway::estimate(10_000_000, 20_000_000, 1_000_000, EpochLength(30), &config);
+        assert_eq!(runway.epochs_left, 0);
+        assert_eq!(runway.status, FeeRunwayStatus::Critical);
+    }
+}
@@ -0,0 +1,365 @@
+//! Bind address, TLS and access control shared by the REST API and metrics servers
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use axum::extract::ConnectInfo;
+use axum::extract::State;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::middleware;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// Drop the rate limit counters of the inactive clients once there are this many of them
+const MAX_RATE_LIMIT_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HttpServerConfig {
+    pub bind_address: IpAddr,
+    pub tls: Option<TlsConfig>,
+    /// Only the clients from these IPs/subnets (e.g. `127.0.0.1` or `10.0.0.0/8`) are allowed.
+    /// All clients are allowed if empty.
+    pub allowed_ips: Vec<IpNet>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub log_requests: bool,
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            tls: None,
+            allowed_ips: Vec::new(),
+            rate_limit: None,
+            log_requests: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TlsConfig {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+    /// How often to check the cert/key files for changes
+    #[serde(default = "default_tls_reload_interval_secs")]
+    pub reload_interval_secs: u64,
+}
+
+fn default_tls_reload_interval_secs() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateLimitConfig {
+    /// Max requests per minute from one IP to one route
+    pub requests_per_minute: u32,
+    /// Overrides of `requests_per_minute` by route path (e.g. `/poolStatus`)
+    #[serde(default)]
+    pub routes: HashMap<String, u32>,
+}
+
+#[derive(Debug, Error)]
+pub enum IpNetError {
+    #[error("invalid IP address: {0}")]
+    Address(String),
+    #[error("invalid prefix length: {0}")]
+    PrefixLength(String),
+}
+
+/// IP address with an optional prefix length (`10.0.0.0/8`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        // treat IPv4-mapped IPv6 client addresses as IPv4
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(*ip),
+            IpAddr::V4(_) => *ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V4(_), IpAddr::V6(_)) | (IpAddr::V6(_), IpAddr::V4(_)) => false,
+        }
+    }
+}
+
+fn prefix_eq(net: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = (prefix_len / 8) as usize;
+    let rem_bits = prefix_len % 8;
+    if net[..full_bytes] != ip[..full_bytes] {
+        return false;
+    }
+    if rem_bits == 0 {
+        return true;
+    }
+    let mask = 0xffu8 << (8 - rem_bits);
+    net[full_bytes] & mask == ip[full_bytes] & mask
+}
+
+impl FromStr for IpNet {
+    type Err = IpNetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr_str, prefix_str) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr = IpAddr::from_str(addr_str.trim())
+            .map_err(|_| IpNetError::Address(addr_str.to_string()))?;
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_str {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_prefix_len)
+                .ok_or_else(|| IpNetError::PrefixLength(prefix.to_string()))?,
+            None => max_prefix_len,
+        };
+        Ok(IpNet { addr, prefix_len })
+    }
+}
+
+impl std::fmt::Display for IpNet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl Serialize for IpNet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        IpNet::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Fixed window request counter per client IP and route
+#[derive(Debug)]
+struct RateLimiter {
+    config: RateLimitConfig,
+    windows: Mutex<HashMap<(IpAddr, String), (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn check(&self, ip: IpAddr, route: &str, now: Instant) -> bool {
+        let limit = self
+            .config
+            .routes
+            .get(route)
+            .copied()
+            .unwrap_or(self.config.requests_per_minute);
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > MAX_RATE_LIMIT_ENTRIES {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+        }
+        let (start, count) = windows.entry((ip, route.to_string())).or_insert((now, 0));
+        if now.duration_since(*start) >= RATE_LIMIT_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+struct AccessControl {
+    server_name: &'static str,
+    allowed_ips: Vec<IpNet>,
+    rate_limiter: Option<RateLimiter>,
+    log_requests: bool,
+}
+
+async fn access_control<B>(
+    State(access): State<Arc<AccessControl>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let remote_addr = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+    let response = match remote_addr {
+        Some(addr)
+            if !access.allowed_ips.is_empty()
+                && !access
+                    .allowed_ips
+                    .iter()
+                    .any(|net| net.contains(&addr.ip())) =>
+        {
+            StatusCode::FORBIDDEN.into_response()
+        }
+        Some(addr)
+            if access
+                .rate_limiter
+                .as_ref()
+                .map(|limiter| !limiter.check(addr.ip(), &path, Instant::now()))
+                .unwrap_or(false) =>
+        {
+            StatusCode::TOO_MANY_REQUESTS.into_response()
+        }
+        Some(_) | None => next.run(request).await,
+    };
+    if access.log_requests {
+        log::info!(
+            "{} request from {}: {} {} -> {} in {}ms",
+            access.server_name,
+            remote_addr
+                .map(|a| a.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            method,
+            path,
+            response.status().as_u16(),
+            started.elapsed().as_millis()
+        );
+    }
+    response
+}
+
+/// Serves the app with the bind address, TLS and access control from the config
+pub async fn serve(
+    app: Router,
+    port: u16,
+    config: &HttpServerConfig,
+    server_name: &'static str,
+) -> Result<(), anyhow::Error> {
+    let access = Arc::new(AccessControl {
+        server_name,
+        allowed_ips: config.allowed_ips.clone(),
+        rate_limiter: config.rate_limit.clone().map(RateLimiter::new),
+        log_requests: config.log_requests,
+    });
+    let app = app
+        .layer(middleware::from_fn_with_state(access, access_control))
+        .into_make_service_with_connect_info::<SocketAddr>();
+    let addr = SocketAddr::new(config.bind_address, port);
+    if let Some(tls) = &config.tls {
+        let rustls_config = RustlsConfig::from_pem_file(&tls.cert_file, &tls.key_file).await?;
+        tokio::spawn(reload_tls_on_change(rustls_config.clone(), tls.clone()));
+        log::info!("Starting {} on {} (TLS)", server_name, addr);
+        axum_server::bind_rustls(addr, rustls_config)
+            .serve(app)
+            .await?;
+    } else {
+        log::info!("Starting {} on {}", server_name, addr);
+        axum::Server::try_bind(&addr)?.serve(app).await?;
+    }
+    Ok(())
+}
+
+async fn reload_tls_on_change(rustls_config: RustlsConfig, tls: TlsConfig) {
+    let modified = |tls: &TlsConfig| -> Option<(SystemTime, SystemTime)> {
+        let cert = std::fs::metadata(&tls.cert_file).ok()?.modified().ok()?;
+        let key = std::fs::metadata(&tls.key_file).ok()?.modified().ok()?;
+        Some((cert, key))
+    };
+    let mut last_modified = modified(&tls);
+    let mut interval = tokio::time::interval(Duration::from_secs(tls.reload_interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        let current = modified(&tls);
+        if current.is_none() || current == last_modified {
+            continue;
+        }
+        match rustls_config
+            .reload_from_pem_file(&tls.cert_file, &tls.key_file)
+            .await
+        {
+            Ok(()) => {
+                log::info!("Reloaded TLS cert {}", tls.cert_file.display());
+                last_modified = current;
+            }
+            Err(e) => log::error!(
+                "Failed to reload TLS cert {}: {}",
+                tls.cert_file.display(),
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_net_contains() {
+        let net: IpNet = "10.1.0.0/16".parse().unwrap();
+        assert!(net.contains(&"10.1.200.3".parse().unwrap()));
+        assert!(!net.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(net.contains(&"::ffff:10.1.0.1".parse().unwrap()));
+        let net: IpNet = "192.168.1.128/25".parse().unwrap();
+        assert!(net.contains(&"192.168.1.200".parse().unwrap()));
+        assert!(!net.contains(&"192.168.1.100".parse().unwrap()));
+        let single: IpNet = "127.0.0.1".parse().unwrap();
+        assert!(single.contains(&"127.0.0.1".parse().unwrap()));
+        assert!(!single.contains(&"127.0.0.2".parse().unwrap()));
+        let v6: IpNet = "fd00::/8".parse().unwrap();
+        assert!(v6.contains(&"fd12::1".parse().unwrap()));
+        assert!(!v6.contains(&"10.1.0.1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("localhost".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn rate_limit_per_ip_and_route() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 2,
+            routes: [("/poolStatus".to_string(), 1)].into_iter().collect(),
+        });
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other_ip: IpAddr = "10.0.0.2".parse().unwrap();
+        let now = Instant::now();
+        assert!(limiter.check(ip, "/", now));
+        assert!(limiter.check(ip, "/", now));
+        assert!(!limiter.check(ip, "/", now));
+        assert!(limiter.check(other_ip, "/", now));
+        assert!(limiter.check(ip, "/poolStatus", now));
+        assert!(!limiter.check(ip, "/poolStatus", now));
+        assert!(limiter.check(ip, "/", now + RATE_LIMIT_WINDOW));
+    }
+}
@@ -0,0 +1,120 @@
+//! Reading and validating [EIP-23](https://github.com/ergoplatform/eips/blob/master/eip-0023.md)
+//! oracle pools.
+//!
+//! The `oracle-core` binary is built on top of this library. The modules and items documented here
+//! are the public API and follow semver. The items below only need a pool config and a node or
+//! explorer URL, and can be used by any application reading a pool:
+//!
+//! - [`PoolConfig`] loads a pool definition (`pool_config.yaml`) with the pool token ids
+//!   ([`TokenIds`]) and the contract parameters.
+//! - [`OraclePool`] gives the current pool, refresh, oracle, ballot and update boxes through the box
+//!   source traits ([`PoolBoxSource`], ...), validated against the pool contracts.
+//! - [`PublicChainReader`] fetches the unspent pool boxes through the node's blockchain indexer or
+//!   the explorer API, without a node API key or scans.
+//! - [`box_kind`] has the box wrappers used to parse a pool, refresh, oracle, ballot or update box
+//!   on its own.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use std::sync::Arc;
+//!
+//! use oracle_core::box_kind::PoolBox;
+//! use oracle_core::{OraclePool, PoolBoxSource, PoolConfig, PublicChainReader};
+//!
+//! # fn main() -> Result<(), anyhow::Error> {
+//! let pool_config = Arc::new(PoolConfig::load_from_file(Path::new("pool_config.yaml"))?);
+//! let reader = Arc::new(PublicChainReader::node_indexer(&"http://127.0.0.1:9053".parse()?));
+//! let token_boxes = reader.pool_token_boxes(&pool_config);
+//! let oracle_pool = OraclePool::with_token_boxes(pool_config, token_boxes, None);
+//! let pool_box = oracle_pool.get_pool_box_source().get_pool_box()?;
+//! println!("rate {} at epoch {}", pool_box.rate(), pool_box.epoch_counter().0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The items of these modules that read the oracle's own config (`oracle_config.yaml`, the node API
+//! key, the scan IDs in the data folder) through an `OracleContext` are used by the `oracle-core`
+//! binary only. They are hidden from the docs and may change in any release. The other modules are
+//! internal to the binary.
+
+// Coding conventions
+#![allow(clippy::redundant_clone)]
+#![allow(clippy::ptr_arg)]
+#![allow(clippy::unit_arg)]
+#![forbid(unsafe_code)]
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(unused_imports)]
+#![deny(clippy::wildcard_enum_match_arm)]
+#![deny(clippy::todo)]
+#![deny(clippy::unimplemented)]
+
+pub mod box_kind;
+pub mod contracts;
+pub mod oracle_state;
+pub mod oracle_types;
+pub mod pool_config;
+pub mod public_boxes;
+pub mod scans;
+pub mod spec_token;
+
+mod action_report;
+mod actions;
+mod address_util;
+mod admin_api;
+mod api;
+mod app;
+mod cli_commands;
+mod config_reload;
+mod context;
+mod datapoint_source;
+mod default_parameters;
+mod events;
+mod explorer_api;
+mod fee_runway;
+mod http_server;
+mod logging;
+mod metrics;
+mod migrate;
+mod monitor;
+mod node_interface;
+mod node_sync;
+mod notifier;
+mod observer;
+mod oracle_config;
+mod oracle_stats;
+mod pending_tx;
+mod pool_commands;
+mod pool_control;
+mod pool_history;
+mod pool_runtime;
+mod reorg;
+mod serde;
+mod snapshot;
+mod state;
+mod util;
+mod wallet;
+mod wallet_monitor;
+
+#[cfg(test)]
+mod tests;
+
+#[doc(hidden)]
+pub use app::run;
+
+pub use oracle_state::BuybackBoxSource;
+pub use oracle_state::DataSourceError;
+pub use oracle_state::OraclePool;
+pub use oracle_state::PoolBoxSource;
+pub use oracle_state::PoolTokenBoxes;
+pub use oracle_state::PostedDatapointBoxesSource;
+pub use oracle_state::RefreshBoxSource;
+pub use oracle_state::UpdateBoxSource;
+pub use oracle_state::VoteBallotBoxesSource;
+pub use pool_config::PoolConfig;
+pub use pool_config::TokenIds;
+pub use public_boxes::PublicChainReader;
+pub use scans::ScanError;
+pub use scans::ScanGetBoxes;
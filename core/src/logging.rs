@@ -10,12 +10,35 @@ use log4rs::config::Appender;
 use log4rs::config::Logger;
 use log4rs::config::Root;
 use log4rs::Config;
+use log4rs::Handle;
 
+/// Logs to stdout and `[data_dir]/oracle-core.log`. The returned handle is used to change the log
+/// level when the oracle config is reloaded.
 pub fn setup_log(
     cmdline_log_level: Option<LevelFilter>,
     config_log_level: Option<LevelFilter>,
     data_dir: &Path,
+) -> Handle {
+    let handle =
+        log4rs::init_config(log_config(cmdline_log_level, config_log_level, data_dir)).unwrap();
+    log_panics::init();
+    handle
+}
+
+pub fn set_log_level(
+    handle: &Handle,
+    cmdline_log_level: Option<LevelFilter>,
+    config_log_level: Option<LevelFilter>,
+    data_dir: &Path,
 ) {
+    handle.set_config(log_config(cmdline_log_level, config_log_level, data_dir));
+}
+
+fn log_config(
+    cmdline_log_level: Option<LevelFilter>,
+    config_log_level: Option<LevelFilter>,
+    data_dir: &Path,
+) -> Config {
     let stdout = ConsoleAppender::builder().build();
 
     // via https://stackoverflow.com/questions/56345288/how-do-i-use-log4rs-rollingfileappender-to-incorporate-rolling-logging#
@@ -44,7 +67,7 @@ pub fn setup_log(
         config_log_level
     };
 
-    let config = Config::builder()
+    Config::builder()
         .appender(Appender::builder().build("stdout", Box::new(stdout)))
         .appender(
             Appender::builder().build(
@@ -69,9 +92,5 @@ pub fn setup_log(
                 .appender("logfile")
                 .build(LevelFilter::Info),
         )
-        .unwrap();
-
-    let _ = log4rs::init_config(config).unwrap();
-
-    log_panics::init();
+        .unwrap()
 }
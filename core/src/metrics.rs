@@ -1,6 +1,5 @@
+use std::collections::HashMap;
 use std::convert::From;
-use std::net::SocketAddr;
-use std::sync::Arc;
 
 use axum::response::IntoResponse;
 use axum::response::Response;
@@ -8,7 +7,10 @@ use axum::routing::get;
 use axum::Router;
 use ergo_node_interface::scanning::NodeError;
 use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
 use prometheus::Encoder;
+use prometheus::GaugeVec;
+use prometheus::IntCounter;
 use prometheus::IntGauge;
 use prometheus::IntGaugeVec;
 use prometheus::Opts;
@@ -16,43 +18,22 @@ use prometheus::TextEncoder;
 use reqwest::StatusCode;
 use tower_http::cors::CorsLayer;
 
-use crate::box_kind::{OracleBox, PoolBox};
-use crate::monitor::check_oracle_health;
-use crate::monitor::check_pool_health;
+use crate::fee_runway::FeeRunway;
+use crate::http_server::serve;
+use crate::http_server::HttpServerConfig;
+use crate::monitor::HealthStatus;
 use crate::monitor::OracleHealth;
 use crate::monitor::PoolHealth;
-use crate::node_interface::node_api::NodeApi;
-use crate::oracle_config::ORACLE_CONFIG;
-use crate::oracle_config::ORACLE_SECRETS;
-use crate::oracle_state::OraclePool;
+use crate::node_sync::NodeSyncStatus;
+use crate::oracle_stats::OracleStats;
+use crate::snapshot::PoolSnapshot;
+use crate::wallet_monitor::WalletState;
 
-static POOL_BOX_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new("pool_box_height", "The height of the pool box")
-            .namespace("ergo")
-            .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
-
-static POOL_BOX_RATE: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new("pool_box_rate", "exchange rate from the pool box")
-            .namespace("ergo")
-            .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
-
-static POOL_BOX_REWARD_TOKEN_AMOUNT: Lazy<IntGauge> = Lazy::new(|| {
+static ORACLE_NODE_WALLET_BALANCE: Lazy<IntGauge> = Lazy::new(|| {
     let m = IntGauge::with_opts(
         Opts::new(
-            "pool_box_reward_token_amount",
-            "The amount of reward token in the pool box",
+            "oracle_node_wallet_nano_erg",
+            "Coins in the oracle's node wallet",
         )
         .namespace("ergo")
         .subsystem("oracle"),
@@ -62,33 +43,11 @@ static POOL_BOX_REWARD_TOKEN_AMOUNT: Lazy<IntGauge> = Lazy::new(|| {
     m
 });
 
-static CURRENT_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new("current_height", "The current height")
-            .namespace("ergo")
-            .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
-
-static EPOCH_LENGTH: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new("epoch_length", "The epoch length")
-            .namespace("ergo")
-            .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
-
-static POOL_IS_HEALTHY: Lazy<IntGauge> = Lazy::new(|| {
+static NODE_IS_SYNCED: Lazy<IntGauge> = Lazy::new(|| {
     let m = IntGauge::with_opts(
         Opts::new(
-            "pool_is_healthy",
-            "The health status of the pool, 1 for Ok and 0 for Down",
+            "node_is_synced",
+            "The sync status of the node, 1 for synced and 0 for catching up",
         )
         .namespace("ergo")
         .subsystem("oracle"),
@@ -98,11 +57,11 @@ static POOL_IS_HEALTHY: Lazy<IntGauge> = Lazy::new(|| {
     m
 });
 
-static ORACLE_IS_HEALTHY: Lazy<IntGauge> = Lazy::new(|| {
+static NODE_SYNC_LAG_BLOCKS: Lazy<IntGauge> = Lazy::new(|| {
     let m = IntGauge::with_opts(
         Opts::new(
-            "oracle_is_healthy",
-            "The health status of the oracle, 1 for Ok and 0 for Down",
+            "node_sync_lag_blocks",
+            "The number of blocks the node's full blocks height lags behind",
         )
         .namespace("ergo")
         .subsystem("oracle"),
@@ -112,264 +71,424 @@ static ORACLE_IS_HEALTHY: Lazy<IntGauge> = Lazy::new(|| {
     m
 });
 
-static MY_ORACLE_BOX_HEIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
-    let m = IntGaugeVec::new(
-        Opts::new(
-            "oracle_box_height",
-            "The height of the posted/collected oracle box for this oracle",
-        )
-        .namespace("ergo")
-        .subsystem("oracle"),
-        &["box_type"],
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
-
-static MY_ORACLE_CLAIMABLE_REWARDS: Lazy<IntGauge> = Lazy::new(|| {
+static WALLET_IS_UNLOCKED: Lazy<IntGauge> = Lazy::new(|| {
     let m = IntGauge::with_opts(
         Opts::new(
-            "oracle_claimable_rewards",
-            "The amount of claimable rewards for this oracle",
-        )
-        .namespace("ergo")
-        .subsystem("oracle"),
-    )
-    .unwrap();
-
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
-
-static ALL_ORACLE_BOX_HEIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
-    let m = IntGaugeVec::new(
-        Opts::new(
-            "all_oracle_box_height",
-            "The height of the posted/collected oracle box for all oracles",
+            "wallet_is_unlocked",
+            "The lock status of the oracle's node wallet, 1 for unlocked and 0 for locked",
         )
         .namespace("ergo")
         .subsystem("oracle"),
-        &["box_type", "oracle_address"],
     )
     .unwrap();
     prometheus::register(Box::new(m.clone())).expect("Failed to register");
     m
 });
 
-static ALL_ORACLE_CLAIMABLE_REWARDS: Lazy<IntGaugeVec> = Lazy::new(|| {
-    let m = IntGaugeVec::new(
+static EXPLORER_FALLBACK_READS: Lazy<IntCounter> = Lazy::new(|| {
+    let m = IntCounter::with_opts(
         Opts::new(
-            "all_oracle_claimable_rewards",
-            "The amount of claimable rewards for all oracles",
+            "explorer_fallback_reads",
+            "The number of box reads served by the explorer API because the node's boxes looked inconsistent",
         )
         .namespace("ergo")
         .subsystem("oracle"),
-        &["oracle_address"],
     )
     .unwrap();
     prometheus::register(Box::new(m.clone())).expect("Failed to register");
     m
 });
 
-static ACTIVE_ORACLE_BOX_HEIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
-    let m = IntGaugeVec::new(
+static WALLET_IS_RESCANNING: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
         Opts::new(
-            "active_oracle_box_height",
-            "The height of the posted/collected oracle boxes of active oracles",
+            "wallet_is_rescanning",
+            "1 if the oracle's node wallet is rescanning, 0 otherwise",
         )
         .namespace("ergo")
         .subsystem("oracle"),
-        &["box_type", "oracle_address"],
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
-
-static ACTIVE_ORACLE_COUNT: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new("active_oracle_count", "The number of active oracles")
-            .namespace("ergo")
-            .subsystem("oracle"),
     )
     .unwrap();
     prometheus::register(Box::new(m.clone())).expect("Failed to register");
     m
 });
 
-static TOTAL_ORACLE_COUNT: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new("total_oracle_count", "The total number of oracle tokens")
-            .namespace("ergo")
-            .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
+/// Metrics of one pool. In the multi-pool mode each pool's metrics carry a `pool` label.
+pub struct PoolMetrics {
+    const_labels: HashMap<String, String>,
+    pool_box_height: IntGauge,
+    pool_box_rate: IntGauge,
+    pool_box_reward_token_amount: IntGauge,
+    current_height: IntGauge,
+    epoch_length: IntGauge,
+    pool_is_healthy: IntGauge,
+    pool_health_status: IntGauge,
+    all_oracle_box_height: IntGaugeVec,
+    all_oracle_claimable_rewards: IntGaugeVec,
+    active_oracle_box_height: IntGaugeVec,
+    active_oracle_count: IntGauge,
+    total_oracle_count: IntGauge,
+    required_oracle_count: IntGauge,
+    oracle_epochs_participated: IntGaugeVec,
+    oracle_epochs_filtered_out: IntGaugeVec,
+    oracle_collections: IntGaugeVec,
+    oracle_reward_tokens_earned: IntGaugeVec,
+    oracle_average_deviation: GaugeVec,
+    last_reorg_depth: IntGauge,
+    /// Registered on the first buyback box
+    reward_tokens_in_buyback_box: OnceCell<IntGauge>,
+    /// Registered on the first snapshot with our oracle's health (not when observing the pool)
+    oracle: OnceCell<OracleMetrics>,
+}
 
-static REQUIRED_ORACLE_COUNT: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new(
-            "required_oracle_count",
-            "The minimum number of active oracles",
-        )
-        .namespace("ergo")
-        .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
+/// Metrics of our oracle in the pool
+struct OracleMetrics {
+    oracle_is_healthy: IntGauge,
+    oracle_health_status: IntGauge,
+    my_oracle_box_height: IntGaugeVec,
+    my_oracle_claimable_rewards: IntGauge,
+    fee_runway_epochs: IntGauge,
+    wallet_spendable_balance: IntGauge,
+}
 
-static ORACLE_NODE_WALLET_BALANCE: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new(
-            "oracle_node_wallet_nano_erg",
-            "Coins in the oracle's node wallet",
-        )
-        .namespace("ergo")
-        .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
+impl PoolMetrics {
+    /// Registers the metrics of the pool, labelled with `pool_name` if set
+    pub fn new(pool_name: Option<&str>) -> Self {
+        let const_labels: HashMap<String, String> = pool_name
+            .map(|name| ("pool".to_string(), name.to_string()))
+            .into_iter()
+            .collect();
+        let labels = &const_labels;
+        Self {
+            pool_box_height: int_gauge("pool_box_height", "The height of the pool box", labels),
+            pool_box_rate: int_gauge("pool_box_rate", "exchange rate from the pool box", labels),
+            pool_box_reward_token_amount: int_gauge(
+                "pool_box_reward_token_amount",
+                "The amount of reward token in the pool box",
+                labels,
+            ),
+            current_height: int_gauge("current_height", "The current height", labels),
+            epoch_length: int_gauge("epoch_length", "The epoch length", labels),
+            pool_is_healthy: int_gauge(
+                "pool_is_healthy",
+                "The health status of the pool, 1 for Ok or Degraded and 0 for Down",
+                labels,
+            ),
+            pool_health_status: int_gauge(
+                "pool_health_status",
+                "The health status of the pool, 2 for Ok, 1 for Degraded and 0 for Down",
+                labels,
+            ),
+            all_oracle_box_height: int_gauge_vec(
+                "all_oracle_box_height",
+                "The height of the posted/collected oracle box for all oracles",
+                &["box_type", "oracle_address"],
+                labels,
+            ),
+            all_oracle_claimable_rewards: int_gauge_vec(
+                "all_oracle_claimable_rewards",
+                "The amount of claimable rewards for all oracles",
+                &["oracle_address"],
+                labels,
+            ),
+            active_oracle_box_height: int_gauge_vec(
+                "active_oracle_box_height",
+                "The height of the posted/collected oracle boxes of active oracles",
+                &["box_type", "oracle_address"],
+                labels,
+            ),
+            active_oracle_count: int_gauge(
+                "active_oracle_count",
+                "The number of active oracles",
+                labels,
+            ),
+            total_oracle_count: int_gauge(
+                "total_oracle_count",
+                "The total number of oracle tokens",
+                labels,
+            ),
+            required_oracle_count: int_gauge(
+                "required_oracle_count",
+                "The minimum number of active oracles",
+                labels,
+            ),
+            oracle_epochs_participated: int_gauge_vec(
+                "epochs_participated",
+                "The number of refreshed epochs with the oracle's datapoint included",
+                &["oracle_address"],
+                labels,
+            ),
+            oracle_epochs_filtered_out: int_gauge_vec(
+                "epochs_filtered_out",
+                "The number of refreshed epochs with the oracle's datapoint filtered out as an outlier",
+                &["oracle_address"],
+                labels,
+            ),
+            oracle_collections: int_gauge_vec(
+                "collections",
+                "The number of refresh txs sent by the oracle",
+                &["oracle_address"],
+                labels,
+            ),
+            oracle_reward_tokens_earned: int_gauge_vec(
+                "reward_tokens_earned",
+                "The amount of reward tokens earned by the oracle",
+                &["oracle_address"],
+                labels,
+            ),
+            oracle_average_deviation: gauge_vec(
+                "average_deviation_percent",
+                "The average deviation (%) of the oracle's datapoints from the pool rate",
+                &["oracle_address"],
+                labels,
+            ),
+            last_reorg_depth: int_gauge(
+                "last_reorg_depth",
+                "The number of blocks rolled back by the last chain reorganization",
+                labels,
+            ),
+            reward_tokens_in_buyback_box: OnceCell::new(),
+            oracle: OnceCell::new(),
+            const_labels,
+        }
+    }
 
-static REWARD_TOKENS_IN_BUYBACK_BOX: Lazy<IntGauge> = Lazy::new(|| {
-    let m = IntGauge::with_opts(
-        Opts::new(
-            "reward_tokens_in_buyback_box",
-            "The amount of reward tokens in the buyback box",
-        )
-        .namespace("ergo")
-        .subsystem("oracle"),
-    )
-    .unwrap();
-    prometheus::register(Box::new(m.clone())).expect("Failed to register");
-    m
-});
+    fn oracle(&self) -> &OracleMetrics {
+        self.oracle.get_or_init(|| {
+            let labels = &self.const_labels;
+            OracleMetrics {
+                oracle_is_healthy: int_gauge(
+                    "oracle_is_healthy",
+                    "The health status of the oracle, 1 for Ok or Degraded and 0 for Down",
+                    labels,
+                ),
+                oracle_health_status: int_gauge(
+                    "oracle_health_status",
+                    "The health status of the oracle, 2 for Ok, 1 for Degraded and 0 for Down",
+                    labels,
+                ),
+                my_oracle_box_height: int_gauge_vec(
+                    "oracle_box_height",
+                    "The height of the posted/collected oracle box for this oracle",
+                    &["box_type"],
+                    labels,
+                ),
+                my_oracle_claimable_rewards: int_gauge(
+                    "oracle_claimable_rewards",
+                    "The amount of claimable rewards for this oracle",
+                    labels,
+                ),
+                fee_runway_epochs: int_gauge(
+                    "fee_runway_epochs",
+                    "The estimated number of epochs the oracle's wallet can pay tx fees for",
+                    labels,
+                ),
+                wallet_spendable_balance: int_gauge(
+                    "wallet_spendable_nano_erg",
+                    "Coins in the unspent boxes of the oracle's node wallet",
+                    labels,
+                ),
+            }
+        })
+    }
 
-fn update_pool_health(pool_health: &PoolHealth) {
-    POOL_BOX_HEIGHT.set(pool_health.details.pool_box_height.into());
-    CURRENT_HEIGHT.set(pool_health.details.current_height.into());
-    EPOCH_LENGTH.set(pool_health.details.epoch_length.into());
-    POOL_IS_HEALTHY.set(pool_health.status as i64);
-    for oracle in &pool_health.details.all_oracle_boxes {
-        let box_type = oracle.box_height.label_name();
-        let box_height = oracle.box_height.oracle_box_height().into();
-        ALL_ORACLE_BOX_HEIGHT
-            .with_label_values(&[box_type, &oracle.address.to_base58()])
-            .set(box_height);
+    pub fn reorg(&self, depth: u32) {
+        self.last_reorg_depth.set(depth as i64);
     }
-    for oracle in &pool_health.details.active_oracle_boxes {
-        let box_type = oracle.box_height.label_name();
-        let box_height = oracle.box_height.oracle_box_height().into();
-        ACTIVE_ORACLE_BOX_HEIGHT
-            .with_label_values(&[box_type, &oracle.address.to_base58()])
-            .set(box_height);
+
+    /// Metrics of the snapshot. Only the pool part is set when observing the pool (`monitor`
+    /// command)
+    pub fn update(&self, snapshot: &PoolSnapshot) {
+        self.pool_box_rate.set(snapshot.pool_box_rate.into());
+        self.update_pool_health(&snapshot.pool_health);
+        self.pool_box_reward_token_amount
+            .set(snapshot.pool_box_reward_tokens as i64);
+        if let Some(buyback_reward_tokens) = snapshot.buyback_reward_tokens {
+            self.reward_tokens_in_buyback_box
+                .get_or_init(|| {
+                    int_gauge(
+                        "reward_tokens_in_buyback_box",
+                        "The amount of reward tokens in the buyback box",
+                        &self.const_labels,
+                    )
+                })
+                .set(buyback_reward_tokens as i64);
+        }
+        self.update_oracle_claimable_reward_tokens(&snapshot.pool_health);
+        if let Some(oracle_health) = &snapshot.oracle_health {
+            self.update_oracle_health(oracle_health);
+        }
+        if let Some(fee_runway) = &snapshot.fee_runway {
+            self.update_fee_runway(fee_runway);
+        }
+        if let Some(local_reward_tokens) = snapshot.local_reward_tokens {
+            self.update_my_claimable_reward_tokens(local_reward_tokens);
+        }
     }
-    ACTIVE_ORACLE_COUNT.set(pool_health.details.active_oracle_boxes.len() as i64);
-    REQUIRED_ORACLE_COUNT.set(pool_health.details.min_data_points.into());
-    TOTAL_ORACLE_COUNT.set(pool_health.details.total_oracle_token_count as i64);
-}
 
-fn update_oracle_health(oracle_health: &OracleHealth) {
-    let box_type = oracle_health.details.box_details.label_name();
-    MY_ORACLE_BOX_HEIGHT
-        .with_label_values(&[box_type])
-        .set(oracle_health.details.box_details.oracle_box_height().into());
-    ORACLE_IS_HEALTHY.set(oracle_health.status as i64);
-}
+    fn update_pool_health(&self, pool_health: &PoolHealth) {
+        self.pool_box_height
+            .set(pool_health.details.pool_box_height.into());
+        self.current_height
+            .set(pool_health.details.current_height.into());
+        self.epoch_length
+            .set(pool_health.details.epoch_length.into());
+        self.pool_is_healthy
+            .set((pool_health.status != HealthStatus::Down) as i64);
+        self.pool_health_status.set(pool_health.status as i64);
+        for oracle in &pool_health.details.all_oracle_boxes {
+            let box_type = oracle.box_height.label_name();
+            let box_height = oracle.box_height.oracle_box_height().into();
+            self.all_oracle_box_height
+                .with_label_values(&[box_type, &oracle.address.to_base58()])
+                .set(box_height);
+        }
+        for oracle in &pool_health.details.active_oracle_boxes {
+            let box_type = oracle.box_height.label_name();
+            let box_height = oracle.box_height.oracle_box_height().into();
+            self.active_oracle_box_height
+                .with_label_values(&[box_type, &oracle.address.to_base58()])
+                .set(box_height);
+        }
+        self.active_oracle_count
+            .set(pool_health.details.active_oracle_boxes.len() as i64);
+        self.required_oracle_count
+            .set(pool_health.details.min_data_points.into());
+        self.total_oracle_count
+            .set(pool_health.details.total_oracle_token_count as i64);
+    }
 
-fn update_reward_tokens_in_buyback_box(oracle_pool: Arc<OraclePool>) {
-    if let Some(buyback_box) = oracle_pool
-        .get_buyback_box_source()
-        .map(|s| s.get_buyback_box())
-        .transpose()
-        .ok()
-        .flatten()
-        .flatten()
-    {
-        let reward_token_amount: i64 = buyback_box
-            .reward_token()
-            .map(|t| t.amount.into())
-            .unwrap_or(0);
-        REWARD_TOKENS_IN_BUYBACK_BOX.set(reward_token_amount);
+    fn update_oracle_health(&self, oracle_health: &OracleHealth) {
+        let oracle = self.oracle();
+        if let Some(box_details) = &oracle_health.details.box_details {
+            oracle
+                .my_oracle_box_height
+                .with_label_values(&[box_details.label_name()])
+                .set(box_details.oracle_box_height().into());
+        }
+        oracle
+            .oracle_is_healthy
+            .set((oracle_health.status != HealthStatus::Down) as i64);
+        oracle.oracle_health_status.set(oracle_health.status as i64);
     }
-}
 
-fn update_oracle_claimable_reward_tokens(pool_health: &PoolHealth) {
-    for oracle in &pool_health.details.all_oracle_boxes {
-        let reward_tokens = oracle.reward_tokens;
+    fn update_fee_runway(&self, fee_runway: &FeeRunway) {
+        let oracle = self.oracle();
+        oracle.fee_runway_epochs.set(fee_runway.epochs_left as i64);
+        oracle
+            .wallet_spendable_balance
+            .set(fee_runway.spendable_nano_erg as i64);
+    }
 
-        if reward_tokens > 0 {
-            let claimable_tokens = reward_tokens - 1;
-            ALL_ORACLE_CLAIMABLE_REWARDS
-                .with_label_values(&[&oracle.address.to_base58()])
-                .set(claimable_tokens as i64);
-        } else {
-            ALL_ORACLE_CLAIMABLE_REWARDS
-                .with_label_values(&[&oracle.address.to_base58()])
-                .set(0);
+    fn update_oracle_claimable_reward_tokens(&self, pool_health: &PoolHealth) {
+        for oracle in &pool_health.details.all_oracle_boxes {
+            let reward_tokens = oracle.reward_tokens;
+
+            if reward_tokens > 0 {
+                let claimable_tokens = reward_tokens - 1;
+                self.all_oracle_claimable_rewards
+                    .with_label_values(&[&oracle.address.to_base58()])
+                    .set(claimable_tokens as i64);
+            } else {
+                self.all_oracle_claimable_rewards
+                    .with_label_values(&[&oracle.address.to_base58()])
+                    .set(0);
+            }
         }
     }
-}
 
-fn update_my_claimable_reward_tokens(oracle_pool: Arc<OraclePool>) {
-    if let Some(oracle_box) = oracle_pool
-        .get_local_datapoint_box_source()
-        .get_local_oracle_datapoint_box()
-        .ok()
-        .flatten()
-    {
-        let num_tokens = *oracle_box.reward_token().amount.as_u64();
+    fn update_my_claimable_reward_tokens(&self, num_tokens: u64) {
+        let claimable_rewards = &self.oracle().my_oracle_claimable_rewards;
         if num_tokens == 0 {
-            MY_ORACLE_CLAIMABLE_REWARDS.set(num_tokens as i64)
+            claimable_rewards.set(num_tokens as i64)
         } else {
             let claimable_tokens = num_tokens - 1;
-            MY_ORACLE_CLAIMABLE_REWARDS.set(claimable_tokens as i64)
+            claimable_rewards.set(claimable_tokens as i64)
+        }
+    }
+
+    /// Per-oracle stats over the last epochs, updated on each new pool box
+    pub fn update_oracle_stats(&self, stats: &[OracleStats]) {
+        for oracle in stats {
+            let labels = [oracle.address.as_str()];
+            self.oracle_epochs_participated
+                .with_label_values(&labels)
+                .set(oracle.epochs_participated as i64);
+            self.oracle_epochs_filtered_out
+                .with_label_values(&labels)
+                .set(oracle.epochs_filtered_out as i64);
+            self.oracle_collections
+                .with_label_values(&labels)
+                .set(oracle.collections as i64);
+            self.oracle_reward_tokens_earned
+                .with_label_values(&labels)
+                .set(oracle.reward_tokens_earned as i64);
+            if let Some(deviation) = oracle.average_deviation_percent {
+                self.oracle_average_deviation
+                    .with_label_values(&labels)
+                    .set(deviation);
+            }
         }
     }
 }
 
-pub fn update_metrics(oracle_pool: Arc<OraclePool>) -> Result<(), anyhow::Error> {
-    let node_api = NodeApi::new(
-        ORACLE_SECRETS.node_api_key.clone(),
-        ORACLE_SECRETS.wallet_password.clone(),
-        &ORACLE_CONFIG.node_url,
-    );
-    let current_height = (node_api.node.current_block_height()? as u32).into();
-    let network_prefix = node_api.get_change_address()?.network();
-    let pool_box = &oracle_pool.get_pool_box_source().get_pool_box()?;
-    {
-        let rate = pool_box.rate();
-        POOL_BOX_RATE.set(rate.into());
-    };
-    let pool_box_height = pool_box.get_box().creation_height.into();
-    let pool_health = check_pool_health(
-        current_height,
-        pool_box_height,
-        pool_box.rate(),
-        oracle_pool.clone(),
-        network_prefix,
-    )?;
-    update_pool_health(&pool_health);
-    let oracle_health = check_oracle_health(
-        oracle_pool.clone(),
-        pool_box_height,
-        current_height,
-        pool_health.details.epoch_length,
-    )?;
-    update_oracle_health(&oracle_health);
-    let wallet_balance: i64 = node_api.node.wallet_nano_ergs_balance()? as i64;
-    ORACLE_NODE_WALLET_BALANCE.set(wallet_balance);
-    POOL_BOX_REWARD_TOKEN_AMOUNT.set(pool_box.reward_token().amount.into());
-    update_reward_tokens_in_buyback_box(oracle_pool.clone());
-    update_my_claimable_reward_tokens(oracle_pool);
-    update_oracle_claimable_reward_tokens(&pool_health);
-    Ok(())
+fn opts(name: &str, help: &str, const_labels: &HashMap<String, String>) -> Opts {
+    Opts::new(name, help)
+        .namespace("ergo")
+        .subsystem("oracle")
+        .const_labels(const_labels.clone())
+}
+
+fn int_gauge(name: &str, help: &str, const_labels: &HashMap<String, String>) -> IntGauge {
+    let m = IntGauge::with_opts(opts(name, help, const_labels)).unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+}
+
+fn int_gauge_vec(
+    name: &str,
+    help: &str,
+    label_names: &[&str],
+    const_labels: &HashMap<String, String>,
+) -> IntGaugeVec {
+    let m = IntGaugeVec::new(opts(name, help, const_labels), label_names).unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+}
+
+fn gauge_vec(
+    name: &str,
+    help: &str,
+    label_names: &[&str],
+    const_labels: &HashMap<String, String>,
+) -> GaugeVec {
+    let m = GaugeVec::new(opts(name, help, const_labels), label_names).unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+}
+
+fn update_wallet_state(wallet_state: &WalletState) {
+    WALLET_IS_UNLOCKED.set(wallet_state.unlocked as i64);
+    WALLET_IS_RESCANNING.set(wallet_state.rescanning as i64);
+    if let Some(balance) = wallet_state.balance_nano_erg {
+        ORACLE_NODE_WALLET_BALANCE.set(balance as i64);
+    }
+}
+
+fn update_node_sync(node_sync_status: &NodeSyncStatus) {
+    NODE_IS_SYNCED.set(node_sync_status.is_synced as i64);
+    NODE_SYNC_LAG_BLOCKS.set(node_sync_status.lag_blocks as i64);
+}
+
+/// Metrics of the node and its wallet, shared by all pools
+pub fn update_node_metrics(node_sync_status: &NodeSyncStatus, wallet_state: &WalletState) {
+    update_node_sync(node_sync_status);
+    update_wallet_state(wallet_state);
+}
+
+/// Counts a box read served by the explorer API, see [`crate::public_boxes::FallbackBoxes`]
+pub fn inc_explorer_fallback_reads() {
+    EXPLORER_FALLBACK_READS.inc();
 }
 
 async fn serve_metrics() -> impl IntoResponse {
@@ -386,18 +505,16 @@ async fn serve_metrics() -> impl IntoResponse {
         .unwrap()
 }
 
-pub async fn start_metrics_server(port_num: u16) -> Result<(), anyhow::Error> {
+pub async fn start_metrics_server(
+    port_num: u16,
+    server_config: &HttpServerConfig,
+) -> Result<(), anyhow::Error> {
     let app = Router::new().route("/metrics", get(serve_metrics)).layer(
         CorsLayer::new()
             .allow_origin(tower_http::cors::Any)
             .allow_methods([axum::http::Method::GET]),
     );
-    let addr = SocketAddr::from(([0, 0, 0, 0], port_num));
-    log::info!("Starting metrics server on {}", addr);
-    axum::Server::try_bind(&addr)?
-        .serve(app.into_make_service())
-        .await?;
-    Ok(())
+    serve(app, port_num, server_config, "metrics server").await
 }
 
 struct MetricsError(String);
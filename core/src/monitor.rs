@@ -7,17 +7,47 @@ use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
 use crate::box_kind::CollectedOracleBox;
 use crate::box_kind::OracleBoxWrapper;
 use crate::box_kind::PostedOracleBox;
+use crate::node_sync::NodeSyncStatus;
 use crate::oracle_state::DataSourceError;
 use crate::oracle_state::OraclePool;
 use crate::oracle_types::BlockHeight;
 use crate::oracle_types::EpochLength;
 use crate::oracle_types::MinDatapoints;
 use crate::oracle_types::Rate;
-use crate::pool_config::POOL_CONFIG;
+use crate::wallet_monitor::WalletState;
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Pool is Down if the pool box is older than epoch length plus this number of blocks
+    pub pool_box_delay_blocks: u32,
+    /// Pool is Degraded if the pool box has fewer reward tokens than this
+    pub min_pool_reward_tokens: u64,
+    /// Pool is Degraded if the number of active oracles is less than `min_data_points` plus this
+    /// margin (and Down if it is less than `min_data_points`)
+    pub active_oracles_margin: u32,
+    /// Oracle is Degraded if the wallet balance is below this
+    pub min_wallet_balance_nano_erg: u64,
+    /// Oracle is Degraded if there was no successful action for this long (or since the start)
+    pub max_secs_since_last_action: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            pool_box_delay_blocks: 3,
+            min_pool_reward_tokens: 100,
+            active_oracles_margin: 1,
+            min_wallet_balance_nano_erg: 100_000_000,
+            max_secs_since_last_action: 2 * 60 * 60,
+        }
+    }
+}
 
 #[derive(Debug, serde::Serialize, Copy, Clone, PartialEq, Eq)]
 pub enum HealthStatus {
-    Ok = 1,
+    Ok = 2,
+    Degraded = 1,
     Down = 0,
 }
 
@@ -29,6 +59,15 @@ impl HealthStatus {
             HealthStatus::Down
         }
     }
+
+    /// The worst status of the given checks (Ok if there are none)
+    fn worst_of(checks: &[HealthCheck]) -> Self {
+        checks
+            .iter()
+            .map(|c| c.status)
+            .min_by_key(|s| s.get_integer_value())
+            .unwrap_or(HealthStatus::Ok)
+    }
 }
 
 impl HealthStatus {
@@ -37,6 +76,28 @@ impl HealthStatus {
     }
 }
 
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub status: HealthStatus,
+    pub reason: Option<String>,
+}
+
+impl HealthCheck {
+    fn new(name: &'static str, status: HealthStatus, reason: impl FnOnce() -> String) -> Self {
+        let reason = if status == HealthStatus::Ok {
+            None
+        } else {
+            Some(reason())
+        };
+        HealthCheck {
+            name,
+            status,
+            reason,
+        }
+    }
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct PoolHealthDetails {
     pub pool_box_height: BlockHeight,
@@ -58,17 +119,21 @@ pub struct OracleDetails {
 #[derive(Debug, serde::Serialize)]
 pub struct PoolHealth {
     pub status: HealthStatus,
+    pub checks: Vec<HealthCheck>,
     pub details: PoolHealthDetails,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn check_pool_health(
     current_height: BlockHeight,
     pool_box_height: BlockHeight,
     pool_box_rate: Rate,
+    pool_box_reward_tokens: u64,
     oracle_pool: Arc<OraclePool>,
     network_prefix: NetworkPrefix,
+    config: &HealthConfig,
 ) -> Result<PoolHealth, anyhow::Error> {
-    let pool_conf = &POOL_CONFIG;
+    let pool_conf = oracle_pool.pool_config().clone();
     let epoch_length = pool_conf
         .refresh_box_wrapper_inputs
         .contract_inputs
@@ -76,41 +141,114 @@ pub fn check_pool_health(
         .epoch_length()
         .0
         .into();
-    let acceptable_pool_box_delay_blocks = 3;
-    let is_healthy = pool_box_height >= current_height - epoch_length - acceptable_pool_box_delay_blocks
-        // on bootstrap pool box created with rate 0
-        && pool_box_rate != 0;
+    let min_data_points = pool_conf
+        .refresh_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters()
+        .min_data_points();
+    let pool_box_check = pool_box_age_check(
+        current_height,
+        pool_box_height,
+        pool_box_rate,
+        epoch_length,
+        config.pool_box_delay_blocks,
+    );
+    let is_pool_box_fresh = pool_box_check.status == HealthStatus::Ok;
     let total_oracle_token_count = oracle_pool.get_total_oracle_token_count()?;
     let all_oracles = get_all_oracle_boxes(oracle_pool, network_prefix)?;
     let active_oracles = get_active_oracle_boxes(
         &all_oracles,
         pool_box_height,
-        is_healthy,
+        is_pool_box_fresh,
         current_height,
         epoch_length,
     );
+    let checks = vec![
+        pool_box_check,
+        active_oracles_check(
+            active_oracles.len(),
+            min_data_points,
+            config.active_oracles_margin,
+        ),
+        pool_reward_tokens_check(pool_box_reward_tokens, config.min_pool_reward_tokens),
+    ];
     Ok(PoolHealth {
-        status: if is_healthy {
-            HealthStatus::Ok
-        } else {
-            HealthStatus::Down
-        },
+        status: HealthStatus::worst_of(&checks),
+        checks,
         details: PoolHealthDetails {
             pool_box_height,
             current_height,
             epoch_length,
             all_oracle_boxes: all_oracles,
             active_oracle_boxes: active_oracles,
-            min_data_points: pool_conf
-                .refresh_box_wrapper_inputs
-                .contract_inputs
-                .contract_parameters()
-                .min_data_points(),
+            min_data_points,
             total_oracle_token_count,
         },
     })
 }
 
+fn pool_box_age_check(
+    current_height: BlockHeight,
+    pool_box_height: BlockHeight,
+    pool_box_rate: Rate,
+    epoch_length: EpochLength,
+    pool_box_delay_blocks: u32,
+) -> HealthCheck {
+    let min_height = BlockHeight(
+        current_height
+            .0
+            .saturating_sub(epoch_length.0.max(0) as u32)
+            .saturating_sub(pool_box_delay_blocks),
+    );
+    // on bootstrap pool box created with rate 0
+    let is_healthy = pool_box_height >= min_height && pool_box_rate != 0;
+    HealthCheck::new("pool_box_age", HealthStatus::from_bool(is_healthy), || {
+        if pool_box_rate == 0 {
+            "pool box has rate 0 (no refresh since bootstrap)".to_string()
+        } else {
+            format!(
+                "pool box height {} is below {} (current height {}, epoch length {}, delay {} blocks)",
+                pool_box_height, min_height, current_height, epoch_length.0, pool_box_delay_blocks
+            )
+        }
+    })
+}
+
+fn active_oracles_check(
+    active_oracles: usize,
+    min_data_points: MinDatapoints,
+    margin: u32,
+) -> HealthCheck {
+    let required = min_data_points.0.max(0) as usize;
+    let status = if active_oracles < required {
+        HealthStatus::Down
+    } else if active_oracles < required + margin as usize {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Ok
+    };
+    HealthCheck::new("active_oracles", status, || {
+        format!(
+            "{} active oracles, min data points is {}, margin {}",
+            active_oracles, required, margin
+        )
+    })
+}
+
+fn pool_reward_tokens_check(reward_tokens: u64, min_reward_tokens: u64) -> HealthCheck {
+    let status = if reward_tokens < min_reward_tokens {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Ok
+    };
+    HealthCheck::new("pool_reward_tokens", status, || {
+        format!(
+            "{} reward tokens left in the pool box (threshold {})",
+            reward_tokens, min_reward_tokens
+        )
+    })
+}
+
 pub fn get_all_oracle_boxes(
     oracle_pool: Arc<OraclePool>,
     network_prefix: NetworkPrefix,
@@ -169,6 +307,7 @@ pub fn get_active_oracle_boxes(
 #[derive(Debug, serde::Serialize)]
 pub struct OracleHealth {
     pub status: HealthStatus,
+    pub checks: Vec<HealthCheck>,
     pub details: OracleHealthDetails,
 }
 
@@ -209,43 +348,180 @@ impl From<CollectedOracleBox> for OracleBoxDetails {
 #[derive(Debug, serde::Serialize)]
 pub struct OracleHealthDetails {
     pub pool_box_height: BlockHeight,
-    pub box_details: OracleBoxDetails,
+    /// `None` until the oracle posts its first datapoint
+    pub box_details: Option<OracleBoxDetails>,
+    pub node_sync: NodeSyncStatus,
+    pub wallet: WalletState,
+    /// `None` in read-only mode, where the oracle takes no actions
+    pub secs_since_last_action: Option<u64>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn check_oracle_health(
     oracle_pool: Arc<OraclePool>,
     pool_box_height: BlockHeight,
     current_height: BlockHeight,
     epoch_length: EpochLength,
+    node_sync: NodeSyncStatus,
+    wallet: WalletState,
+    secs_since_last_action: Option<u64>,
+    config: &HealthConfig,
 ) -> Result<OracleHealth, anyhow::Error> {
-    let min_healthy_height = current_height - epoch_length;
-    let health = match oracle_pool
+    let box_details: Option<OracleBoxDetails> = oracle_pool
         .get_local_datapoint_box_source()
         .get_local_oracle_datapoint_box()?
-        .ok_or_else(|| anyhow::anyhow!("Oracle box not found"))?
-    {
-        OracleBoxWrapper::Posted(posted_box) => {
-            let posted_box_height = posted_box.get_box().creation_height.into();
-            let is_healthy = posted_box_height > min_healthy_height;
-            OracleHealth {
-                status: HealthStatus::from_bool(is_healthy),
-                details: OracleHealthDetails {
-                    pool_box_height,
-                    box_details: OracleBoxDetails::PostedBox(posted_box_height),
-                },
-            }
-        }
-        OracleBoxWrapper::Collected(collected_box) => {
-            let collected_box_height = collected_box.get_box().creation_height.into();
-            let is_healthy = collected_box_height > min_healthy_height;
-            OracleHealth {
-                status: HealthStatus::from_bool(is_healthy),
-                details: OracleHealthDetails {
-                    pool_box_height,
-                    box_details: OracleBoxDetails::CollectedBox(collected_box_height),
-                },
-            }
-        }
+        .map(|oracle_box| match oracle_box {
+            OracleBoxWrapper::Posted(posted_box) => posted_box.into(),
+            OracleBoxWrapper::Collected(collected_box) => collected_box.into(),
+        });
+    let mut checks = vec![
+        oracle_box_age_check(box_details.as_ref(), current_height, epoch_length),
+        node_sync_check(&node_sync),
+        wallet_check(&wallet),
+        wallet_balance_check(&wallet, config.min_wallet_balance_nano_erg),
+    ];
+    if let Some(secs) = secs_since_last_action {
+        checks.push(last_action_check(secs, config.max_secs_since_last_action));
+    }
+    Ok(OracleHealth {
+        status: HealthStatus::worst_of(&checks),
+        checks,
+        details: OracleHealthDetails {
+            pool_box_height,
+            box_details,
+            node_sync,
+            wallet,
+            secs_since_last_action,
+        },
+    })
+}
+
+fn oracle_box_age_check(
+    box_details: Option<&OracleBoxDetails>,
+    current_height: BlockHeight,
+    epoch_length: EpochLength,
+) -> HealthCheck {
+    // a new oracle has its oracle token in the wallet until it posts the first datapoint
+    let Some(box_details) = box_details else {
+        return HealthCheck::new("oracle_box_age", HealthStatus::Degraded, || {
+            "no oracle box yet, the first datapoint is not posted".to_string()
+        });
     };
-    Ok(health)
+    let min_healthy_height = current_height - epoch_length;
+    let box_height = box_details.oracle_box_height();
+    HealthCheck::new(
+        "oracle_box_age",
+        HealthStatus::from_bool(box_height > min_healthy_height),
+        || {
+            format!(
+                "{} oracle box height {} is not above {}",
+                box_details.label_name(),
+                box_height,
+                min_healthy_height
+            )
+        },
+    )
+}
+
+fn node_sync_check(node_sync: &NodeSyncStatus) -> HealthCheck {
+    HealthCheck::new(
+        "node_sync",
+        HealthStatus::from_bool(node_sync.is_synced),
+        || {
+            node_sync
+                .reason
+                .clone()
+                .unwrap_or_else(|| format!("node is {} blocks behind", node_sync.lag_blocks))
+        },
+    )
+}
+
+fn wallet_check(wallet: &WalletState) -> HealthCheck {
+    HealthCheck::new("wallet", HealthStatus::from_bool(wallet.is_ready()), || {
+        wallet
+            .reason
+            .clone()
+            .unwrap_or_else(|| "wallet is not ready".to_string())
+    })
+}
+
+fn wallet_balance_check(wallet: &WalletState, min_balance_nano_erg: u64) -> HealthCheck {
+    // balance is unknown while the wallet is locked, which is reported by the wallet check
+    let status = match wallet.balance_nano_erg {
+        Some(balance) if balance < min_balance_nano_erg => HealthStatus::Degraded,
+        Some(_) | None => HealthStatus::Ok,
+    };
+    HealthCheck::new("wallet_balance", status, || {
+        format!(
+            "wallet balance {} nanoERG is below {} nanoERG",
+            wallet.balance_nano_erg.unwrap_or(0),
+            min_balance_nano_erg
+        )
+    })
+}
+
+fn last_action_check(secs_since_last_action: u64, max_secs: u64) -> HealthCheck {
+    let status = if secs_since_last_action > max_secs {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Ok
+    };
+    HealthCheck::new("last_action", status, || {
+        format!(
+            "no successful action for {}s (threshold {}s)",
+            secs_since_last_action, max_secs
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_box_age() {
+        let check = |pool_box_height: u32, rate: i64| {
+            pool_box_age_check(
+                BlockHeight(100),
+                BlockHeight(pool_box_height),
+                rate.into(),
+                EpochLength(30),
+                3,
+            )
+            .status
+        };
+        assert_eq!(check(67, 1), HealthStatus::Ok);
+        assert_eq!(check(66, 1), HealthStatus::Down);
+        assert_eq!(check(90, 0), HealthStatus::Down);
+    }
+
+    #[test]
+    fn active_oracles_thresholds() {
+        assert_eq!(
+            active_oracles_check(3, MinDatapoints(4), 1).status,
+            HealthStatus::Down
+        );
+        assert_eq!(
+            active_oracles_check(4, MinDatapoints(4), 1).status,
+            HealthStatus::Degraded
+        );
+        let check = active_oracles_check(5, MinDatapoints(4), 1);
+        assert_eq!(check.status, HealthStatus::Ok);
+        assert_eq!(check.reason, None);
+    }
+
+    #[test]
+    fn worst_status_wins() {
+        let checks = vec![
+            pool_reward_tokens_check(10, 100),
+            last_action_check(10, 100),
+        ];
+        assert_eq!(HealthStatus::worst_of(&checks), HealthStatus::Degraded);
+        let checks = vec![
+            pool_reward_tokens_check(10, 100),
+            active_oracles_check(0, MinDatapoints(4), 1),
+        ];
+        assert_eq!(HealthStatus::worst_of(&checks), HealthStatus::Down);
+        assert_eq!(HealthStatus::worst_of(&[]), HealthStatus::Ok);
+    }
 }
@@ -1,7 +1,8 @@
 use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
 use ergo_lib::{
     chain::transaction::{unsigned::UnsignedTransaction, Transaction, TxId, TxIoVec},
-    ergotree_ir::chain::ergo_box::ErgoBox,
+    ergotree_ir::chain::ergo_box::{BoxId, ErgoBox},
 };
 use ergo_node_interface::node_interface::{NodeError, NodeInterface};
 use log::debug;
@@ -24,6 +25,25 @@ pub trait SignTransactionWithInputs {
     ) -> Result<Transaction>;
 }
 
+/// Where a submitted tx is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    Confirmed,
+    /// In the mempool
+    Unconfirmed,
+    /// Neither in a block nor in the mempool (never submitted, dropped or double spent)
+    Unknown,
+}
+
+pub trait GetTxStatus {
+    fn get_tx_status(&self, tx_id: &TxId) -> std::result::Result<TxStatus, NodeApiError>;
+}
+
+pub trait GetBoxStatus {
+    /// Whether the box can be spent by a new tx (see [`NodeApi::is_box_unspent`])
+    fn is_box_unspent(&self, box_id: &BoxId) -> std::result::Result<bool, NodeApiError>;
+}
+
 pub trait SignTransaction {
     fn sign_transaction(&self, unsigned_tx: &UnsignedTransaction) -> Result<Transaction>;
 }
@@ -63,6 +83,24 @@ impl SignTransactionWithInputs for NodeInterface {
     }
 }
 
+impl GetTxStatus for NodeApi {
+    fn get_tx_status(&self, tx_id: &TxId) -> std::result::Result<TxStatus, NodeApiError> {
+        if self.is_wallet_tx_confirmed(tx_id)? {
+            Ok(TxStatus::Confirmed)
+        } else if self.is_tx_unconfirmed(tx_id)? {
+            Ok(TxStatus::Unconfirmed)
+        } else {
+            Ok(TxStatus::Unknown)
+        }
+    }
+}
+
+impl GetBoxStatus for NodeApi {
+    fn is_box_unspent(&self, box_id: &BoxId) -> std::result::Result<bool, NodeApiError> {
+        NodeApi::is_box_unspent(self, box_id)
+    }
+}
+
 pub fn try_ensure_wallet_unlocked(node: &NodeApi) {
     let unlocked = node.node.wallet_status().unwrap().unlocked;
 
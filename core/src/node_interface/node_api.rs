@@ -3,15 +3,20 @@ use ergo_lib::chain::transaction::TxId;
 use ergo_lib::ergotree_ir::chain::address::AddressEncoder;
 use ergo_lib::ergotree_ir::chain::address::AddressEncoderError;
 use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
 use ergo_node_interface::scanning::NodeError;
 use ergo_node_interface::NodeInterface;
 use ergo_node_interface::ScanId;
 use log::info;
 use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use serde_json::json;
 use thiserror::Error;
 
+use crate::oracle_types::BlockHeight;
 use crate::scans::ScanID;
 use crate::wallet::WalletDataError;
 use crate::wallet::WalletDataSource;
@@ -97,6 +102,110 @@ impl NodeApi {
         Ok(self.node.submit_transaction(&signed_tx)?)
     }
 
+    /// GET /info
+    pub fn get_node_info(&self) -> Result<NodeInfo, NodeApiError> {
+        self.get_json("/info")
+    }
+
+    /// Timestamp (in milliseconds) of the best header known to the node
+    pub fn get_last_header_timestamp(&self) -> Result<Option<u64>, NodeApiError> {
+        let headers: Vec<NodeHeader> = self.get_json("/blocks/lastHeaders/1")?;
+        Ok(headers.first().map(|h| h.timestamp))
+    }
+
+    /// Whether the tx is in the node's mempool
+    pub fn is_tx_unconfirmed(&self, tx_id: &TxId) -> Result<bool, NodeApiError> {
+        let endpoint = format!("/transactions/unconfirmed/byTransactionId/{}", tx_id);
+        let res = self.node.send_get_req(&endpoint)?;
+        Ok(res.status().is_success())
+    }
+
+    /// Whether the box is in the UTXO set or created by a tx of the mempool, and not spent by a tx
+    /// of the mempool
+    pub fn is_box_unspent(&self, box_id: &BoxId) -> Result<bool, NodeApiError> {
+        let endpoint = format!("/utxo/withPool/byId/{}", box_id);
+        let res = self.node.send_get_req(&endpoint)?;
+        Ok(res.status().is_success())
+    }
+
+    /// Whether the tx is in a block, as known by the node wallet (the tx must spend or create a
+    /// box of the wallet)
+    pub fn is_wallet_tx_confirmed(&self, tx_id: &TxId) -> Result<bool, NodeApiError> {
+        let endpoint = format!("/wallet/transactionById?id={}", tx_id);
+        let res = self.node.send_get_req(&endpoint)?;
+        if !res.status().is_success() {
+            return Ok(false);
+        }
+        let wallet_tx: WalletTransaction = serde_json::from_str(
+            &res.text()
+                .map_err(|e| NodeApiError::UnexpectedResponse(e.to_string()))?,
+        )
+        .map_err(|e| NodeApiError::UnexpectedResponse(e.to_string()))?;
+        Ok(wallet_tx.num_confirmations > 0)
+    }
+
+    /// Height indexed by the node's blockchain indexer, `None` if the indexer is disabled
+    pub fn get_indexed_height(&self) -> Result<Option<u32>, NodeApiError> {
+        let res = self.node.send_get_req("/blockchain/indexedHeight")?;
+        if !res.status().is_success() {
+            return Ok(None);
+        }
+        let indexed_height: IndexedHeight = serde_json::from_str(
+            &res.text()
+                .map_err(|e| NodeApiError::UnexpectedResponse(e.to_string()))?,
+        )
+        .map_err(|e| NodeApiError::UnexpectedResponse(e.to_string()))?;
+        Ok(Some(indexed_height.indexed_height))
+    }
+
+    /// GET /scan/listAll
+    pub fn list_scans(&self) -> Result<Vec<NodeScan>, NodeApiError> {
+        self.get_json("/scan/listAll")
+    }
+
+    /// GET /blockchain/box/byId/{boxId} (requires the blockchain indexer)
+    pub fn get_indexed_box(&self, box_id: &BoxId) -> Result<ErgoBox, NodeApiError> {
+        self.get_json(&format!("/blockchain/box/byId/{}", String::from(*box_id)))
+    }
+
+    /// GET /blockchain/transaction/byId/{txId} (requires the blockchain indexer)
+    pub fn get_indexed_transaction(
+        &self,
+        tx_id: &TxId,
+    ) -> Result<IndexedTransaction, NodeApiError> {
+        self.get_json(&format!("/blockchain/transaction/byId/{}", tx_id))
+    }
+
+    /// GET /blockchain/box/unspent/byTokenId/{tokenId} (requires the blockchain indexer)
+    pub fn get_unspent_boxes_by_token_id(
+        &self,
+        token_id: &TokenId,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ErgoBox>, NodeApiError> {
+        self.get_json(&format!(
+            "/blockchain/box/unspent/byTokenId/{}?offset={offset}&limit={limit}",
+            String::from(*token_id)
+        ))
+    }
+
+    /// Sends a GET request and parses the JSON response into `T`
+    fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, NodeApiError> {
+        let res = self.node.send_get_req(endpoint)?;
+        let status = res.status();
+        let text = res
+            .text()
+            .map_err(|e| NodeApiError::UnexpectedResponse(e.to_string()))?;
+        if !status.is_success() {
+            return Err(NodeApiError::NodeInterfaceError(NodeError::BadRequest(
+                text,
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| {
+            NodeApiError::UnexpectedResponse(format!("{endpoint} response {text}: {e}"))
+        })
+    }
+
     /// Unlock wallet
     pub fn wallet_unlock(&self, password: &str) -> Result<bool, NodeApiError> {
         let endpoint = "/wallet/unlock";
@@ -117,6 +226,107 @@ impl NodeApi {
     }
 }
 
+/// Subset of the node's `/info` response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub headers_height: Option<u32>,
+    pub full_height: Option<u32>,
+    pub max_peer_height: Option<u32>,
+    pub peers_count: Option<u32>,
+    pub best_full_header_id: Option<String>,
+}
+
+/// Block of the node's chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockRef {
+    pub id: String,
+    pub height: BlockHeight,
+}
+
+/// Tip of the node's chain
+pub trait ChainTipSource {
+    /// Best full block, `None` until the node has applied any block
+    fn best_full_block(&self) -> Result<Option<BlockRef>, NodeApiError>;
+}
+
+/// Last blocks of the node's chain
+pub trait RecentBlocksSource {
+    /// Last `count` blocks of the best chain, oldest first
+    fn recent_blocks(&self, count: u32) -> Result<Vec<BlockRef>, NodeApiError>;
+}
+
+/// Scan registered on the node
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeScan {
+    pub scan_id: u64,
+    pub scan_name: String,
+    pub tracking_rule: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NodeHeader {
+    id: String,
+    height: u32,
+    timestamp: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletTransaction {
+    num_confirmations: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexedHeight {
+    indexed_height: u32,
+}
+
+/// Subset of a confirmed transaction as returned by the node's blockchain indexer and the explorer
+/// API
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedTransaction {
+    pub id: TxId,
+    pub inputs: Vec<IndexedInput>,
+    pub outputs: Vec<ErgoBox>,
+    pub inclusion_height: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedInput {
+    pub box_id: BoxId,
+}
+
+impl ChainTipSource for NodeApi {
+    fn best_full_block(&self) -> Result<Option<BlockRef>, NodeApiError> {
+        let info = self.get_node_info()?;
+        Ok(match (info.best_full_header_id, info.full_height) {
+            (Some(id), Some(height)) => Some(BlockRef {
+                id,
+                height: BlockHeight(height),
+            }),
+            _ => None,
+        })
+    }
+}
+
+impl RecentBlocksSource for NodeApi {
+    fn recent_blocks(&self, count: u32) -> Result<Vec<BlockRef>, NodeApiError> {
+        let headers: Vec<NodeHeader> = self.get_json(&format!("/blocks/lastHeaders/{count}"))?;
+        Ok(headers
+            .into_iter()
+            .map(|h| BlockRef {
+                id: h.id,
+                height: BlockHeight(h.height),
+            })
+            .collect())
+    }
+}
+
 impl WalletDataSource for NodeApi {
     fn get_unspent_wallet_boxes(&self) -> Result<Vec<ErgoBox>, WalletDataError> {
         self.node.unspent_boxes().map_err(Into::into)
@@ -137,4 +347,6 @@ pub enum NodeApiError {
     NoChangeAddressSetInNode,
     #[error("invalid scan id: {0}")]
     InvalidScanId(String),
+    #[error("unexpected node response: {0}")]
+    UnexpectedResponse(String),
 }
@@ -0,0 +1,165 @@
+//! Guard against acting on a stale view of the chain while the node is catching up
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+use crate::node_interface::node_api::NodeInfo;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NodeSyncConfig {
+    /// Max number of blocks the node's full blocks height may lag behind its headers height
+    /// (and the peers height if `check_peer_height` is set)
+    pub max_lag_blocks: u32,
+    /// Also compare with the max height reported by the node's peers
+    pub check_peer_height: bool,
+    /// Consider the node out of sync if the best header is older than this (wall clock)
+    pub max_last_block_age_secs: Option<u64>,
+    /// Max number of blocks the node wallet may lag behind the node's full blocks height before
+    /// it is considered rescanning (the wallet applies each new block shortly after the node)
+    pub max_wallet_lag_blocks: u32,
+}
+
+impl Default for NodeSyncConfig {
+    fn default() -> Self {
+        Self {
+            max_lag_blocks: 2,
+            check_peer_height: true,
+            max_last_block_age_secs: None,
+            max_wallet_lag_blocks: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSyncStatus {
+    pub is_synced: bool,
+    pub headers_height: u32,
+    pub full_height: u32,
+    pub max_peer_height: Option<u32>,
+    pub lag_blocks: u32,
+    pub last_block_age_secs: Option<u64>,
+    pub reason: Option<String>,
+}
+
+impl NodeSyncStatus {
+    pub fn evaluate(
+        info: &NodeInfo,
+        last_block_age_secs: Option<u64>,
+        config: &NodeSyncConfig,
+    ) -> Self {
+        let headers_height = info.headers_height.unwrap_or(0);
+        let full_height = info.full_height.unwrap_or(0);
+        let target_height = match info.max_peer_height {
+            Some(peer_height) if config.check_peer_height => headers_height.max(peer_height),
+            _ => headers_height,
+        };
+        let lag_blocks = target_height.saturating_sub(full_height);
+        let reason = if full_height == 0 {
+            Some("node has no full blocks".to_string())
+        } else if lag_blocks > config.max_lag_blocks {
+            Some(format!(
+                "full blocks height {full_height} lags behind {target_height} by {lag_blocks} blocks (max {})",
+                config.max_lag_blocks
+            ))
+        } else {
+            match (last_block_age_secs, config.max_last_block_age_secs) {
+                (Some(age), Some(max_age)) if age > max_age => Some(format!(
+                    "last block is {age} seconds old (max {max_age} seconds)"
+                )),
+                _ => None,
+            }
+        };
+        NodeSyncStatus {
+            is_synced: reason.is_none(),
+            headers_height,
+            full_height,
+            max_peer_height: info.max_peer_height,
+            lag_blocks,
+            last_block_age_secs,
+            reason,
+        }
+    }
+}
+
+pub fn check_node_sync(
+    node_api: &NodeApi,
+    config: &NodeSyncConfig,
+) -> Result<NodeSyncStatus, NodeApiError> {
+    let info = node_api.get_node_info()?;
+    let last_block_age_secs = if config.max_last_block_age_secs.is_some() {
+        node_api
+            .get_last_header_timestamp()?
+            .map(|timestamp_ms| now_millis().saturating_sub(timestamp_ms) / 1000)
+    } else {
+        None
+    };
+    Ok(NodeSyncStatus::evaluate(&info, last_block_age_secs, config))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_info(headers: u32, full: u32, peers: Option<u32>) -> NodeInfo {
+        NodeInfo {
+            headers_height: Some(headers),
+            full_height: Some(full),
+            max_peer_height: peers,
+            peers_count: Some(10),
+            best_full_header_id: None,
+        }
+    }
+
+    #[test]
+    fn synced_node() {
+        let status =
+            NodeSyncStatus::evaluate(&node_info(100, 99, Some(100)), None, &Default::default());
+        assert!(status.is_synced);
+        assert_eq!(status.lag_blocks, 1);
+    }
+
+    #[test]
+    fn full_blocks_lag_behind_headers() {
+        let status =
+            NodeSyncStatus::evaluate(&node_info(100, 90, Some(100)), None, &Default::default());
+        assert!(!status.is_synced);
+        assert_eq!(status.lag_blocks, 10);
+    }
+
+    #[test]
+    fn peers_ahead_of_node() {
+        let status =
+            NodeSyncStatus::evaluate(&node_info(100, 100, Some(150)), None, &Default::default());
+        assert!(!status.is_synced);
+        let config = NodeSyncConfig {
+            check_peer_height: false,
+            ..Default::default()
+        };
+        let status = NodeSyncStatus::evaluate(&node_info(100, 100, Some(150)), None, &config);
+        assert!(status.is_synced);
+    }
+
+    #[test]
+    fn stale_last_block() {
+        let config = NodeSyncConfig {
+            max_last_block_age_secs: Some(600),
+            ..Default::default()
+        };
+        let status = NodeSyncStatus::evaluate(&node_info(100, 100, None), Some(3600), &config);
+        assert!(!status.is_synced);
+        let status = NodeSyncStatus::evaluate(&node_info(100, 100, None), Some(60), &config);
+        assert!(status.is_synced);
+    }
+}
@@ -0,0 +1,466 @@
+//! Optional webhook notifications on health transitions and failed actions
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::time::Instant;
+
+use ergo_lib::chain::transaction::TxId;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::monitor::HealthStatus;
+use crate::oracle_types::BlockHeight;
+
+const DEFAULT_STUCK_TX_BLOCKS: u32 = 10;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NotifierConfig {
+    pub webhooks: Vec<WebhookConfig>,
+    /// Min time between two notifications of the same (unresolved) alert
+    #[serde(default = "default_repeat_after_secs")]
+    pub repeat_after_secs: u64,
+    /// Alert when the amount of reward tokens in the pool box drops below this
+    #[serde(default)]
+    pub low_reward_tokens_threshold: Option<u64>,
+    /// Alert when our tx is still in the mempool after this number of blocks
+    #[serde(default = "default_stuck_tx_blocks")]
+    pub stuck_tx_blocks: u32,
+}
+
+fn default_repeat_after_secs() -> u64 {
+    3600
+}
+
+fn default_stuck_tx_blocks() -> u32 {
+    DEFAULT_STUCK_TX_BLOCKS
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    pub url: Url,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Chat id for the Telegram `sendMessage` endpoint
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    /// Custom JSON payload, `{{message}}` is replaced with the message as a JSON string
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+pub enum WebhookFormat {
+    #[default]
+    Generic,
+    Discord,
+    Telegram,
+    Slack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    PoolHealth,
+    OracleHealth,
+    ConsensusFailure,
+    DatapointSourceFailure,
+    LowRewardTokens,
+    WalletLocked,
+    StuckTx,
+    Reorg,
+}
+
+/// Alerts with the same key are deduplicated
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AlertKey {
+    /// At most one alert of the kind at a time
+    Kind(AlertKind),
+    StuckTx(TxId),
+    Reorg(BlockHeight),
+}
+
+impl AlertKey {
+    fn kind(&self) -> AlertKind {
+        match self {
+            AlertKey::Kind(kind) => *kind,
+            AlertKey::StuckTx(_) => AlertKind::StuckTx,
+            AlertKey::Reorg(_) => AlertKind::Reorg,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Alert {
+    PoolHealth(HealthStatus),
+    OracleHealth(HealthStatus),
+    ConsensusFailure {
+        expected: i32,
+        found_num: i32,
+        found_oracle_addresses: String,
+    },
+    DatapointSourceFailure(String),
+    LowRewardTokens {
+        amount: u64,
+        threshold: u64,
+    },
+    WalletLocked,
+    StuckTx {
+        tx_id: TxId,
+        blocks: u32,
+    },
+    Reorg {
+        depth: u32,
+        fork_height: BlockHeight,
+    },
+}
+
+impl Alert {
+    pub fn kind(&self) -> AlertKind {
+        match self {
+            Alert::PoolHealth(_) => AlertKind::PoolHealth,
+            Alert::OracleHealth(_) => AlertKind::OracleHealth,
+            Alert::ConsensusFailure { .. } => AlertKind::ConsensusFailure,
+            Alert::DatapointSourceFailure(_) => AlertKind::DatapointSourceFailure,
+            Alert::LowRewardTokens { .. } => AlertKind::LowRewardTokens,
+            Alert::WalletLocked => AlertKind::WalletLocked,
+            Alert::StuckTx { .. } => AlertKind::StuckTx,
+            Alert::Reorg { .. } => AlertKind::Reorg,
+        }
+    }
+
+    fn key(&self) -> AlertKey {
+        match self {
+            Alert::StuckTx { tx_id, .. } => AlertKey::StuckTx(*tx_id),
+            Alert::Reorg { fork_height, .. } => AlertKey::Reorg(*fork_height),
+            Alert::PoolHealth(_)
+            | Alert::OracleHealth(_)
+            | Alert::ConsensusFailure { .. }
+            | Alert::DatapointSourceFailure(_)
+            | Alert::LowRewardTokens { .. }
+            | Alert::WalletLocked => AlertKey::Kind(self.kind()),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Alert::PoolHealth(status) => format!("Pool health changed to {:?}", status),
+            Alert::OracleHealth(status) => format!("Oracle health changed to {:?}", status),
+            Alert::ConsensusFailure {
+                expected,
+                found_num,
+                found_oracle_addresses,
+            } => format!(
+                "Refresh failed, not enough datapoints within the deviation range: required {expected}, found {found_num} from addresses {found_oracle_addresses}"
+            ),
+            Alert::DatapointSourceFailure(e) => format!("Failed to get datapoint: {e}"),
+            Alert::LowRewardTokens { amount, threshold } => format!(
+                "Only {amount} reward tokens left in the pool box (threshold {threshold})"
+            ),
+            Alert::WalletLocked => "Node wallet is locked, the oracle cannot act".to_string(),
+            Alert::StuckTx { tx_id, blocks } => {
+                format!("Tx {tx_id} is still unconfirmed after {blocks} blocks")
+            }
+            Alert::Reorg { depth, fork_height } => format!(
+                "Chain reorganization of {depth} blocks after height {fork_height} rolled back the pool boxes or our txs"
+            ),
+        }
+    }
+}
+
+pub struct Notifier {
+    config: Option<NotifierConfig>,
+    /// Prefix for every message to tell the oracles apart
+    source: String,
+    last_sent: HashMap<AlertKey, Instant>,
+    pool_health: Option<HealthStatus>,
+    oracle_health: Option<HealthStatus>,
+    /// Queue of the webhook sender thread, started with the first alert
+    sender: Option<mpsc::Sender<Delivery>>,
+}
+
+/// Webhook call handed over to the sender thread
+struct Delivery {
+    webhook: WebhookConfig,
+    kind: AlertKind,
+    message: String,
+}
+
+impl Notifier {
+    pub fn new(config: Option<NotifierConfig>, source: String) -> Self {
+        Self {
+            config,
+            source,
+            last_sent: HashMap::new(),
+            pool_health: None,
+            oracle_health: None,
+            sender: None,
+        }
+    }
+
+    /// New alert settings (oracle config reload), the alerts already sent are kept
+    pub fn set_config(&mut self, config: Option<NotifierConfig>) {
+        self.config = config;
+    }
+
+    pub fn stuck_tx_blocks(&self) -> u32 {
+        self.config
+            .as_ref()
+            .map(|c| c.stuck_tx_blocks)
+            .unwrap_or(DEFAULT_STUCK_TX_BLOCKS)
+    }
+
+    /// Sends the alert unless the same alert was sent less than `repeat_after_secs` ago. The
+    /// webhooks are called from a background thread so that a slow endpoint does not delay the
+    /// main loop.
+    pub fn alert(&mut self, alert: Alert) {
+        let Some(config) = self.config.clone() else {
+            return;
+        };
+        let repeat_after = Duration::from_secs(config.repeat_after_secs);
+        if !self.should_send(alert.key(), Instant::now(), repeat_after) {
+            log::debug!("Skipping repeated alert: {}", alert.message());
+            return;
+        }
+        let message = format!("[{}] {}", self.source, alert.message());
+        let sender = self.sender.get_or_insert_with(spawn_webhook_sender);
+        for webhook in config.webhooks {
+            let delivery = Delivery {
+                webhook,
+                kind: alert.kind(),
+                message: message.clone(),
+            };
+            if sender.send(delivery).is_err() {
+                log::error!("Webhook sender thread is gone, dropping alert: {}", message);
+                self.sender = None;
+                return;
+            }
+        }
+    }
+
+    /// Clears the alerts of the given kind so that the next occurrence is sent right away
+    pub fn resolve(&mut self, kind: AlertKind) {
+        self.last_sent.retain(|key, _| key.kind() != kind);
+    }
+
+    pub fn pool_health(&mut self, status: HealthStatus) {
+        if let Some(status) = health_transition(&mut self.pool_health, status) {
+            self.health_alert(Alert::PoolHealth(status), status);
+        }
+    }
+
+    pub fn oracle_health(&mut self, status: HealthStatus) {
+        if let Some(status) = health_transition(&mut self.oracle_health, status) {
+            self.health_alert(Alert::OracleHealth(status), status);
+        }
+    }
+
+    /// A health transition is always sent, even within `repeat_after_secs` of the previous one
+    fn health_alert(&mut self, alert: Alert, status: HealthStatus) {
+        let kind = alert.kind();
+        self.resolve(kind);
+        self.alert(alert);
+        if status == HealthStatus::Ok {
+            self.resolve(kind);
+        }
+    }
+
+    pub fn pool_reward_tokens(&mut self, amount: u64) {
+        let threshold = self
+            .config
+            .as_ref()
+            .and_then(|c| c.low_reward_tokens_threshold);
+        match threshold {
+            Some(threshold) if amount < threshold => {
+                self.alert(Alert::LowRewardTokens { amount, threshold })
+            }
+            _ => self.resolve(AlertKind::LowRewardTokens),
+        }
+    }
+
+    fn should_send(&mut self, key: AlertKey, now: Instant, repeat_after: Duration) -> bool {
+        match self.last_sent.get(&key) {
+            Some(sent) if now.duration_since(*sent) < repeat_after => false,
+            _ => {
+                self.last_sent.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+/// Returns the new status if it differs from the last one (the first `Ok` is not reported)
+fn health_transition(
+    last: &mut Option<HealthStatus>,
+    status: HealthStatus,
+) -> Option<HealthStatus> {
+    let changed = match last {
+        Some(last_status) => *last_status != status,
+        None => status != HealthStatus::Ok,
+    };
+    *last = Some(status);
+    changed.then_some(status)
+}
+
+fn webhook_payload(
+    webhook: &WebhookConfig,
+    kind: AlertKind,
+    message: &str,
+) -> Result<String, serde_json::Error> {
+    if let Some(template) = &webhook.template {
+        return Ok(template.replace("{{message}}", &serde_json::to_string(message)?));
+    }
+    let payload = match webhook.format {
+        WebhookFormat::Generic => json!({
+            "alert": format!("{:?}", kind),
+            "message": message,
+        }),
+        WebhookFormat::Discord => json!({ "content": message }),
+        WebhookFormat::Slack => json!({ "text": message }),
+        WebhookFormat::Telegram => json!({
+            "chat_id": webhook.chat_id,
+            "text": message,
+        }),
+    };
+    serde_json::to_string(&payload)
+}
+
+fn spawn_webhook_sender() -> mpsc::Sender<Delivery> {
+    let (sender, receiver) = mpsc::channel::<Delivery>();
+    std::thread::spawn(move || {
+        for delivery in receiver {
+            if let Err(e) = send_webhook(&delivery.webhook, delivery.kind, &delivery.message) {
+                log::error!(
+                    "Failed to send notification to {}: {}",
+                    delivery.webhook.url,
+                    e
+                );
+            }
+        }
+    });
+    sender
+}
+
+fn send_webhook(
+    webhook: &WebhookConfig,
+    kind: AlertKind,
+    message: &str,
+) -> Result<(), anyhow::Error> {
+    let payload = webhook_payload(webhook, kind, message)?;
+    reqwest::blocking::Client::new()
+        .post(webhook.url.clone())
+        .header(CONTENT_TYPE, "application/json")
+        .timeout(WEBHOOK_TIMEOUT)
+        .body(payload)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webhook(format: WebhookFormat, template: Option<String>) -> WebhookConfig {
+        WebhookConfig {
+            url: Url::parse("http://127.0.0.1:8080/hook").unwrap(),
+            format,
+            chat_id: Some("42".to_string()),
+            template,
+        }
+    }
+
+    #[test]
+    fn payload_formats() {
+        let msg = "Pool health changed to \"Down\"";
+        assert_eq!(
+            webhook_payload(
+                &webhook(WebhookFormat::Discord, None),
+                AlertKind::PoolHealth,
+                msg
+            )
+            .unwrap(),
+            r#"{"content":"Pool health changed to \"Down\""}"#
+        );
+        assert_eq!(
+            webhook_payload(
+                &webhook(WebhookFormat::Telegram, None),
+                AlertKind::PoolHealth,
+                msg
+            )
+            .unwrap(),
+            r#"{"chat_id":"42","text":"Pool health changed to \"Down\""}"#
+        );
+        assert_eq!(
+            webhook_payload(
+                &webhook(
+                    WebhookFormat::Generic,
+                    Some(r#"{"msg": {{message}}, "source": "oracle"}"#.to_string())
+                ),
+                AlertKind::PoolHealth,
+                msg
+            )
+            .unwrap(),
+            r#"{"msg": "Pool health changed to \"Down\"", "source": "oracle"}"#
+        );
+    }
+
+    #[test]
+    fn repeated_alerts_are_deduplicated() {
+        let mut notifier = Notifier::new(None, "test".to_string());
+        let now = Instant::now();
+        let repeat_after = Duration::from_secs(60);
+        let key = Alert::WalletLocked.key();
+        assert!(notifier.should_send(key.clone(), now, repeat_after));
+        assert!(!notifier.should_send(key.clone(), now + Duration::from_secs(10), repeat_after));
+        assert!(notifier.should_send(key.clone(), now + Duration::from_secs(61), repeat_after));
+        notifier.resolve(AlertKind::WalletLocked);
+        assert!(notifier.should_send(key, now + Duration::from_secs(62), repeat_after));
+    }
+
+    #[test]
+    fn health_flaps_are_sent() {
+        let config = NotifierConfig {
+            webhooks: vec![webhook(WebhookFormat::Generic, None)],
+            repeat_after_secs: 3600,
+            low_reward_tokens_threshold: None,
+            stuck_tx_blocks: DEFAULT_STUCK_TX_BLOCKS,
+        };
+        let mut notifier = Notifier::new(Some(config), "test".to_string());
+        let (sender, receiver) = mpsc::channel();
+        notifier.sender = Some(sender);
+        notifier.pool_health(HealthStatus::Down);
+        notifier.pool_health(HealthStatus::Ok);
+        notifier.pool_health(HealthStatus::Down);
+        notifier.pool_health(HealthStatus::Down);
+        notifier.oracle_health(HealthStatus::Degraded);
+        let messages: Vec<String> = receiver.try_iter().map(|d| d.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "[test] Pool health changed to Down",
+                "[test] Pool health changed to Ok",
+                "[test] Pool health changed to Down",
+                "[test] Oracle health changed to Degraded",
+            ]
+        );
+    }
+
+    #[test]
+    fn health_transitions() {
+        let mut last = None;
+        assert_eq!(health_transition(&mut last, HealthStatus::Ok), None);
+        assert_eq!(health_transition(&mut last, HealthStatus::Ok), None);
+        assert_eq!(
+            health_transition(&mut last, HealthStatus::Down),
+            Some(HealthStatus::Down)
+        );
+        assert_eq!(health_transition(&mut last, HealthStatus::Down), None);
+        assert_eq!(
+            health_transition(&mut last, HealthStatus::Ok),
+            Some(HealthStatus::Ok)
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! `monitor` command: watches a pool without being one of its oracles. Only `pool_config.yaml` is
+//! loaded, the boxes are read through public endpoints (no node API key, wallet or scans) and
+//! nothing is ever signed.
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Context;
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use log::error;
+
+use crate::api::start_monitor_rest_server;
+use crate::events::ChainObserver;
+use crate::events::EventBus;
+use crate::http_server::HttpServerConfig;
+use crate::metrics::start_metrics_server;
+use crate::metrics::PoolMetrics;
+use crate::monitor::HealthConfig;
+use crate::oracle_state::OraclePool;
+use crate::pool_config::PoolConfig;
+use crate::public_boxes::PublicChainReader;
+use crate::snapshot::PoolSnapshot;
+use crate::snapshot::SnapshotCache;
+
+pub fn run_monitor(
+    pool_config: PoolConfig,
+    reader: PublicChainReader,
+    network_prefix: NetworkPrefix,
+    api_port: Option<u16>,
+    metrics_port: Option<u16>,
+    server_config: HttpServerConfig,
+) {
+    log::info!("Observing the pool through {:?}", reader);
+    let reader = Arc::new(reader);
+    let token_boxes = reader.pool_token_boxes(&pool_config);
+    let oracle_pool = Arc::new(OraclePool::with_token_boxes(
+        Arc::new(pool_config),
+        token_boxes,
+        None,
+    ));
+    let events = Arc::new(EventBus::new());
+    let snapshot_cache = Arc::new(SnapshotCache::new());
+    let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
+    if let Some(api_port) = api_port {
+        let op_clone = oracle_pool.clone();
+        let events_clone = events.clone();
+        let snapshot_cache_clone = snapshot_cache.clone();
+        let server_config = server_config.clone();
+        tokio_runtime.spawn(async move {
+            if let Err(e) = start_monitor_rest_server(
+                op_clone,
+                events_clone,
+                snapshot_cache_clone,
+                network_prefix,
+                api_port,
+                server_config,
+            )
+            .await
+            {
+                error!("An error occurred while starting the REST server: {}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        });
+    }
+    if let Some(metrics_port) = metrics_port {
+        tokio_runtime.spawn(async move {
+            if let Err(e) = start_metrics_server(metrics_port, &server_config).await {
+                error!("An error occurred while starting the metrics server: {}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        });
+    }
+    let health_config = HealthConfig::default();
+    let metrics = PoolMetrics::new(None);
+    let mut chain_observer = ChainObserver::new();
+    loop {
+        if let Err(e) = monitor_iteration(
+            &reader,
+            oracle_pool.clone(),
+            network_prefix,
+            &health_config,
+            &events,
+            &mut chain_observer,
+            &snapshot_cache,
+            &metrics,
+        ) {
+            error!("error: {:?}", e);
+        }
+        thread::sleep(Duration::new(30, 0));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn monitor_iteration(
+    reader: &PublicChainReader,
+    oracle_pool: Arc<OraclePool>,
+    network_prefix: NetworkPrefix,
+    health_config: &HealthConfig,
+    events: &EventBus,
+    chain_observer: &mut ChainObserver,
+    snapshot_cache: &SnapshotCache,
+    metrics: &PoolMetrics,
+) -> Result<(), anyhow::Error> {
+    let height = reader
+        .current_height()
+        .context("Failed to get the current height")?;
+    let snapshot = snapshot_cache.store(
+        PoolSnapshot::observe(oracle_pool.clone(), height, network_prefix, health_config)
+            .context("Failed to take the pool snapshot")?,
+    );
+    log::debug!(
+        "Height {}, pool epoch {}, rate {}, pool health {:?}",
+        snapshot.height,
+        snapshot.pool_box_epoch_counter.0,
+        snapshot.pool_box_rate,
+        snapshot.pool_health.status
+    );
+    metrics.update(&snapshot);
+    chain_observer.health(&snapshot.pool_health, None, events);
+    if let Err(e) = chain_observer.observe(
+        &snapshot,
+        oracle_pool.get_ballot_boxes_source(),
+        oracle_pool.get_update_box_source(),
+        events,
+    ) {
+        log::warn!("Failed to observe the pool boxes for events: {:?}", e);
+    }
+    Ok(())
+}
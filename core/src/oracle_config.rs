@@ -1,5 +1,4 @@
 use std::{
-    convert::TryFrom,
     io::Write,
     path::{Path, PathBuf},
 };
@@ -8,13 +7,10 @@ use anyhow::Context;
 use ergo_lib::{
     ergotree_ir::chain::address::NetworkAddress,
     ergotree_ir::{
-        chain::{
-            address::{Address, AddressEncoder},
-            ergo_box::box_value::BoxValue,
-        },
+        chain::address::{Address, AddressEncoder},
         sigma_protocol::sigma_boolean::ProveDlog,
     },
-    wallet::tx_builder::{self, SUGGESTED_TX_FEE},
+    wallet::tx_builder,
 };
 use log::{warn, LevelFilter};
 use once_cell::sync;
@@ -22,7 +18,14 @@ use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::datapoint_source::connector::PushDataPointConfig;
 use crate::explorer_api::explorer_url::default_explorer_api_url;
+use crate::fee_runway::FeeRunwayConfig;
+use crate::http_server::HttpServerConfig;
+use crate::monitor::HealthConfig;
+use crate::node_sync::NodeSyncConfig;
+use crate::notifier::NotifierConfig;
+use crate::pool_runtime::PoolEntryConfig;
 
 pub const DEFAULT_ORACLE_CONFIG_FILE_NAME: &str = "oracle_config.yaml";
 
@@ -35,13 +38,57 @@ pub struct OracleConfig {
     pub core_api_port: u16,
     pub oracle_address: NetworkAddress,
     pub data_point_source_custom_script: Option<String>,
+    /// Use the datapoints pushed by an external connector to the REST API (requires
+    /// ORACLE_CONNECTOR_API_KEY)
+    #[serde(default)]
+    pub data_point_source_push: Option<PushDataPointConfig>,
     pub explorer_url: Option<Url>,
+    /// Backend reading the pool boxes
+    #[serde(default)]
+    pub box_source: BoxSource,
+    /// Read the pool boxes from the explorer API when the box source's boxes look inconsistent
+    #[serde(default)]
+    pub explorer_fallback: bool,
     pub metrics_port: Option<u16>,
+    #[serde(default)]
+    pub node_sync: NodeSyncConfig,
+    #[serde(default)]
+    pub fee_runway: FeeRunwayConfig,
+    #[serde(default)]
+    pub health: HealthConfig,
+    /// Enables the `/admin` routes of the REST API (requires ORACLE_ADMIN_API_KEY)
+    #[serde(default)]
+    pub admin_api_enabled: bool,
+    /// Bind address, TLS and access control of the REST API server
+    #[serde(default)]
+    pub rest_api: HttpServerConfig,
+    /// Bind address, TLS and access control of the metrics server
+    #[serde(default)]
+    pub metrics_server: HttpServerConfig,
+    pub notifications: Option<NotifierConfig>,
+    /// Pools run by this oracle in the multi-pool mode (`--pool-config-file` is ignored if set)
+    #[serde(default)]
+    pub pools: Vec<PoolEntryConfig>,
 }
 
+/// Backend reading the pool boxes of the running oracle and the CLI commands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoxSource {
+    /// Node scans registered by the oracle (`scanIDs.json`), the node wallet rescans the chain
+    /// from `scan_start_height` after registration
+    #[default]
+    NodeScans,
+    /// The node's blockchain indexer (`extraIndex = true`), no scans are registered
+    NodeIndexer,
+}
+
+#[derive(Clone)]
 pub struct OracleSecrets {
     pub node_api_key: String,
     pub wallet_password: Option<String>,
+    pub admin_api_key: Option<String>,
+    pub connector_api_key: Option<String>,
 }
 
 impl OracleSecrets {
@@ -58,10 +105,17 @@ impl OracleSecrets {
         Self {
             node_api_key: api_key,
             wallet_password: wallet_pass,
+            admin_api_key: api_key_var("ORACLE_ADMIN_API_KEY"),
+            connector_api_key: api_key_var("ORACLE_CONNECTOR_API_KEY"),
         }
     }
 }
 
+/// An empty key is treated as unset, it would let any request with an empty key header in
+fn api_key_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|key| !key.is_empty())
+}
+
 impl OracleConfig {
     pub fn write_default_config_file(path: &Path) {
         let config = OracleConfig::default();
@@ -70,7 +124,7 @@ impl OracleConfig {
         file.write_all(yaml_str.as_bytes()).unwrap();
     }
 
-    fn load() -> Result<Self, anyhow::Error> {
+    pub fn load() -> Result<Self, anyhow::Error> {
         let config_file_path = ORACLE_CONFIG_FILE_PATH.get().ok_or_else(|| {
             OracleConfigFileError::IoError("ORACLE_CONFIG_FILE_PATH not set".to_string())
         })?;
@@ -129,22 +183,24 @@ impl Default for OracleConfig {
             core_api_port: 9010,
             scan_start_height: 0,
             data_point_source_custom_script: None,
+            data_point_source_push: None,
             base_fee: *tx_builder::SUGGESTED_TX_FEE().as_u64(),
             log_level: LevelFilter::Info.into(),
             node_url: Url::parse("http://127.0.0.1:9053").unwrap(),
             explorer_url: Some(default_explorer_api_url(address.network())),
+            box_source: BoxSource::default(),
+            explorer_fallback: false,
             metrics_port: None,
+            node_sync: NodeSyncConfig::default(),
+            fee_runway: FeeRunwayConfig::default(),
+            health: HealthConfig::default(),
+            admin_api_enabled: false,
+            rest_api: HttpServerConfig::default(),
+            metrics_server: HttpServerConfig::default(),
+            notifications: None,
+            pools: vec![],
         }
     }
 }
 
 pub static ORACLE_CONFIG_FILE_PATH: sync::OnceCell<PathBuf> = sync::OnceCell::new();
-lazy_static! {
-    pub static ref ORACLE_CONFIG: OracleConfig = OracleConfig::load().unwrap();
-    pub static ref ORACLE_SECRETS: OracleSecrets = OracleSecrets::load();
-    pub static ref ORACLE_CONFIG_OPT: Result<OracleConfig, anyhow::Error> = OracleConfig::load();
-    pub static ref BASE_FEE: BoxValue = ORACLE_CONFIG_OPT
-        .as_ref()
-        .map(|c| BoxValue::try_from(c.base_fee).unwrap())
-        .unwrap_or_else(|_| SUGGESTED_TX_FEE());
-}
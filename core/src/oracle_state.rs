@@ -5,18 +5,30 @@ use crate::box_kind::{
     PostedOracleBox, RefreshBoxError, RefreshBoxWrapper, RefreshBoxWrapperInputs, UpdateBoxError,
     UpdateBoxWrapper, UpdateBoxWrapperInputs, VoteBallotBoxWrapper,
 };
+use crate::context::OracleContext;
 use crate::datapoint_source::DataPointSourceError;
-use crate::oracle_config::ORACLE_CONFIG;
+use crate::node_interface::node_api::BlockRef;
+use crate::node_interface::node_api::ChainTipSource;
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+use crate::oracle_config::BoxSource;
 use crate::oracle_types::{BlockHeight, EpochCounter, Rate};
-use crate::pool_config::POOL_CONFIG;
-use crate::scans::{GenericTokenScan, NodeScanRegistry, ScanError, ScanGetBoxes};
+use crate::pool_config::PoolConfig;
+use crate::public_boxes::with_explorer_fallback;
+use crate::public_boxes::FallbackBoxes;
+use crate::public_boxes::PublicChainReader;
+use crate::scans::{
+    NodeScanBoxes, NodeScanId, NodeScanRegistry, ScanError, ScanGetBoxes, SharedScanRegistry,
+    SnapshotBoxes,
+};
 use crate::spec_token::{
     BallotTokenId, BuybackTokenId, OracleTokenId, PoolTokenId, RefreshTokenId, RewardTokenId,
     TokenIdKind, UpdateTokenId,
 };
 use crate::util::get_token_count;
-use anyhow::Error;
+use std::sync::Arc;
 
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
 use ergo_lib::ergotree_ir::mir::constant::TryExtractFromError;
 use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
 use thiserror::Error;
@@ -51,6 +63,27 @@ pub enum DataSourceError {
     BuybackBoxError(#[from] BuybackBoxError),
 }
 
+/// Reads of the pool boxes at a single height, see [`OraclePool::snapshot`]
+const SNAPSHOT_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum PoolBoxesSnapshotError {
+    #[error("node api error: {0}")]
+    NodeApi(#[from] NodeApiError),
+    #[error("scan error: {0}")]
+    Scan(#[from] ScanError),
+    #[error("the node has no full block yet")]
+    NoBestBlock,
+    #[error("new blocks kept arriving while reading the pool boxes ({0} attempts)")]
+    ChainMoving(usize),
+    #[error("box {box_id:?} was created at height {creation_height}, above the best block at height {height}")]
+    BoxAboveBestBlock {
+        box_id: BoxId,
+        creation_height: u32,
+        height: BlockHeight,
+    },
+}
+
 pub trait PoolBoxSource {
     fn get_pool_box(&self) -> Result<PoolBoxWrapper>;
 }
@@ -90,6 +123,7 @@ pub trait BuybackBoxSource {
 /// Overarching struct which allows for acquiring the state of the whole oracle pool protocol
 #[derive(Debug)]
 pub struct OraclePool {
+    pool_config: Arc<PoolConfig>,
     oracle_datapoint_scan: OracleDatapointScan,
     local_oracle_datapoint_scan: LocalOracleDatapointScan,
     local_ballot_box_scan: LocalBallotBoxScan,
@@ -98,55 +132,60 @@ pub struct OraclePool {
     ballot_boxes_scan: BallotBoxesScan,
     update_box_scan: UpdateBoxScan,
     buyback_box_scan: Option<BuybackBoxScan>,
+    token_boxes: PoolTokenBoxes,
+    oracle_pk: Option<ProveDlog>,
+    /// Best block the boxes were read at, `None` unless taken with [`OraclePool::snapshot`]
+    snapshot_block: Option<BlockRef>,
 }
 
 #[derive(Debug)]
 pub struct OracleDatapointScan {
-    scan: GenericTokenScan<OracleTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     oracle_box_wrapper_inputs: OracleBoxWrapperInputs,
 }
 
 #[derive(Debug)]
 pub struct LocalOracleDatapointScan {
-    scan: GenericTokenScan<OracleTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     oracle_box_wrapper_inputs: OracleBoxWrapperInputs,
-    oracle_pk: ProveDlog,
+    /// `None` when observing the pool without being one of its oracles
+    oracle_pk: Option<ProveDlog>,
 }
 
 #[derive(Debug)]
 pub struct LocalBallotBoxScan {
-    scan: GenericTokenScan<BallotTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     ballot_box_wrapper_inputs: BallotBoxWrapperInputs,
-    ballot_token_owner_pk: ProveDlog,
+    ballot_token_owner_pk: Option<ProveDlog>,
 }
 
 #[derive(Debug)]
 pub struct PoolBoxScan {
-    scan: GenericTokenScan<PoolTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     pool_box_wrapper_inputs: PoolBoxWrapperInputs,
 }
 
 #[derive(Debug)]
 pub struct RefreshBoxScan {
-    scan: GenericTokenScan<RefreshTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     refresh_box_wrapper_inputs: RefreshBoxWrapperInputs,
 }
 
 #[derive(Debug)]
 pub struct BallotBoxesScan {
-    scan: GenericTokenScan<BallotTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     ballot_box_wrapper_inputs: BallotBoxWrapperInputs,
 }
 
 #[derive(Debug)]
 pub struct UpdateBoxScan {
-    scan: GenericTokenScan<UpdateTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     update_box_wrapper_inputs: UpdateBoxWrapperInputs,
 }
 
 #[derive(Debug)]
 pub struct BuybackBoxScan {
-    scan: GenericTokenScan<BuybackTokenId>,
+    scan: Arc<dyn ScanGetBoxes>,
     reward_token_id: RewardTokenId,
 }
 
@@ -160,7 +199,7 @@ pub struct LiveEpochState {
 }
 
 /// Last posted datapoint box info by the local oracle
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LocalDatapointState {
     Collected {
         height: BlockHeight,
@@ -171,61 +210,236 @@ pub enum LocalDatapointState {
     },
 }
 
+impl From<&OracleBoxWrapper> for LocalDatapointState {
+    fn from(local_data_point_box: &OracleBoxWrapper) -> Self {
+        match local_data_point_box {
+            OracleBoxWrapper::Posted(posted_box) => LocalDatapointState::Posted {
+                epoch_id: posted_box.epoch_counter(),
+                height: BlockHeight(local_data_point_box.get_box().creation_height),
+            },
+            OracleBoxWrapper::Collected(_) => LocalDatapointState::Collected {
+                height: BlockHeight(local_data_point_box.get_box().creation_height),
+            },
+        }
+    }
+}
+
+/// Sources of the unspent boxes of each pool token
+#[derive(Debug, Clone)]
+pub struct PoolTokenBoxes {
+    pub oracle: Arc<dyn ScanGetBoxes>,
+    pub pool: Arc<dyn ScanGetBoxes>,
+    pub ballot: Arc<dyn ScanGetBoxes>,
+    pub refresh: Arc<dyn ScanGetBoxes>,
+    pub update: Arc<dyn ScanGetBoxes>,
+    pub buyback: Option<Arc<dyn ScanGetBoxes>>,
+}
+
+impl PoolTokenBoxes {
+    /// Boxes of the node scans in `registry`
+    pub fn from_node_scans(registry: &NodeScanRegistry, node_api: &Arc<NodeApi>) -> Self {
+        Self::from_shared_node_scans(
+            &Arc::new(SharedScanRegistry::new(registry.clone())),
+            node_api,
+        )
+    }
+
+    /// Boxes of the node scans in `registry`, following the scans registered again
+    pub fn from_shared_node_scans(
+        registry: &Arc<SharedScanRegistry>,
+        node_api: &Arc<NodeApi>,
+    ) -> Self {
+        let scans = registry.load();
+        Self {
+            oracle: NodeScanBoxes::new(registry, |r| Some(r.oracle_token_scan.scan_id()), node_api),
+            pool: NodeScanBoxes::new(registry, |r| Some(r.pool_token_scan.scan_id()), node_api),
+            ballot: NodeScanBoxes::new(registry, |r| Some(r.ballot_token_scan.scan_id()), node_api),
+            refresh: NodeScanBoxes::new(
+                registry,
+                |r| Some(r.refresh_token_scan.scan_id()),
+                node_api,
+            ),
+            update: NodeScanBoxes::new(registry, |r| Some(r.update_token_scan.scan_id()), node_api),
+            buyback: scans.buyback_token_scan.map(|_| {
+                NodeScanBoxes::new(
+                    registry,
+                    |r| r.buyback_token_scan.map(|scan| scan.scan_id()),
+                    node_api,
+                )
+            }),
+        }
+    }
+}
+
+impl PoolTokenBoxes {
+    /// Reads the boxes from `fallback` when these look inconsistent (see [`FallbackBoxes`])
+    pub fn with_fallback(self, fallback: PoolTokenBoxes) -> Self {
+        Self {
+            oracle: FallbackBoxes::new(self.oracle, fallback.oracle, false),
+            pool: FallbackBoxes::new(self.pool, fallback.pool, true),
+            ballot: FallbackBoxes::new(self.ballot, fallback.ballot, false),
+            refresh: FallbackBoxes::new(self.refresh, fallback.refresh, true),
+            update: FallbackBoxes::new(self.update, fallback.update, true),
+            buyback: match (self.buyback, fallback.buyback) {
+                (Some(buyback), Some(fallback_buyback)) => {
+                    Some(FallbackBoxes::new(buyback, fallback_buyback, true))
+                }
+                (buyback, _) => buyback,
+            },
+        }
+    }
+
+    /// Boxes of all tokens read at the node's best block. The boxes are read again when a block
+    /// arrives meanwhile.
+    pub fn snapshot(
+        &self,
+        chain_tip: &dyn ChainTipSource,
+    ) -> std::result::Result<(PoolTokenBoxes, BlockRef), PoolBoxesSnapshotError> {
+        for _ in 0..SNAPSHOT_ATTEMPTS {
+            let best_block = chain_tip
+                .best_full_block()?
+                .ok_or(PoolBoxesSnapshotError::NoBestBlock)?;
+            let token_boxes = self.read()?;
+            let best_block_after = chain_tip
+                .best_full_block()?
+                .ok_or(PoolBoxesSnapshotError::NoBestBlock)?;
+            if best_block == best_block_after {
+                token_boxes.check_created_before(best_block.height)?;
+                return Ok((token_boxes, best_block));
+            }
+            log::debug!(
+                "Block {} at height {} arrived while reading the pool boxes, reading them again",
+                best_block_after.id,
+                best_block_after.height
+            );
+        }
+        Err(PoolBoxesSnapshotError::ChainMoving(SNAPSHOT_ATTEMPTS))
+    }
+
+    fn read(&self) -> std::result::Result<PoolTokenBoxes, ScanError> {
+        Ok(Self {
+            oracle: SnapshotBoxes::read(&self.oracle)?,
+            pool: SnapshotBoxes::read(&self.pool)?,
+            ballot: SnapshotBoxes::read(&self.ballot)?,
+            refresh: SnapshotBoxes::read(&self.refresh)?,
+            update: SnapshotBoxes::read(&self.update)?,
+            buyback: self.buyback.as_ref().map(SnapshotBoxes::read).transpose()?,
+        })
+    }
+
+    /// A box created above `height` means the node applied a block while it was being read
+    fn check_created_before(
+        &self,
+        height: BlockHeight,
+    ) -> std::result::Result<(), PoolBoxesSnapshotError> {
+        let sources = [
+            &self.oracle,
+            &self.pool,
+            &self.ballot,
+            &self.refresh,
+            &self.update,
+        ];
+        for source in sources.into_iter().chain(self.buyback.as_ref()) {
+            if let Some(b) = source
+                .get_boxes()?
+                .into_iter()
+                .find(|b| b.creation_height > height.0)
+            {
+                return Err(PoolBoxesSnapshotError::BoxAboveBestBlock {
+                    box_id: b.box_id(),
+                    creation_height: b.creation_height,
+                    height,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 impl OraclePool {
-    pub fn new(node_scan_registry: &NodeScanRegistry) -> std::result::Result<OraclePool, Error> {
-        let pool_config = &POOL_CONFIG;
-        let oracle_config = &ORACLE_CONFIG;
-        let oracle_pk = oracle_config.oracle_address_p2pk()?;
+    /// Pool of `ctx` read through the node scans of `node_scan_registry`
+    #[doc(hidden)]
+    pub fn new(ctx: &OracleContext, node_scan_registry: &NodeScanRegistry) -> OraclePool {
+        Self::with_token_boxes(
+            ctx.pool_config.clone(),
+            PoolTokenBoxes::from_node_scans(node_scan_registry, &ctx.node_api),
+            Some(ctx.oracle_pk()),
+        )
+    }
+
+    /// Pool of `ctx` read through the box source of the oracle config: the node scans of
+    /// `scanIDs.json` in the data folder or the node's blockchain indexer
+    #[doc(hidden)]
+    pub fn load(ctx: &OracleContext) -> std::result::Result<OraclePool, anyhow::Error> {
+        let token_boxes = match ctx.oracle_config.box_source {
+            BoxSource::NodeScans => {
+                PoolTokenBoxes::from_node_scans(&NodeScanRegistry::load()?, &ctx.node_api)
+            }
+            BoxSource::NodeIndexer => Arc::new(PublicChainReader::oracle_node_indexer(ctx)?)
+                .pool_token_boxes(&ctx.pool_config),
+        };
+        Ok(Self::with_token_boxes(
+            ctx.pool_config.clone(),
+            with_explorer_fallback(ctx, token_boxes),
+            Some(ctx.oracle_pk()),
+        ))
+    }
 
+    /// Pool read through `token_boxes`. Without `oracle_pk` (observer) there is no local
+    /// datapoint or ballot box.
+    pub fn with_token_boxes(
+        pool_config: Arc<PoolConfig>,
+        token_boxes: PoolTokenBoxes,
+        oracle_pk: Option<ProveDlog>,
+    ) -> OraclePool {
+        let boxes = token_boxes.clone();
+        let pk = oracle_pk.clone();
         // Create all `Scan` structs for protocol
         let oracle_datapoint_scan = OracleDatapointScan {
-            scan: node_scan_registry.oracle_token_scan.clone(),
+            scan: token_boxes.oracle.clone(),
             oracle_box_wrapper_inputs: pool_config.oracle_box_wrapper_inputs.clone(),
         };
         let local_oracle_datapoint_scan = LocalOracleDatapointScan {
-            scan: node_scan_registry.oracle_token_scan.clone(),
+            scan: token_boxes.oracle,
             oracle_box_wrapper_inputs: pool_config.oracle_box_wrapper_inputs.clone(),
             oracle_pk: oracle_pk.clone(),
         };
 
         let local_ballot_box_scan = LocalBallotBoxScan {
-            scan: node_scan_registry.ballot_token_scan.clone(),
+            scan: token_boxes.ballot.clone(),
             ballot_box_wrapper_inputs: pool_config.ballot_box_wrapper_inputs.clone(),
-            ballot_token_owner_pk: oracle_pk.clone(),
+            ballot_token_owner_pk: oracle_pk,
         };
 
         let ballot_boxes_scan = BallotBoxesScan {
-            scan: node_scan_registry.ballot_token_scan.clone(),
+            scan: token_boxes.ballot,
             ballot_box_wrapper_inputs: pool_config.ballot_box_wrapper_inputs.clone(),
         };
 
         let pool_box_scan = PoolBoxScan {
-            scan: node_scan_registry.pool_token_scan.clone(),
+            scan: token_boxes.pool,
             pool_box_wrapper_inputs: pool_config.pool_box_wrapper_inputs.clone(),
         };
 
         let refresh_box_scan = RefreshBoxScan {
-            scan: node_scan_registry.refresh_token_scan.clone(),
+            scan: token_boxes.refresh,
             refresh_box_wrapper_inputs: pool_config.refresh_box_wrapper_inputs.clone(),
         };
 
         let update_box_scan = UpdateBoxScan {
-            scan: node_scan_registry.update_token_scan.clone(),
+            scan: token_boxes.update,
             update_box_wrapper_inputs: pool_config.update_box_wrapper_inputs.clone(),
         };
 
-        let buyback_box_scan =
-            node_scan_registry
-                .buyback_token_scan
-                .clone()
-                .map(|scan| BuybackBoxScan {
-                    scan,
-                    reward_token_id: pool_config.token_ids.reward_token_id.clone(),
-                });
+        let buyback_box_scan = token_boxes.buyback.map(|scan| BuybackBoxScan {
+            scan,
+            reward_token_id: pool_config.token_ids.reward_token_id.clone(),
+        });
 
         log::debug!("Scans loaded");
 
-        Ok(OraclePool {
+        OraclePool {
+            pool_config,
             oracle_datapoint_scan,
             local_oracle_datapoint_scan,
             local_ballot_box_scan,
@@ -234,13 +448,36 @@ impl OraclePool {
             refresh_box_scan,
             update_box_scan,
             buyback_box_scan,
+            token_boxes: boxes,
+            oracle_pk: pk,
+            snapshot_block: None,
+        }
+    }
+
+    /// Pool backed by the boxes of all tokens read once at the node's best block, so that all
+    /// sources agree within a main loop iteration (see [`PoolTokenBoxes::snapshot`])
+    pub fn snapshot(
+        &self,
+        chain_tip: &dyn ChainTipSource,
+    ) -> std::result::Result<OraclePool, PoolBoxesSnapshotError> {
+        let (token_boxes, best_block) = self.token_boxes.snapshot(chain_tip)?;
+        Ok(OraclePool {
+            snapshot_block: Some(best_block),
+            ..Self::with_token_boxes(
+                self.pool_config.clone(),
+                token_boxes,
+                self.oracle_pk.clone(),
+            )
         })
     }
 
-    /// Create a new `OraclePool` struct with loaded scans
-    pub fn load() -> std::result::Result<OraclePool, Error> {
-        let node_scan_registry = NodeScanRegistry::load()?;
-        Self::new(&node_scan_registry)
+    /// Best block of the node at which the boxes were read, see [`OraclePool::snapshot`]
+    pub fn snapshot_block(&self) -> Option<&BlockRef> {
+        self.snapshot_block.as_ref()
+    }
+
+    pub fn pool_config(&self) -> &Arc<PoolConfig> {
+        &self.pool_config
     }
 
     /// Get the state of the current oracle pool epoch
@@ -252,15 +489,7 @@ impl OraclePool {
         let local_datapoint_box_state = self
             .get_local_datapoint_box_source()
             .get_local_oracle_datapoint_box()?
-            .map(|local_data_point_box| match local_data_point_box {
-                OracleBoxWrapper::Posted(ref posted_box) => LocalDatapointState::Posted {
-                    epoch_id: posted_box.epoch_counter(),
-                    height: BlockHeight(local_data_point_box.get_box().creation_height),
-                },
-                OracleBoxWrapper::Collected(_) => LocalDatapointState::Collected {
-                    height: BlockHeight(local_data_point_box.get_box().creation_height),
-                },
-            });
+            .map(|local_data_point_box| (&local_data_point_box).into());
 
         let latest_pool_datapoint = pool_box.rate();
 
@@ -345,12 +574,15 @@ impl PoolBoxSource for PoolBoxScan {
 
 impl LocalBallotBoxSource for LocalBallotBoxScan {
     fn get_ballot_box(&self) -> Result<Option<BallotBoxWrapper>> {
+        let Some(ballot_token_owner_pk) = &self.ballot_token_owner_pk else {
+            return Ok(None);
+        };
         Ok(self
             .scan
             .get_boxes()?
             .into_iter()
             .filter_map(|b| BallotBoxWrapper::new(b, &self.ballot_box_wrapper_inputs).ok())
-            .find(|b| b.ballot_token_owner() == *self.ballot_token_owner_pk.h))
+            .find(|b| b.ballot_token_owner() == *ballot_token_owner_pk.h))
     }
 }
 
@@ -368,12 +600,15 @@ impl RefreshBoxSource for RefreshBoxScan {
 
 impl LocalDatapointBoxSource for LocalOracleDatapointScan {
     fn get_local_oracle_datapoint_box(&self) -> Result<Option<OracleBoxWrapper>> {
+        let Some(oracle_pk) = &self.oracle_pk else {
+            return Ok(None);
+        };
         Ok(self
             .scan
             .get_boxes()?
             .into_iter()
             .filter_map(|b| OracleBoxWrapper::new(b, &self.oracle_box_wrapper_inputs).ok())
-            .find(|b| b.public_key() == *self.oracle_pk.h))
+            .find(|b| b.public_key() == *oracle_pk.h))
     }
 }
 
@@ -442,3 +677,110 @@ impl BuybackBoxSource for BuybackBoxScan {
             .map(|ergo_box| BuybackBoxWrapper::new(ergo_box, self.reward_token_id.clone())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    /// Node whose best block moves to the next of `blocks` on every call, staying at the last one
+    struct MovingChain(RefCell<Vec<BlockRef>>);
+
+    impl ChainTipSource for MovingChain {
+        fn best_full_block(&self) -> std::result::Result<Option<BlockRef>, NodeApiError> {
+            let mut blocks = self.0.borrow_mut();
+            if blocks.len() > 1 {
+                Ok(Some(blocks.remove(0)))
+            } else {
+                Ok(blocks.first().cloned())
+            }
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingBoxes {
+        boxes: Vec<ErgoBox>,
+        reads: AtomicUsize,
+    }
+
+    impl ScanGetBoxes for CountingBoxes {
+        fn get_boxes(&self) -> std::result::Result<Vec<ErgoBox>, ScanError> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            Ok(self.boxes.clone())
+        }
+    }
+
+    fn block(height: u32) -> BlockRef {
+        BlockRef {
+            id: format!("block{height}"),
+            height: BlockHeight(height),
+        }
+    }
+
+    fn token_boxes(pool: Arc<CountingBoxes>) -> PoolTokenBoxes {
+        let empty = || -> Arc<dyn ScanGetBoxes> { Arc::new(CountingBoxes::default()) };
+        PoolTokenBoxes {
+            oracle: empty(),
+            pool,
+            ballot: empty(),
+            refresh: empty(),
+            update: empty(),
+            buyback: None,
+        }
+    }
+
+    #[test]
+    fn snapshot_is_read_again_when_a_block_arrives() {
+        let mut pool_box = force_any_val::<ErgoBox>();
+        pool_box.creation_height = 100;
+        let pool = Arc::new(CountingBoxes {
+            boxes: vec![pool_box.clone()],
+            ..Default::default()
+        });
+        let height = 100;
+        let chain = MovingChain(RefCell::new(vec![
+            block(height),
+            block(height + 1),
+            block(height + 1),
+        ]));
+        let (snapshot, best_block) = token_boxes(pool.clone()).snapshot(&chain).unwrap();
+        assert_eq!(best_block, block(height + 1));
+        assert_eq!(pool.reads.load(Ordering::SeqCst), 2);
+        assert_eq!(snapshot.pool.get_boxes().unwrap(), vec![pool_box]);
+        // the snapshot does not read the source again
+        assert_eq!(pool.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn snapshot_fails_on_moving_chain() {
+        let chain = MovingChain(RefCell::new((1..10).map(block).collect()));
+        assert!(matches!(
+            token_boxes(Arc::new(CountingBoxes::default())).snapshot(&chain),
+            Err(PoolBoxesSnapshotError::ChainMoving(SNAPSHOT_ATTEMPTS))
+        ));
+    }
+
+    #[test]
+    fn snapshot_rejects_boxes_above_best_block() {
+        let mut pool_box = force_any_val::<ErgoBox>();
+        pool_box.creation_height = 100;
+        let pool = Arc::new(CountingBoxes {
+            boxes: vec![pool_box],
+            ..Default::default()
+        });
+        let chain = MovingChain(RefCell::new(vec![block(99)]));
+        assert!(matches!(
+            token_boxes(pool).snapshot(&chain),
+            Err(PoolBoxesSnapshotError::BoxAboveBestBlock {
+                creation_height: 100,
+                ..
+            })
+        ));
+    }
+}
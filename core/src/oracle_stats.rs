@@ -0,0 +1,381 @@
+//! Per-oracle participation and accuracy over the last epochs of the pool history
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use serde::Deserialize;
+use serde::Serialize;
+
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+
+use crate::context::OracleContext;
+use crate::metrics::PoolMetrics;
+use crate::oracle_types::EpochCounter;
+use crate::oracle_types::Rate;
+use crate::pool_config::TokenIds;
+use crate::pool_history::chain_index;
+use crate::pool_history::get_pool_history;
+use crate::pool_history::ChainIndex;
+use crate::pool_history::PoolHistoryCache;
+use crate::pool_history::PoolHistoryEntry;
+use crate::pool_history::PoolHistoryError;
+use crate::pool_history::SharedPoolHistoryCache;
+use crate::snapshot::PoolSnapshot;
+use crate::util::write_file_atomically;
+
+/// Default number of epochs for `/oracles/stats`, `oracle-stats` and the metrics
+pub const DEFAULT_STATS_EPOCHS: usize = 100;
+
+/// Epochs kept in the posted datapoints log
+const MAX_LOGGED_EPOCHS: u32 = 1000;
+
+/// Datapoint box posted by an oracle, as seen by the main loop before the refresh
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostedDatapoint {
+    pub address: String,
+    /// Epoch counter of the pool box at the time of posting
+    pub epoch_counter: EpochCounter,
+    pub rate: Rate,
+}
+
+/// Posted datapoints observed by the main loop. Refresh txs only spend the datapoints within the
+/// deviation range, so this is the only way to know the filtered out ones.
+#[derive(Debug, Clone, Default)]
+pub struct PostedDatapointLog {
+    path: Option<PathBuf>,
+    datapoints: BTreeMap<(EpochCounter, String), Rate>,
+}
+
+impl PostedDatapointLog {
+    /// Loads the log from `path` (an empty log if the file does not exist or cannot be read)
+    pub fn load(path: &Path) -> Self {
+        let datapoints = read_posted_datapoints(path).unwrap_or_else(|e| {
+            log::warn!(
+                "Ignoring the posted datapoints log {}: {}",
+                path.display(),
+                e
+            );
+            vec![]
+        });
+        Self {
+            path: Some(path.to_path_buf()),
+            datapoints: datapoints
+                .into_iter()
+                .map(|d| ((d.epoch_counter, d.address), d.rate))
+                .collect(),
+        }
+    }
+
+    /// Adds the datapoints and saves the log if there are new ones
+    pub fn record(&mut self, datapoints: &[PostedDatapoint]) -> Result<(), PoolHistoryError> {
+        let mut changed = false;
+        for d in datapoints {
+            changed |= self
+                .datapoints
+                .insert((d.epoch_counter, d.address.clone()), d.rate)
+                .is_none();
+        }
+        if !changed {
+            return Ok(());
+        }
+        if let Some(last_epoch) = self.datapoints.keys().map(|(epoch, _)| *epoch).max() {
+            let min_epoch = last_epoch.0.saturating_sub(MAX_LOGGED_EPOCHS);
+            self.datapoints.retain(|(epoch, _), _| epoch.0 >= min_epoch);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), PoolHistoryError> {
+        if let Some(path) = &self.path {
+            let datapoints: Vec<PostedDatapoint> = self
+                .datapoints
+                .iter()
+                .map(|((epoch_counter, address), rate)| PostedDatapoint {
+                    address: address.clone(),
+                    epoch_counter: *epoch_counter,
+                    rate: *rate,
+                })
+                .collect();
+            let json = serde_json::to_string(&datapoints)
+                .map_err(|e| PoolHistoryError::Cache(e.to_string()))?;
+            write_file_atomically(path, &json)
+                .map_err(|e| PoolHistoryError::Cache(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn posted_in_epoch(&self, epoch: EpochCounter) -> impl Iterator<Item = (&String, &Rate)> {
+        self.datapoints
+            .range((epoch, String::new())..)
+            .take_while(move |((e, _), _)| *e == epoch)
+            .map(|((_, address), rate)| (address, rate))
+    }
+}
+
+fn read_posted_datapoints(path: &Path) -> Result<Vec<PostedDatapoint>, PoolHistoryError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let json = fs::read_to_string(path).map_err(|e| PoolHistoryError::Cache(e.to_string()))?;
+    serde_json::from_str(&json).map_err(|e| PoolHistoryError::Cache(e.to_string()))
+}
+
+pub fn get_posted_datapoint_log_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("posted_datapoints.json")
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OracleStats {
+    pub address: String,
+    /// Epochs with the oracle's datapoint included in the refresh
+    pub epochs_participated: u32,
+    /// Epochs with the oracle's datapoint posted, but left out of the refresh (only known for the
+    /// epochs observed by the running oracle)
+    pub epochs_filtered_out: u32,
+    /// Average deviation (%) of the oracle's datapoints from the resulting pool rate
+    pub average_deviation_percent: Option<f64>,
+    /// Refresh txs sent by the oracle
+    pub collections: u32,
+    pub reward_tokens_earned: u64,
+    #[serde(skip)]
+    deviation_sum: f64,
+    #[serde(skip)]
+    deviation_count: u32,
+}
+
+impl OracleStats {
+    fn add_deviation(&mut self, rate: Rate, pool_rate: Rate) {
+        let pool_rate = i64::from(pool_rate);
+        if pool_rate != 0 {
+            let deviation = (i64::from(rate) - pool_rate).abs() as f64 / pool_rate as f64 * 100.0;
+            self.deviation_sum += deviation;
+            self.deviation_count += 1;
+        }
+    }
+}
+
+fn oracle_stats<'a>(
+    stats: &'a mut BTreeMap<String, OracleStats>,
+    address: &str,
+) -> &'a mut OracleStats {
+    stats
+        .entry(address.to_string())
+        .or_insert_with(|| OracleStats {
+            address: address.to_string(),
+            ..Default::default()
+        })
+}
+
+/// Stats over the refresh txs in `history` (newest first, as returned by `get_pool_history`)
+pub fn compute_oracle_stats(
+    history: &[PoolHistoryEntry],
+    posted_log: &PostedDatapointLog,
+) -> Vec<OracleStats> {
+    let mut stats: BTreeMap<String, OracleStats> = BTreeMap::new();
+    for entry in history.iter().filter(|e| !e.oracles.is_empty()) {
+        for oracle in &entry.oracles {
+            let oracle_stats = oracle_stats(&mut stats, &oracle.address);
+            oracle_stats.epochs_participated += 1;
+            oracle_stats.reward_tokens_earned += 1;
+            oracle_stats.add_deviation(oracle.rate, entry.rate);
+        }
+        if let Some(collector) = &entry.collector {
+            let collector_stats = oracle_stats(&mut stats, collector);
+            collector_stats.collections += 1;
+            collector_stats.reward_tokens_earned += entry.oracles.len() as u64;
+        }
+        // the refreshed datapoints were posted in the previous epoch
+        let posted_epoch = EpochCounter(entry.epoch_counter.0.saturating_sub(1));
+        for (address, rate) in posted_log.posted_in_epoch(posted_epoch) {
+            if entry.oracles.iter().all(|o| &o.address != address) {
+                let oracle_stats = oracle_stats(&mut stats, address);
+                oracle_stats.epochs_filtered_out += 1;
+                oracle_stats.add_deviation(*rate, entry.rate);
+            }
+        }
+    }
+    stats
+        .into_values()
+        .map(|mut s| {
+            s.average_deviation_percent =
+                (s.deviation_count > 0).then(|| s.deviation_sum / s.deviation_count as f64);
+            s
+        })
+        .collect()
+}
+
+/// Stats over the last `epochs` pool boxes, starting at `latest_pool_box_id`
+pub fn get_oracle_stats(
+    chain_index: &dyn ChainIndex,
+    history_cache: &mut PoolHistoryCache,
+    posted_log: &PostedDatapointLog,
+    token_ids: &TokenIds,
+    network_prefix: NetworkPrefix,
+    latest_pool_box_id: BoxId,
+    epochs: usize,
+) -> Result<Vec<OracleStats>, PoolHistoryError> {
+    let history = get_pool_history(
+        chain_index,
+        history_cache,
+        token_ids,
+        network_prefix,
+        latest_pool_box_id,
+        epochs,
+    )?;
+    Ok(compute_oracle_stats(&history, posted_log))
+}
+
+/// Main loop part: logs the posted datapoints of each snapshot and updates the stats metrics on
+/// each new pool box. The pool history is walked on a background thread (up to
+/// `DEFAULT_STATS_EPOCHS` txs on a cold cache), the metrics are updated by a later `observe` call
+/// once it is done.
+#[derive(Debug)]
+pub struct OracleStatsTracker {
+    posted_log: PostedDatapointLog,
+    history_cache: SharedPoolHistoryCache,
+    token_ids: TokenIds,
+    last_pool_box_id: Option<BoxId>,
+    /// Computes the stats for `last_pool_box_id`
+    worker: Option<JoinHandle<Result<Vec<OracleStats>, PoolHistoryError>>>,
+}
+
+impl OracleStatsTracker {
+    /// Loads the posted datapoints log from `data_dir`
+    pub fn load(
+        data_dir: &Path,
+        token_ids: TokenIds,
+        history_cache: SharedPoolHistoryCache,
+    ) -> Self {
+        Self {
+            posted_log: PostedDatapointLog::load(&get_posted_datapoint_log_file_path(data_dir)),
+            history_cache,
+            token_ids,
+            last_pool_box_id: None,
+            worker: None,
+        }
+    }
+
+    pub fn observe(
+        &mut self,
+        ctx: &Arc<OracleContext>,
+        snapshot: &PoolSnapshot,
+        metrics: &PoolMetrics,
+    ) -> Result<(), PoolHistoryError> {
+        self.posted_log.record(&snapshot.posted_datapoints)?;
+        if matches!(&self.worker, Some(worker) if worker.is_finished()) {
+            let result = self.worker.take().unwrap().join().unwrap_or_else(|_| {
+                Err(PoolHistoryError::Cache(
+                    "the oracle stats thread panicked".to_string(),
+                ))
+            });
+            match result {
+                Ok(stats) => metrics.update_oracle_stats(&stats),
+                Err(e) => {
+                    // retried on the next call
+                    self.last_pool_box_id = None;
+                    return Err(e);
+                }
+            }
+        }
+        if ctx.oracle_config.metrics_port.is_none()
+            || self.worker.is_some()
+            || self.last_pool_box_id == Some(snapshot.pool_box_id)
+        {
+            return Ok(());
+        }
+        let ctx = ctx.clone();
+        let history_cache = self.history_cache.clone();
+        let posted_log = self.posted_log.clone();
+        let token_ids = self.token_ids.clone();
+        let pool_box_id = snapshot.pool_box_id;
+        self.worker = Some(std::thread::spawn(move || {
+            get_oracle_stats(
+                chain_index(&ctx)?.as_ref(),
+                &mut history_cache.lock().unwrap(),
+                &posted_log,
+                &token_ids,
+                ctx.network_prefix,
+                pool_box_id,
+                DEFAULT_STATS_EPOCHS,
+            )
+        }));
+        self.last_pool_box_id = Some(pool_box_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::chain::transaction::TxId;
+    use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+    use sigma_test_util::force_any_val;
+
+    use crate::oracle_types::BlockHeight;
+    use crate::pool_history::HistoryOracle;
+
+    use super::*;
+
+    fn refresh(
+        epoch: u32,
+        rate: i64,
+        collector: &str,
+        oracles: &[(&str, i64)],
+    ) -> PoolHistoryEntry {
+        PoolHistoryEntry {
+            pool_box_id: force_any_val::<BoxId>(),
+            epoch_counter: EpochCounter(epoch),
+            rate: Rate::from(rate),
+            height: BlockHeight(epoch * 30),
+            tx_id: force_any_val::<TxId>(),
+            oracles: oracles
+                .iter()
+                .map(|(address, rate)| HistoryOracle {
+                    address: address.to_string(),
+                    rate: Rate::from(*rate),
+                })
+                .collect(),
+            collector: Some(collector.to_string()),
+            previous_pool_box_id: Some(force_any_val::<BoxId>()),
+        }
+    }
+
+    #[test]
+    fn stats_from_history_and_posted_datapoints() {
+        let history = vec![
+            refresh(3, 100, "a", &[("b", 100), ("a", 100)]),
+            refresh(2, 200, "b", &[("a", 190), ("b", 210)]),
+        ];
+        let mut log = PostedDatapointLog::default();
+        log.record(&[
+            PostedDatapoint {
+                address: "a".to_string(),
+                epoch_counter: EpochCounter(2),
+                rate: Rate::from(100),
+            },
+            PostedDatapoint {
+                address: "c".to_string(),
+                epoch_counter: EpochCounter(2),
+                rate: Rate::from(150),
+            },
+        ])
+        .unwrap();
+        let stats = compute_oracle_stats(&history, &log);
+        assert_eq!(
+            stats.iter().map(|s| s.address.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(stats[0].epochs_participated, 2);
+        assert_eq!(stats[0].collections, 1);
+        assert_eq!(stats[0].reward_tokens_earned, 4);
+        assert_eq!(stats[0].average_deviation_percent, Some(2.5));
+        assert_eq!(stats[1].collections, 1);
+        assert_eq!(stats[1].reward_tokens_earned, 4);
+        assert_eq!(stats[2].epochs_participated, 0);
+        assert_eq!(stats[2].epochs_filtered_out, 1);
+        assert_eq!(stats[2].average_deviation_percent, Some(50.0));
+    }
+}
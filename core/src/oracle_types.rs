@@ -10,7 +10,9 @@ use derive_more::Sub;
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Serialize, Deserialize, Copy, Clone, From)]
+#[derive(
+    PartialEq, PartialOrd, Eq, Ord, Hash, Debug, Serialize, Deserialize, Copy, Clone, From,
+)]
 #[serde(transparent)]
 pub struct BlockHeight(pub u32);
 
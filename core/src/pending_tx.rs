@@ -0,0 +1,206 @@
+//! Tracks the txs submitted by the oracle until they are confirmed or dropped by the node
+use ergo_lib::chain::transaction::TxId;
+
+use crate::node_interface::node_api::NodeApiError;
+use crate::node_interface::GetTxStatus;
+use crate::node_interface::TxStatus;
+use crate::oracle_types::BlockHeight;
+use crate::reorg::REORG_WINDOW;
+
+/// Blocks a tx may stay unknown to the node after it left the mempool before it is considered
+/// dropped (the node wallet applies a block shortly after the node removes its txs from the
+/// mempool)
+const DROPPED_AFTER_BLOCKS: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingTxKind {
+    Refresh,
+    PublishDatapoint,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PendingTx {
+    pub tx_id: TxId,
+    pub kind: PendingTxKind,
+    pub submitted_height: BlockHeight,
+    /// Height at which the node first did not know the tx (neither in the mempool nor confirmed)
+    #[serde(skip)]
+    unknown_since: Option<BlockHeight>,
+    /// Height at which the tx was seen confirmed, the block including it is at or below it
+    #[serde(skip)]
+    pub confirmed_height: Option<BlockHeight>,
+}
+
+/// Txs that left the tracker in [`PendingTxTracker::update`], and the ones that are stuck
+#[derive(Debug, Default)]
+pub struct PendingTxUpdate {
+    pub confirmed: Vec<PendingTx>,
+    /// Dropped from the mempool (evicted or double spent)
+    pub dropped: Vec<PendingTx>,
+    /// Still in the mempool after `stuck_after_blocks` blocks
+    pub stuck: Vec<PendingTx>,
+}
+
+#[derive(Debug, Default)]
+pub struct PendingTxTracker {
+    txs: Vec<PendingTx>,
+    /// Confirmed in the last `REORG_WINDOW` blocks, checked against the chain reorganizations
+    confirmed: Vec<PendingTx>,
+}
+
+impl PendingTxTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, tx_id: TxId, kind: PendingTxKind, submitted_height: BlockHeight) {
+        self.txs.push(PendingTx {
+            tx_id,
+            kind,
+            submitted_height,
+            unknown_since: None,
+            confirmed_height: None,
+        });
+    }
+
+    /// Txs confirmed in the last `REORG_WINDOW` blocks
+    pub fn recently_confirmed(&self) -> &[PendingTx] {
+        &self.confirmed
+    }
+
+    /// Forgets all txs, e.g. after a reorg rolled them back
+    pub fn clear(&mut self) {
+        self.txs.clear();
+        self.confirmed.clear();
+    }
+
+    /// Checks the status of the txs on the node and drops the confirmed and dropped ones
+    pub fn update(
+        &mut self,
+        tx_status: &dyn GetTxStatus,
+        height: BlockHeight,
+        stuck_after_blocks: u32,
+    ) -> Result<PendingTxUpdate, NodeApiError> {
+        let mut update = PendingTxUpdate::default();
+        let mut still_pending = vec![];
+        for mut tx in std::mem::take(&mut self.txs) {
+            match tx_status.get_tx_status(&tx.tx_id)? {
+                TxStatus::Confirmed => {
+                    log::debug!("Tx {} is confirmed", tx.tx_id);
+                    tx.confirmed_height = Some(height);
+                    self.confirmed.push(tx.clone());
+                    update.confirmed.push(tx);
+                }
+                TxStatus::Unconfirmed => {
+                    tx.unknown_since = None;
+                    if height.0.saturating_sub(tx.submitted_height.0) >= stuck_after_blocks {
+                        update.stuck.push(tx.clone());
+                    }
+                    still_pending.push(tx);
+                }
+                TxStatus::Unknown => match tx.unknown_since {
+                    Some(since) if height.0.saturating_sub(since.0) >= DROPPED_AFTER_BLOCKS => {
+                        log::debug!("Tx {} was dropped by the node", tx.tx_id);
+                        update.dropped.push(tx);
+                    }
+                    Some(_) => still_pending.push(tx),
+                    None => {
+                        tx.unknown_since = Some(height);
+                        still_pending.push(tx);
+                    }
+                },
+            }
+        }
+        self.txs = still_pending;
+        self.confirmed.retain(|tx| {
+            tx.confirmed_height.map_or(false, |confirmed| {
+                height.0.saturating_sub(confirmed.0) <= REORG_WINDOW
+            })
+        });
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use ergo_lib::ergo_chain_types::Digest32;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TxStatusMock(RefCell<HashMap<TxId, TxStatus>>);
+
+    impl TxStatusMock {
+        fn set(&self, tx_id: TxId, status: TxStatus) {
+            self.0.borrow_mut().insert(tx_id, status);
+        }
+    }
+
+    impl GetTxStatus for TxStatusMock {
+        fn get_tx_status(&self, tx_id: &TxId) -> Result<TxStatus, NodeApiError> {
+            Ok(self
+                .0
+                .borrow()
+                .get(tx_id)
+                .copied()
+                .unwrap_or(TxStatus::Unknown))
+        }
+    }
+
+    fn tx_id(byte: u8) -> TxId {
+        TxId(Digest32::from([byte; 32]))
+    }
+
+    #[test]
+    fn confirmed_dropped_and_stuck_txs() {
+        let node = TxStatusMock::default();
+        let mut tracker = PendingTxTracker::new();
+        tracker.add(tx_id(1), PendingTxKind::Refresh, BlockHeight(100));
+        tracker.add(tx_id(2), PendingTxKind::Refresh, BlockHeight(100));
+        tracker.add(tx_id(3), PendingTxKind::PublishDatapoint, BlockHeight(100));
+        node.set(tx_id(1), TxStatus::Unconfirmed);
+        node.set(tx_id(3), TxStatus::Unconfirmed);
+
+        let update = tracker.update(&node, BlockHeight(101), 10).unwrap();
+        assert!(update.confirmed.is_empty() && update.dropped.is_empty());
+        assert!(update.stuck.is_empty());
+
+        node.set(tx_id(1), TxStatus::Confirmed);
+        // the tx unknown for less than DROPPED_AFTER_BLOCKS blocks is still tracked
+        let update = tracker.update(&node, BlockHeight(102), 10).unwrap();
+        assert_eq!(update.confirmed.len(), 1);
+        assert_eq!(update.confirmed[0].tx_id, tx_id(1));
+        assert_eq!(update.confirmed[0].confirmed_height, Some(BlockHeight(102)));
+        assert!(update.dropped.is_empty());
+
+        let update = tracker.update(&node, BlockHeight(110), 10).unwrap();
+        assert_eq!(update.dropped.len(), 1);
+        assert_eq!(update.dropped[0].tx_id, tx_id(2));
+        assert_eq!(update.stuck.len(), 1);
+        assert_eq!(update.stuck[0].tx_id, tx_id(3));
+        assert_eq!(tracker.recently_confirmed().len(), 1);
+
+        // kept for the reorg checks during `REORG_WINDOW` blocks
+        tracker
+            .update(&node, BlockHeight(102 + REORG_WINDOW + 1), 10)
+            .unwrap();
+        assert!(tracker.recently_confirmed().is_empty());
+    }
+
+    #[test]
+    fn tx_known_again_is_not_dropped() {
+        let node = TxStatusMock::default();
+        let mut tracker = PendingTxTracker::new();
+        tracker.add(tx_id(1), PendingTxKind::Refresh, BlockHeight(100));
+        tracker.update(&node, BlockHeight(100), 10).unwrap();
+        // the wallet applied the block with the tx
+        node.set(tx_id(1), TxStatus::Confirmed);
+        let update = tracker.update(&node, BlockHeight(105), 10).unwrap();
+        assert!(update.dropped.is_empty());
+        assert_eq!(update.confirmed.len(), 1);
+    }
+}
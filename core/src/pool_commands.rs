@@ -5,11 +5,10 @@ use thiserror::Error;
 use crate::action_report::PoolActionReport;
 use crate::actions::PoolAction;
 use crate::box_kind::PoolBox;
+use crate::context::OracleContext;
 use crate::datapoint_source::RuntimeDataPointSource;
-use crate::oracle_config::ORACLE_CONFIG;
 use crate::oracle_state::{DataSourceError, OraclePool};
 use crate::oracle_types::BlockHeight;
-use crate::pool_config::POOL_CONFIG;
 use crate::wallet::WalletDataSource;
 
 use self::publish_datapoint::build_publish_first_datapoint_action;
@@ -17,7 +16,9 @@ use self::publish_datapoint::{
     build_subsequent_publish_datapoint_action, PublishDatapointActionError,
 };
 use self::refresh::build_refresh_action;
+use self::refresh::build_refresh_preview;
 use self::refresh::RefreshActionError;
+use self::refresh::RefreshPreview;
 
 pub mod publish_datapoint;
 pub mod refresh;
@@ -45,12 +46,11 @@ pub enum PoolCommandError {
     Digest(#[from] DigestNError),
     #[error("Address encoder error: {0}")]
     AddressEncoder(#[from] AddressEncoderError),
-    #[error("Wrong oracle address type")]
-    WrongOracleAddressType,
 }
 
 pub fn build_action(
     cmd: PoolCommand,
+    ctx: &OracleContext,
     op: &OraclePool,
     wallet: &dyn WalletDataSource,
     height: BlockHeight,
@@ -61,20 +61,16 @@ pub fn build_action(
     let datapoint_boxes_source = op.get_posted_datapoint_boxes_source();
     let pool_box = op.get_pool_box_source().get_pool_box()?;
     let current_epoch_counter = pool_box.epoch_counter();
-    let oracle_public_key =
-        if let Address::P2Pk(public_key) = ORACLE_CONFIG.oracle_address.address() {
-            *public_key.h
-        } else {
-            return Err(PoolCommandError::WrongOracleAddressType);
-        };
+    let oracle_public_key = *ctx.oracle_pk().h;
     match cmd {
         PoolCommand::PublishFirstDataPoint => build_publish_first_datapoint_action(
             wallet,
             height,
             change_address,
             oracle_public_key,
-            POOL_CONFIG.oracle_box_wrapper_inputs.clone(),
+            op.pool_config().oracle_box_wrapper_inputs.clone(),
             datapoint_source,
+            ctx.base_fee,
         )
         .map_err(Into::into)
         .map(|(action, report)| (action.into(), report.into())),
@@ -91,7 +87,8 @@ pub fn build_action(
                     change_address,
                     datapoint_source,
                     new_epoch_counter,
-                    &POOL_CONFIG.token_ids.reward_token_id,
+                    &op.pool_config().token_ids.reward_token_id,
+                    ctx.base_fee,
                 )
                 .map_err(Into::into)
                 .map(|(action, report)| (action.into(), report.into()))
@@ -105,12 +102,12 @@ pub fn build_action(
             op.get_pool_box_source(),
             refresh_box_source,
             datapoint_boxes_source,
-            POOL_CONFIG
+            op.pool_config()
                 .refresh_box_wrapper_inputs
                 .contract_inputs
                 .contract_parameters()
                 .max_deviation_percent() as u32,
-            POOL_CONFIG
+            op.pool_config()
                 .refresh_box_wrapper_inputs
                 .contract_inputs
                 .contract_parameters()
@@ -120,8 +117,34 @@ pub fn build_action(
             change_address,
             &oracle_public_key,
             op.get_buyback_box_source(),
+            ctx.base_fee,
         )
         .map_err(Into::into)
         .map(|(action, report)| (action.into(), report.into())),
     }
 }
+
+/// Refresh outcome from the current posted datapoints, nothing is built or signed
+pub fn preview_refresh(
+    ctx: &OracleContext,
+    op: &OraclePool,
+    height: BlockHeight,
+) -> Result<RefreshPreview, PoolCommandError> {
+    let oracle_public_key = *ctx.oracle_pk().h;
+    let contract_parameters = op
+        .pool_config()
+        .refresh_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters();
+    build_refresh_preview(
+        op.get_pool_box_source(),
+        op.get_refresh_box_source(),
+        op.get_posted_datapoint_boxes_source(),
+        contract_parameters.max_deviation_percent() as u32,
+        contract_parameters.min_data_points(),
+        height,
+        &oracle_public_key,
+        ctx.network_prefix,
+    )
+    .map_err(Into::into)
+}
@@ -4,7 +4,7 @@ use ergo_lib::{
     chain::ergo_box::box_builder::ErgoBoxCandidateBuilderError,
     ergo_chain_types::EcPoint,
     ergotree_interpreter::sigma_protocol::prover::ContextExtension,
-    ergotree_ir::chain::{address::Address, token::TokenAmount},
+    ergotree_ir::chain::{address::Address, ergo_box::box_value::BoxValue, token::TokenAmount},
     wallet::{
         box_selector::{BoxSelector, BoxSelectorError, SimpleBoxSelector},
         tx_builder::{TxBuilder, TxBuilderError},
@@ -18,7 +18,6 @@ use crate::{
     box_kind::{make_oracle_box_candidate, OracleBox, OracleBoxWrapper, OracleBoxWrapperInputs},
     contracts::oracle::{OracleContract, OracleContractError},
     datapoint_source::{DataPointSource, DataPointSourceError},
-    oracle_config::BASE_FEE,
     oracle_state::DataSourceError,
     oracle_types::{BlockHeight, EpochCounter},
     spec_token::{OracleTokenId, RewardTokenId, SpecToken},
@@ -29,8 +28,6 @@ use crate::{
 pub enum PublishDatapointActionError {
     #[error("data source error: {0}")]
     DataSourceError(#[from] DataSourceError),
-    #[error("Oracle box has no reward token")]
-    NoRewardTokenInOracleBox,
     #[error("tx builder error: {0}")]
     TxBuilder(#[from] TxBuilderError),
     #[error("box builder error: {0}")]
@@ -53,6 +50,7 @@ pub fn build_subsequent_publish_datapoint_action(
     datapoint_source: &dyn DataPointSource,
     new_epoch_counter: EpochCounter,
     reward_token_id: &RewardTokenId,
+    tx_fee: BoxValue,
 ) -> Result<(PublishDataPointAction, PublishDatapointActionReport), PublishDatapointActionError> {
     let new_datapoint = datapoint_source.get_datapoint()?;
     let in_oracle_box = local_datapoint_box;
@@ -78,7 +76,6 @@ pub fn build_subsequent_publish_datapoint_action(
     )?;
 
     let mut unspent_boxes = wallet.get_unspent_wallet_boxes()?;
-    let tx_fee = *BASE_FEE;
     let box_selector = SimpleBoxSelector::new();
     let target_tokens = vec![
         in_oracle_box.oracle_token().into(),
@@ -115,10 +112,10 @@ pub fn build_publish_first_datapoint_action(
     public_key: EcPoint,
     inputs: OracleBoxWrapperInputs,
     datapoint_source: &dyn DataPointSource,
+    tx_fee: BoxValue,
 ) -> Result<(PublishDataPointAction, PublishDatapointActionReport), PublishDatapointActionError> {
     let new_datapoint = datapoint_source.get_datapoint()?;
     let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
-    let tx_fee = *BASE_FEE;
     let box_selector = SimpleBoxSelector::new();
     let oracle_token: SpecToken<OracleTokenId> = SpecToken {
         token_id: inputs.oracle_token_id.clone(),
@@ -196,6 +193,7 @@ mod tests {
     use ergo_lib::ergotree_ir::mir::constant::Constant;
     use ergo_lib::ergotree_ir::mir::expr::Expr;
     use ergo_lib::wallet::signing::TransactionContext;
+    use ergo_lib::wallet::tx_builder::SUGGESTED_TX_FEE;
     use ergo_lib::wallet::Wallet;
     use sigma_test_util::force_any_val;
 
@@ -221,7 +219,7 @@ mod tests {
         let in_pool_box = make_pool_box(
             200,
             pool_box_epoch_id,
-            *BASE_FEE,
+            SUGGESTED_TX_FEE(),
             height - EpochLength(32), // from previous epoch
             &pool_contract_parameters,
             &token_ids,
@@ -260,7 +258,7 @@ mod tests {
 
         let wallet_unspent_box = make_wallet_unspent_box(
             secret.public_image(),
-            BASE_FEE.checked_mul_u32(10000).unwrap(),
+            SUGGESTED_TX_FEE().checked_mul_u32(10000).unwrap(),
             None,
         );
         let wallet_mock = WalletDataMock {
@@ -279,6 +277,7 @@ mod tests {
             &datapoint_source,
             pool_box_epoch_id,
             &token_ids.reward_token_id,
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -322,7 +321,7 @@ mod tests {
         let expr: Expr = c.into();
         let ergo_tree = ErgoTree::try_from(expr).unwrap();
 
-        let value = BASE_FEE.checked_mul_u32(10000).unwrap();
+        let value = SUGGESTED_TX_FEE().checked_mul_u32(10000).unwrap();
         let box_with_tokens = ErgoBox::new(
             value,
             ergo_tree.clone(),
@@ -336,7 +335,7 @@ mod tests {
         let unspent_boxes = vec![
             box_with_tokens.clone(),
             ErgoBox::new(
-                *BASE_FEE,
+                SUGGESTED_TX_FEE(),
                 ergo_tree.clone(),
                 None,
                 NonMandatoryRegisters::new(vec![].into_iter().collect()).unwrap(),
@@ -368,6 +367,7 @@ mod tests {
             &MockDatapointSource {
                 datapoint: 201.into(),
             },
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -397,7 +397,7 @@ mod tests {
         let in_pool_box = make_pool_box(
             200,
             pool_box_epoch_id,
-            *BASE_FEE,
+            SUGGESTED_TX_FEE(),
             height - EpochLength(32), // from previous epoch
             &pool_contract_parameters,
             &token_ids,
@@ -436,7 +436,7 @@ mod tests {
 
         let wallet_unspent_box = make_wallet_unspent_box(
             secret.public_image(),
-            BASE_FEE.checked_mul_u32(10000).unwrap(),
+            SUGGESTED_TX_FEE().checked_mul_u32(10000).unwrap(),
             Some(
                 vec![Token {
                     token_id: minted_reward_token_id.token_id(),
@@ -462,6 +462,7 @@ mod tests {
             &datapoint_source,
             pool_box_epoch_id,
             &minted_reward_token_id,
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -8,7 +8,6 @@ use crate::box_kind::PoolBoxWrapper;
 use crate::box_kind::PostedOracleBox;
 use crate::box_kind::RefreshBox;
 use crate::box_kind::RefreshBoxWrapper;
-use crate::oracle_config::BASE_FEE;
 use crate::oracle_state::BuybackBoxSource;
 use crate::oracle_state::DataSourceError;
 use crate::oracle_state::PoolBoxSource;
@@ -27,6 +26,9 @@ use ergo_lib::chain::ergo_box::box_builder::ErgoBoxCandidateBuilderError;
 use ergo_lib::ergo_chain_types::EcPoint;
 use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
 use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBoxCandidate;
 use ergo_lib::ergotree_ir::chain::token::TokenAmount;
 use ergo_lib::wallet::box_selector::BoxSelection;
@@ -35,6 +37,7 @@ use ergo_lib::wallet::box_selector::BoxSelectorError;
 use ergo_lib::wallet::box_selector::SimpleBoxSelector;
 use ergo_lib::wallet::tx_builder::TxBuilder;
 use ergo_lib::wallet::tx_builder::TxBuilderError;
+use serde::Serialize;
 use thiserror::Error;
 
 use std::convert::TryInto;
@@ -75,41 +78,19 @@ pub fn build_refresh_action(
     change_address: Address,
     my_oracle_pk: &EcPoint,
     buyback_box_source: Option<&dyn BuybackBoxSource>,
+    tx_fee: BoxValue,
 ) -> Result<(RefreshAction, RefreshActionReport), RefreshActionError> {
-    let tx_fee = *BASE_FEE;
     let in_pool_box = pool_box_source.get_pool_box()?;
     let in_refresh_box = refresh_box_source.get_refresh_box()?;
     let min_start_height = height - in_refresh_box.contract().epoch_length();
-    let in_pool_box_epoch_id = in_pool_box.epoch_counter();
-    let mut in_oracle_boxes: Vec<PostedOracleBox> = datapoint_src
-        .get_posted_datapoint_boxes()?
-        .into_iter()
-        .filter(|b| {
-            b.get_box().creation_height > min_start_height.0
-                && b.epoch_counter() == in_pool_box_epoch_id
-        })
-        .collect();
+    let (in_oracle_boxes, _) = current_epoch_oracle_boxes(
+        datapoint_src.get_posted_datapoint_boxes()?,
+        in_pool_box.epoch_counter(),
+        min_start_height,
+    );
     // log::info!("Building refresh action {:?}", in_oracle_boxes);
-    let deviation_range = max_deviation_percent;
-    in_oracle_boxes.sort_by_key(|b| b.rate());
-    let valid_in_oracle_boxes_datapoints = filtered_oracle_boxes_by_rate(
-        in_oracle_boxes.iter().map(|b| b.rate()).collect(),
-        deviation_range,
-    )?;
-    let valid_in_oracle_boxes = in_oracle_boxes
-        .into_iter()
-        .filter(|b| valid_in_oracle_boxes_datapoints.contains(&b.rate()))
-        .collect::<Vec<_>>();
-    if (valid_in_oracle_boxes.len() as i32) < min_data_points.0 {
-        return Err(RefreshActionError::FailedToReachConsensus {
-            found_num: valid_in_oracle_boxes.len() as i32,
-            expected: min_data_points.0,
-            found_public_keys: valid_in_oracle_boxes
-                .iter()
-                .map(|b| b.public_key())
-                .collect(),
-        });
-    }
+    let (valid_in_oracle_boxes, _) = split_outliers(in_oracle_boxes, max_deviation_percent)?;
+    check_consensus(&valid_in_oracle_boxes, min_data_points)?;
     let rate = calc_pool_rate(valid_in_oracle_boxes.iter().map(|b| b.rate()).collect());
     let reward_decrement = valid_in_oracle_boxes.len() as u64 * 2;
     let out_refresh_box = build_out_refresh_box(&in_refresh_box, height)?;
@@ -212,6 +193,185 @@ pub fn build_refresh_action(
     Ok((RefreshAction { tx }, report))
 }
 
+/// Why a posted datapoint would be left out of the refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    /// Posted for another epoch
+    WrongEpoch,
+    /// Posted before the epoch window
+    Expired,
+    /// Outside the deviation range
+    Outlier,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewDatapoint {
+    pub address: String,
+    pub rate: Rate,
+    /// Deviation (%) from the resulting pool rate (or the current one if there is none)
+    pub deviation_percent: f64,
+    pub epoch_counter: EpochCounter,
+    pub height: BlockHeight,
+    /// `None` if the datapoint is included
+    pub exclusion_reason: Option<ExclusionReason>,
+}
+
+/// Outcome of the refresh as if it was built now from the posted datapoints
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshPreview {
+    pub height: BlockHeight,
+    pub pool_box_epoch_counter: EpochCounter,
+    pub current_rate: Rate,
+    /// The refresh can be sent after this height
+    pub epoch_end_height: BlockHeight,
+    pub max_deviation_percent: u32,
+    pub min_data_points: MinDatapoints,
+    pub included: Vec<PreviewDatapoint>,
+    pub excluded: Vec<PreviewDatapoint>,
+    /// Pool rate from the included datapoints
+    pub rate: Option<Rate>,
+    /// Why the refresh would fail, `None` if it would succeed
+    pub failure: Option<String>,
+    /// Whether our datapoint is included (only an included oracle can send the refresh)
+    pub can_collect: bool,
+}
+
+/// Runs the datapoint filtering of `build_refresh_action` without building the tx
+#[allow(clippy::too_many_arguments)]
+pub fn build_refresh_preview(
+    pool_box_source: &dyn PoolBoxSource,
+    refresh_box_source: &dyn RefreshBoxSource,
+    datapoint_src: &dyn PostedDatapointBoxesSource,
+    max_deviation_percent: u32,
+    min_data_points: MinDatapoints,
+    height: BlockHeight,
+    my_oracle_pk: &EcPoint,
+    network_prefix: NetworkPrefix,
+) -> Result<RefreshPreview, RefreshActionError> {
+    let in_pool_box = pool_box_source.get_pool_box()?;
+    let in_refresh_box = refresh_box_source.get_refresh_box()?;
+    let epoch_length = in_refresh_box.contract().epoch_length();
+    let min_start_height = height - epoch_length;
+    let (in_oracle_boxes, mut excluded) = current_epoch_oracle_boxes(
+        datapoint_src.get_posted_datapoint_boxes()?,
+        in_pool_box.epoch_counter(),
+        min_start_height,
+    );
+    let (valid_oracle_boxes, failure) =
+        match split_outliers(in_oracle_boxes.clone(), max_deviation_percent) {
+            Ok((valid, outliers)) => {
+                excluded.extend(outliers.into_iter().map(|b| (b, ExclusionReason::Outlier)));
+                let failure = check_consensus(&valid, min_data_points)
+                    .err()
+                    .map(|e| e.to_string());
+                (valid, failure)
+            }
+            Err(e) => {
+                excluded.extend(
+                    in_oracle_boxes
+                        .into_iter()
+                        .map(|b| (b, ExclusionReason::Outlier)),
+                );
+                (vec![], Some(e.to_string()))
+            }
+        };
+    let rate = (!valid_oracle_boxes.is_empty())
+        .then(|| calc_pool_rate(valid_oracle_boxes.iter().map(|b| b.rate()).collect()));
+    let reference_rate = rate.unwrap_or_else(|| in_pool_box.rate());
+    let preview_datapoint = |b: &PostedOracleBox, exclusion_reason| PreviewDatapoint {
+        address: NetworkAddress::new(network_prefix, &Address::P2Pk(b.public_key().into()))
+            .to_base58(),
+        rate: b.rate(),
+        deviation_percent: deviation_percent(b.rate(), reference_rate),
+        epoch_counter: b.epoch_counter(),
+        height: BlockHeight(b.get_box().creation_height),
+        exclusion_reason,
+    };
+    Ok(RefreshPreview {
+        height,
+        pool_box_epoch_counter: in_pool_box.epoch_counter(),
+        current_rate: in_pool_box.rate(),
+        epoch_end_height: BlockHeight(in_pool_box.get_box().creation_height) + epoch_length,
+        max_deviation_percent,
+        min_data_points,
+        included: valid_oracle_boxes
+            .iter()
+            .map(|b| preview_datapoint(b, None))
+            .collect(),
+        excluded: excluded
+            .iter()
+            .map(|(b, reason)| preview_datapoint(b, Some(*reason)))
+            .collect(),
+        rate,
+        can_collect: valid_oracle_boxes
+            .iter()
+            .any(|b| &b.public_key() == my_oracle_pk),
+        failure,
+    })
+}
+
+fn deviation_percent(rate: Rate, reference_rate: Rate) -> f64 {
+    let reference_rate = i64::from(reference_rate);
+    if reference_rate == 0 {
+        return 0.0;
+    }
+    (i64::from(rate) - reference_rate).abs() as f64 / reference_rate as f64 * 100.0
+}
+
+/// Splits the posted oracle boxes into the ones of the current epoch (sorted by rate) and the ones
+/// the refresh can't use (posted in another epoch or before the epoch window)
+fn current_epoch_oracle_boxes(
+    posted_boxes: Vec<PostedOracleBox>,
+    pool_box_epoch_id: EpochCounter,
+    min_start_height: BlockHeight,
+) -> (
+    Vec<PostedOracleBox>,
+    Vec<(PostedOracleBox, ExclusionReason)>,
+) {
+    let mut current = vec![];
+    let mut excluded = vec![];
+    for b in posted_boxes {
+        if b.epoch_counter() != pool_box_epoch_id {
+            excluded.push((b, ExclusionReason::WrongEpoch));
+        } else if b.get_box().creation_height <= min_start_height.0 {
+            excluded.push((b, ExclusionReason::Expired));
+        } else {
+            current.push(b);
+        }
+    }
+    current.sort_by_key(|b| b.rate());
+    (current, excluded)
+}
+
+/// Splits the oracle boxes into the ones within the deviation range and the outliers
+fn split_outliers(
+    oracle_boxes: Vec<PostedOracleBox>,
+    max_deviation_percent: u32,
+) -> Result<(Vec<PostedOracleBox>, Vec<PostedOracleBox>), RefreshActionError> {
+    let valid_datapoints = filtered_oracle_boxes_by_rate(
+        oracle_boxes.iter().map(|b| b.rate()).collect(),
+        max_deviation_percent,
+    )?;
+    Ok(oracle_boxes
+        .into_iter()
+        .partition(|b| valid_datapoints.contains(&b.rate())))
+}
+
+fn check_consensus(
+    valid_oracle_boxes: &[PostedOracleBox],
+    min_data_points: MinDatapoints,
+) -> Result<(), RefreshActionError> {
+    if (valid_oracle_boxes.len() as i32) < min_data_points.0 {
+        return Err(RefreshActionError::FailedToReachConsensus {
+            found_num: valid_oracle_boxes.len() as i32,
+            expected: min_data_points.0,
+            found_public_keys: valid_oracle_boxes.iter().map(|b| b.public_key()).collect(),
+        });
+    }
+    Ok(())
+}
+
 fn filtered_oracle_boxes_by_rate<T>(
     oracle_boxes: Vec<T>,
     deviation_range: u32,
@@ -378,6 +538,7 @@ mod tests {
     use ergo_lib::ergotree_ir::chain::ergo_box::NonMandatoryRegisters;
     use ergo_lib::ergotree_ir::chain::token::Token;
     use ergo_lib::wallet::signing::TransactionContext;
+    use ergo_lib::wallet::tx_builder::SUGGESTED_TX_FEE;
     use ergo_lib::wallet::Wallet;
     use sigma_test_util::force_any_val;
 
@@ -391,7 +552,6 @@ mod tests {
     use crate::contracts::refresh::RefreshContract;
     use crate::contracts::refresh::RefreshContractInputs;
     use crate::contracts::refresh::RefreshContractParameters;
-    use crate::oracle_config::BASE_FEE;
     use crate::oracle_state::DataSourceError;
     use crate::oracle_types::EpochLength;
     use crate::pool_commands::test_utils::generate_token_ids;
@@ -514,11 +674,12 @@ mod tests {
             contract_inputs: refresh_contract_inputs,
         };
         let pool_box_epoch_id = EpochCounter(1);
-        let in_refresh_box = make_refresh_box(*BASE_FEE, &inputs, height - EpochLength(32));
+        let in_refresh_box =
+            make_refresh_box(SUGGESTED_TX_FEE(), &inputs, height - EpochLength(32));
         let in_pool_box = make_pool_box(
             200,
             pool_box_epoch_id,
-            *BASE_FEE,
+            SUGGESTED_TX_FEE(),
             height - EpochLength(32), // from previous epoch
             &pool_contract_parameters,
             &token_ids,
@@ -540,7 +701,7 @@ mod tests {
             oracle_pub_keys.clone(),
             vec![199, 70, 196, 197, 198, 200],
             pool_box_epoch_id,
-            BASE_FEE.checked_mul_u32(100).unwrap(),
+            SUGGESTED_TX_FEE().checked_mul_u32(100).unwrap(),
             height - EpochLength(9),
             &oracle_contract_parameters,
             &token_ids,
@@ -564,7 +725,7 @@ mod tests {
         .unwrap();
         let wallet_unspent_box = make_wallet_unspent_box(
             secret.public_image(),
-            BASE_FEE.checked_mul_u32(10000).unwrap(),
+            SUGGESTED_TX_FEE().checked_mul_u32(10000).unwrap(),
             None,
         );
         let wallet_mock = WalletDataMock {
@@ -585,6 +746,7 @@ mod tests {
             change_address.address(),
             &oracle_pub_key,
             None,
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -615,7 +777,7 @@ mod tests {
                 oracle_pub_keys.clone(),
                 vec![199, 70, 196, 197, 198, 200],
                 EpochCounter(pool_box_epoch_id.0 + 1),
-                BASE_FEE.checked_mul_u32(100).unwrap(),
+                SUGGESTED_TX_FEE().checked_mul_u32(100).unwrap(),
                 height - EpochLength(9),
                 &oracle_contract_parameters,
                 &token_ids,
@@ -632,6 +794,7 @@ mod tests {
             change_address.address(),
             &oracle_pub_key,
             None,
+            SUGGESTED_TX_FEE(),
         );
         dbg!(&wrong_epoch_res);
         assert!(matches!(
@@ -647,7 +810,7 @@ mod tests {
 
         let buyback_box = make_wallet_unspent_box(
             secret.public_image(),
-            *BASE_FEE,
+            SUGGESTED_TX_FEE(),
             Some(
                 vec![
                     Token {
@@ -681,6 +844,7 @@ mod tests {
             change_address.address(),
             &oracle_pub_key,
             Some(&buyback_source),
+            SUGGESTED_TX_FEE(),
         )
         .unwrap();
 
@@ -730,6 +894,100 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_refresh_preview() {
+        let height = BlockHeight(force_any_val::<ErgoStateContext>().pre_header.height);
+        let pool_contract_parameters = PoolContractParameters::default();
+        let oracle_contract_parameters = OracleContractParameters::default();
+        let token_ids = generate_token_ids();
+        let inputs = RefreshBoxWrapperInputs {
+            refresh_nft_token_id: token_ids.refresh_nft_token_id.clone(),
+            contract_inputs: RefreshContractInputs::build_with(
+                RefreshContractParameters::default(),
+                token_ids.oracle_token_id.clone(),
+                token_ids.pool_nft_token_id.clone(),
+            )
+            .unwrap(),
+        };
+        let pool_box_epoch_id = EpochCounter(1);
+        let pool_box_mock = PoolBoxMock {
+            pool_box: make_pool_box(
+                200,
+                pool_box_epoch_id,
+                SUGGESTED_TX_FEE(),
+                height - EpochLength(32),
+                &pool_contract_parameters,
+                &token_ids,
+            ),
+        };
+        let refresh_box_mock = RefreshBoxMock {
+            refresh_box: make_refresh_box(SUGGESTED_TX_FEE(), &inputs, height - EpochLength(32)),
+        };
+        let oracle_pub_keys: Vec<EcPoint> = (0..6).map(|_| force_any_val::<EcPoint>()).collect();
+        let mut datapoints = make_datapoint_boxes(
+            oracle_pub_keys[..5].to_vec(),
+            vec![199, 70, 196, 197, 198],
+            pool_box_epoch_id,
+            SUGGESTED_TX_FEE().checked_mul_u32(100).unwrap(),
+            height - EpochLength(9),
+            &oracle_contract_parameters,
+            &token_ids,
+        );
+        datapoints.append(&mut make_datapoint_boxes(
+            oracle_pub_keys[5..].to_vec(),
+            vec![200],
+            EpochCounter(0),
+            SUGGESTED_TX_FEE().checked_mul_u32(100).unwrap(),
+            height - EpochLength(9),
+            &oracle_contract_parameters,
+            &token_ids,
+        ));
+        let datapoint_source = DatapointSourceMock { datapoints };
+
+        let preview = build_refresh_preview(
+            &pool_box_mock,
+            &refresh_box_mock,
+            &datapoint_source,
+            5,
+            MinDatapoints(4),
+            height,
+            &oracle_pub_keys[0],
+            NetworkPrefix::Mainnet,
+        )
+        .unwrap();
+        assert_eq!(preview.included.len(), 4);
+        assert_eq!(preview.rate, Some(Rate::from(197)));
+        assert!(preview.failure.is_none());
+        assert!(preview.can_collect);
+        let mut reasons: Vec<_> = preview
+            .excluded
+            .iter()
+            .map(|d| (i64::from(d.rate), d.exclusion_reason))
+            .collect();
+        reasons.sort_by_key(|(rate, _)| *rate);
+        assert_eq!(
+            reasons,
+            vec![
+                (70, Some(ExclusionReason::Outlier)),
+                (200, Some(ExclusionReason::WrongEpoch))
+            ]
+        );
+
+        let preview = build_refresh_preview(
+            &pool_box_mock,
+            &refresh_box_mock,
+            &datapoint_source,
+            5,
+            MinDatapoints(5),
+            height,
+            &oracle_pub_keys[1],
+            NetworkPrefix::Mainnet,
+        )
+        .unwrap();
+        assert!(preview.failure.is_some());
+        assert!(!preview.can_collect);
+    }
+
     #[test]
     fn test_oracle_deviation_check() {
         assert_eq!(
@@ -32,6 +32,7 @@ use crate::box_kind::OracleBoxWrapperInputs;
 use crate::box_kind::PoolBoxWrapper;
 use crate::box_kind::PoolBoxWrapperInputs;
 use crate::box_kind::UpdateBoxWrapper;
+use crate::box_kind::UpdateBoxWrapperInputs;
 use crate::box_kind::VoteBallotBoxWrapper;
 use crate::contracts::oracle::OracleContract;
 use crate::contracts::oracle::OracleContractError;
@@ -40,6 +41,9 @@ use crate::contracts::oracle::OracleContractParameters;
 use crate::contracts::pool::PoolContract;
 use crate::contracts::pool::PoolContractInputs;
 use crate::contracts::pool::PoolContractParameters;
+use crate::contracts::update::UpdateContract;
+use crate::contracts::update::UpdateContractInputs;
+use crate::contracts::update::UpdateContractParameters;
 use crate::node_interface::SignTransactionWithInputs;
 use crate::oracle_state::BuybackBoxSource;
 use crate::oracle_state::LocalBallotBoxSource;
@@ -201,6 +205,43 @@ pub(crate) fn make_pool_box(
     .unwrap()
 }
 
+pub(crate) fn make_update_box(
+    creation_height: BlockHeight,
+    token_ids: &TokenIds,
+) -> UpdateBoxWrapper {
+    let update_contract_inputs = UpdateContractInputs::build_with(
+        UpdateContractParameters::default(),
+        token_ids.pool_nft_token_id.clone(),
+        token_ids.ballot_token_id.clone(),
+    )
+    .unwrap();
+    let tokens = vec![Token::from((
+        token_ids.update_nft_token_id.token_id(),
+        1u64.try_into().unwrap(),
+    ))]
+    .try_into()
+    .unwrap();
+    UpdateBoxWrapper::new(
+        ErgoBox::new(
+            BoxValue::SAFE_USER_MIN,
+            UpdateContract::checked_load(&update_contract_inputs)
+                .unwrap()
+                .ergo_tree(),
+            Some(tokens),
+            NonMandatoryRegisters::empty(),
+            creation_height.0,
+            force_any_val::<TxId>(),
+            0,
+        )
+        .unwrap(),
+        &UpdateBoxWrapperInputs {
+            contract_inputs: update_contract_inputs,
+            update_nft_token_id: token_ids.update_nft_token_id.clone(),
+        },
+    )
+    .unwrap()
+}
+
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn make_datapoint_box(
     pub_key: EcPoint,
@@ -1,7 +1,6 @@
 use std::path::Path;
 use std::path::PathBuf;
 
-use anyhow::anyhow;
 use anyhow::Context;
 use once_cell::sync;
 use serde::Deserialize;
@@ -29,11 +28,6 @@ use crate::spec_token::UpdateTokenId;
 
 pub const DEFAULT_POOL_CONFIG_FILE_NAME: &str = "pool_config.yaml";
 pub static POOL_CONFIG_FILE_PATH: sync::OnceCell<PathBuf> = sync::OnceCell::new();
-lazy_static! {
-    pub static ref POOL_CONFIG: PoolConfig = PoolConfig::load().unwrap();
-    pub static ref POOL_CONFIG_OPT: Result<PoolConfig, String> =
-        PoolConfig::load().map_err(|e| e.to_string());
-}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(
@@ -156,14 +150,12 @@ impl PoolConfig {
         })
     }
 
-    fn load() -> Result<Self, anyhow::Error> {
-        let config_file_path = POOL_CONFIG_FILE_PATH
-            .get()
-            .ok_or_else(|| anyhow!("Pool config file path not set"))?;
-        log::info!("Loading pool config from {}", config_file_path.display());
-        let config_str = std::fs::read_to_string(config_file_path).context(format!(
+    /// Loads a pool definition (`pool_config.yaml`)
+    pub fn load_from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        log::info!("Loading pool config from {}", path.display());
+        let config_str = std::fs::read_to_string(path).context(format!(
             "failed to load pool config file from {}",
-            config_file_path.display()
+            path.display()
         ))?;
         Self::load_from_str(&config_str)
     }
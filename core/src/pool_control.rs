@@ -0,0 +1,139 @@
+//! Operator overrides of the main loop decisions (set through the admin API)
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::pool_commands::PoolCommand;
+use crate::state::PoolState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ForcedCommand {
+    Republish,
+    Refresh,
+}
+
+#[derive(Debug, Default)]
+pub struct PoolControl {
+    publishing_paused: AtomicBool,
+    forced_command: Mutex<Option<ForcedCommand>>,
+}
+
+impl PoolControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pause_publishing(&self) {
+        self.publishing_paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume_publishing(&self) {
+        self.publishing_paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_publishing_paused(&self) -> bool {
+        self.publishing_paused.load(Ordering::SeqCst)
+    }
+
+    /// Ask the main loop to attempt the command on its next iteration
+    pub fn force(&self, cmd: ForcedCommand) {
+        *self.forced_command.lock().unwrap() = Some(cmd);
+    }
+
+    pub fn forced_command(&self) -> Option<ForcedCommand> {
+        *self.forced_command.lock().unwrap()
+    }
+
+    /// Applies the overrides to the command chosen by the main loop. A forced command is consumed
+    /// even if it cannot be applied.
+    pub fn apply(&self, cmd: Option<PoolCommand>, pool_state: &PoolState) -> Option<PoolCommand> {
+        let forced_command = self.forced_command.lock().unwrap().take();
+        let cmd = match (forced_command, pool_state) {
+            (Some(forced_command), PoolState::NeedsBootstrap) => {
+                log::warn!("Ignoring forced {forced_command:?}, the pool is not bootstrapped");
+                cmd
+            }
+            (Some(ForcedCommand::Refresh), PoolState::LiveEpoch(_)) => {
+                log::info!("Attempting a forced refresh");
+                Some(PoolCommand::Refresh)
+            }
+            (Some(ForcedCommand::Republish), PoolState::LiveEpoch(live_epoch)) => {
+                log::info!("Attempting a forced republish");
+                if live_epoch.local_datapoint_box_state.is_some() {
+                    Some(PoolCommand::PublishSubsequentDataPoint { republish: true })
+                } else {
+                    Some(PoolCommand::PublishFirstDataPoint)
+                }
+            }
+            (None, _) => cmd,
+        };
+        match cmd {
+            Some(PoolCommand::PublishFirstDataPoint)
+            | Some(PoolCommand::PublishSubsequentDataPoint { .. })
+                if self.is_publishing_paused() =>
+            {
+                log::info!("Publishing is paused, skipping {:?}", cmd);
+                None
+            }
+            Some(_) | None => cmd,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::oracle_state::LiveEpochState;
+    use crate::oracle_state::LocalDatapointState;
+    use crate::oracle_types::BlockHeight;
+    use crate::oracle_types::EpochCounter;
+
+    use super::*;
+
+    fn live_epoch() -> PoolState {
+        PoolState::LiveEpoch(LiveEpochState {
+            pool_box_epoch_id: EpochCounter(1),
+            local_datapoint_box_state: Some(LocalDatapointState::Collected {
+                height: BlockHeight(100),
+            }),
+            latest_pool_datapoint: 1.into(),
+            latest_pool_box_height: BlockHeight(100),
+        })
+    }
+
+    #[test]
+    fn paused_publishing_skips_publish_but_not_refresh() {
+        let control = PoolControl::new();
+        control.pause_publishing();
+        assert!(control
+            .apply(
+                Some(PoolCommand::PublishSubsequentDataPoint { republish: false }),
+                &live_epoch()
+            )
+            .is_none());
+        assert!(matches!(
+            control.apply(Some(PoolCommand::Refresh), &live_epoch()),
+            Some(PoolCommand::Refresh)
+        ));
+        control.resume_publishing();
+        assert!(matches!(
+            control.apply(Some(PoolCommand::PublishFirstDataPoint), &live_epoch()),
+            Some(PoolCommand::PublishFirstDataPoint)
+        ));
+    }
+
+    #[test]
+    fn forced_command_is_applied_once() {
+        let control = PoolControl::new();
+        control.force(ForcedCommand::Republish);
+        assert!(matches!(
+            control.apply(None, &live_epoch()),
+            Some(PoolCommand::PublishSubsequentDataPoint { republish: true })
+        ));
+        assert!(control.apply(None, &live_epoch()).is_none());
+        control.force(ForcedCommand::Refresh);
+        assert!(control.apply(None, &PoolState::NeedsBootstrap).is_none());
+        assert_eq!(control.forced_command(), None);
+    }
+}
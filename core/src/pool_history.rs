@@ -0,0 +1,514 @@
+//! Past pool boxes, reconstructed by walking back the transactions that created them
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergo_chain_types::EcPoint;
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::ergo_box::NonMandatoryRegisterId;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::mir::constant::TryExtractFrom;
+use ergo_lib::ergotree_ir::mir::constant::TryExtractInto;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::address_util::pks_to_network_addresses;
+use crate::context::OracleContext;
+use crate::explorer_api::ExplorerApi;
+use crate::explorer_api::ExplorerApiError;
+use crate::node_interface::node_api::IndexedTransaction;
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::oracle_types::Rate;
+use crate::pool_config::TokenIds;
+use crate::spec_token::TokenIdKind;
+use crate::util::write_file_atomically;
+
+/// Max number of epochs returned by `/poolHistory`
+pub const MAX_HISTORY_EPOCHS: usize = 1000;
+
+/// Format of the cache file, the files of another version are ignored (version 1 did not read the
+/// collector from the refresh tx outputs)
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Debug, Error)]
+pub enum PoolHistoryError {
+    #[error("node api error: {0}")]
+    NodeApi(#[from] NodeApiError),
+    #[error("explorer api error: {0}")]
+    ExplorerApi(#[from] ExplorerApiError),
+    #[error("box {0:?} is not a pool box")]
+    NotPoolBox(BoxId),
+    #[error("pool history cache error: {0}")]
+    Cache(String),
+}
+
+/// Confirmed boxes and transactions lookup (node blockchain indexer or explorer API)
+pub trait ChainIndex {
+    fn get_box(&self, box_id: &BoxId) -> Result<ErgoBox, PoolHistoryError>;
+    fn get_transaction(&self, tx_id: &TxId) -> Result<IndexedTransaction, PoolHistoryError>;
+}
+
+impl ChainIndex for NodeApi {
+    fn get_box(&self, box_id: &BoxId) -> Result<ErgoBox, PoolHistoryError> {
+        Ok(self.get_indexed_box(box_id)?)
+    }
+
+    fn get_transaction(&self, tx_id: &TxId) -> Result<IndexedTransaction, PoolHistoryError> {
+        Ok(self.get_indexed_transaction(tx_id)?)
+    }
+}
+
+impl<T: ChainIndex + ?Sized> ChainIndex for Arc<T> {
+    fn get_box(&self, box_id: &BoxId) -> Result<ErgoBox, PoolHistoryError> {
+        self.as_ref().get_box(box_id)
+    }
+
+    fn get_transaction(&self, tx_id: &TxId) -> Result<IndexedTransaction, PoolHistoryError> {
+        self.as_ref().get_transaction(tx_id)
+    }
+}
+
+impl ChainIndex for ExplorerApi {
+    fn get_box(&self, box_id: &BoxId) -> Result<ErgoBox, PoolHistoryError> {
+        Ok(self.get_box_v1(box_id)?)
+    }
+
+    fn get_transaction(&self, tx_id: &TxId) -> Result<IndexedTransaction, PoolHistoryError> {
+        Ok(self.get_indexed_transaction_v1(tx_id)?)
+    }
+}
+
+/// Uses the node's blockchain indexer if it is enabled, the explorer API otherwise
+pub fn chain_index(ctx: &OracleContext) -> Result<Box<dyn ChainIndex>, PoolHistoryError> {
+    if ctx.node_api.get_indexed_height()?.is_some() {
+        Ok(Box::new(ctx.node_api.clone()))
+    } else {
+        let explorer_api = ctx.explorer_api();
+        log::debug!(
+            "Node blockchain indexer is disabled, using explorer API {}",
+            explorer_api.url
+        );
+        Ok(Box::new(explorer_api))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryOracle {
+    pub address: String,
+    pub rate: Rate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolHistoryEntry {
+    pub pool_box_id: BoxId,
+    pub epoch_counter: EpochCounter,
+    pub rate: Rate,
+    /// Creation height of the pool box
+    pub height: BlockHeight,
+    /// Tx that created the pool box
+    pub tx_id: TxId,
+    /// Oracle boxes spent by the refresh tx (empty for other pool box txs, e.g. update)
+    pub oracles: Vec<HistoryOracle>,
+    /// Oracle that collected the datapoints and sent the refresh tx
+    pub collector: Option<String>,
+    /// `None` for the first pool box
+    pub previous_pool_box_id: Option<BoxId>,
+}
+
+/// Pool boxes never change once created, so the entries are kept forever
+#[derive(Debug, Default)]
+pub struct PoolHistoryCache {
+    path: Option<PathBuf>,
+    entries: HashMap<BoxId, PoolHistoryEntry>,
+}
+
+/// Cache shared by the main loop and the REST API of a pool, so that they do not overwrite each
+/// other's entries
+pub type SharedPoolHistoryCache = Arc<Mutex<PoolHistoryCache>>;
+
+#[derive(Serialize, Deserialize)]
+struct PoolHistoryCacheFile {
+    version: u32,
+    entries: Vec<PoolHistoryEntry>,
+}
+
+impl PoolHistoryCache {
+    /// In-memory only cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the cache from `path`. The cache starts empty if the file does not exist or cannot be
+    /// read, it is only a copy of the chain.
+    pub fn load(path: &Path) -> Self {
+        let entries = read_cache_file(path).unwrap_or_else(|e| {
+            log::warn!("Ignoring the pool history cache {}: {}", path.display(), e);
+            vec![]
+        });
+        Self {
+            path: Some(path.to_path_buf()),
+            entries: entries.into_iter().map(|e| (e.pool_box_id, e)).collect(),
+        }
+    }
+
+    fn save(&self) -> Result<(), PoolHistoryError> {
+        if let Some(path) = &self.path {
+            let mut entries: Vec<PoolHistoryEntry> = self.entries.values().cloned().collect();
+            entries.sort_by_key(|e| e.height);
+            let json = serde_json::to_string(&PoolHistoryCacheFile {
+                version: CACHE_VERSION,
+                entries,
+            })
+            .map_err(|e| PoolHistoryError::Cache(e.to_string()))?;
+            write_file_atomically(path, &json)
+                .map_err(|e| PoolHistoryError::Cache(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+fn read_cache_file(path: &Path) -> Result<Vec<PoolHistoryEntry>, PoolHistoryError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let json = fs::read_to_string(path).map_err(|e| PoolHistoryError::Cache(e.to_string()))?;
+    let file: PoolHistoryCacheFile =
+        serde_json::from_str(&json).map_err(|e| PoolHistoryError::Cache(e.to_string()))?;
+    if file.version != CACHE_VERSION {
+        return Err(PoolHistoryError::Cache(format!(
+            "version {} (expected {})",
+            file.version, CACHE_VERSION
+        )));
+    }
+    Ok(file.entries)
+}
+
+pub fn get_pool_history_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("pool_history.json")
+}
+
+/// Returns up to `epochs` pool boxes, newest first, starting at `latest_pool_box_id`
+pub fn get_pool_history(
+    chain_index: &dyn ChainIndex,
+    cache: &mut PoolHistoryCache,
+    token_ids: &TokenIds,
+    network_prefix: NetworkPrefix,
+    latest_pool_box_id: BoxId,
+    epochs: usize,
+) -> Result<Vec<PoolHistoryEntry>, PoolHistoryError> {
+    let mut history = vec![];
+    let mut next_box_id = Some(latest_pool_box_id);
+    let mut cache_updated = false;
+    while let Some(box_id) = next_box_id {
+        if history.len() >= epochs {
+            break;
+        }
+        let entry = match cache.entries.get(&box_id) {
+            Some(entry) => entry.clone(),
+            None => {
+                let pool_box = chain_index.get_box(&box_id)?;
+                let entry = read_pool_box(chain_index, &pool_box, token_ids, network_prefix)?;
+                cache.entries.insert(box_id, entry.clone());
+                cache_updated = true;
+                entry
+            }
+        };
+        next_box_id = entry.previous_pool_box_id;
+        history.push(entry);
+    }
+    if cache_updated {
+        cache.save()?;
+    }
+    Ok(history)
+}
+
+fn read_pool_box(
+    chain_index: &dyn ChainIndex,
+    pool_box: &ErgoBox,
+    token_ids: &TokenIds,
+    network_prefix: NetworkPrefix,
+) -> Result<PoolHistoryEntry, PoolHistoryError> {
+    let pool_nft_token_id = token_ids.pool_nft_token_id.token_id();
+    if !has_token(pool_box, &pool_nft_token_id) {
+        return Err(PoolHistoryError::NotPoolBox(pool_box.box_id()));
+    }
+    let rate: i64 = register(pool_box, NonMandatoryRegisterId::R4)
+        .ok_or_else(|| PoolHistoryError::NotPoolBox(pool_box.box_id()))?;
+    let epoch_counter: i32 = register(pool_box, NonMandatoryRegisterId::R5)
+        .ok_or_else(|| PoolHistoryError::NotPoolBox(pool_box.box_id()))?;
+    let tx = chain_index.get_transaction(&pool_box.transaction_id)?;
+    // the pool box is the first input of both refresh and update txs, and the second input is the
+    // refresh (update) box, the oracle boxes follow in the refresh tx
+    let previous_pool_box_id = match tx.inputs.first() {
+        Some(input) => {
+            let input_box = chain_index.get_box(&input.box_id)?;
+            has_token(&input_box, &pool_nft_token_id).then_some(input.box_id)
+        }
+        None => None,
+    };
+    let oracle_token_id = token_ids.oracle_token_id.token_id();
+    let reward_token_id = token_ids.reward_token_id.token_id();
+    let mut oracle_pks: Vec<EcPoint> = vec![];
+    let mut oracle_rates: Vec<i64> = vec![];
+    let mut collector_index = None;
+    if previous_pool_box_id.is_some() {
+        for input in tx.inputs.iter().skip(2) {
+            let input_box = chain_index.get_box(&input.box_id)?;
+            if !has_token(&input_box, &oracle_token_id) {
+                continue;
+            }
+            if let (Some(pk), Some(rate)) = (
+                register::<EcPoint>(&input_box, NonMandatoryRegisterId::R4),
+                register::<i64>(&input_box, NonMandatoryRegisterId::R6),
+            ) {
+                // each oracle box gets 1 reward token, the collector's gets 1 more per collected
+                // oracle box
+                let reward_increment = collected_oracle_box(&tx.outputs, &oracle_token_id, &pk)
+                    .map(|out_box| {
+                        token_amount(out_box, &reward_token_id)
+                            .saturating_sub(token_amount(&input_box, &reward_token_id))
+                    });
+                if reward_increment.map_or(false, |increment| increment > 1) {
+                    collector_index = Some(oracle_pks.len());
+                }
+                oracle_pks.push(pk);
+                oracle_rates.push(rate);
+            }
+        }
+    }
+    let oracles: Vec<HistoryOracle> = pks_to_network_addresses(oracle_pks, network_prefix)
+        .into_iter()
+        .zip(oracle_rates)
+        .map(|(address, rate)| HistoryOracle {
+            address: address.to_base58(),
+            rate: rate.into(),
+        })
+        .collect();
+    Ok(PoolHistoryEntry {
+        pool_box_id: pool_box.box_id(),
+        epoch_counter: EpochCounter(epoch_counter as u32),
+        rate: rate.into(),
+        height: BlockHeight(pool_box.creation_height),
+        tx_id: tx.id,
+        collector: collector_index.map(|i| oracles[i].address.clone()),
+        oracles,
+        previous_pool_box_id,
+    })
+}
+
+/// Output oracle box of the oracle with the public key `pk`
+fn collected_oracle_box<'a>(
+    outputs: &'a [ErgoBox],
+    oracle_token_id: &TokenId,
+    pk: &EcPoint,
+) -> Option<&'a ErgoBox> {
+    outputs.iter().find(|out_box| {
+        has_token(out_box, oracle_token_id)
+            && register::<EcPoint>(out_box, NonMandatoryRegisterId::R4).as_ref() == Some(pk)
+    })
+}
+
+fn token_amount(ergo_box: &ErgoBox, token_id: &TokenId) -> u64 {
+    ergo_box
+        .tokens
+        .as_ref()
+        .and_then(|tokens| tokens.iter().find(|t| t.token_id == *token_id))
+        .map(|t| *t.amount.as_u64())
+        .unwrap_or(0)
+}
+
+fn has_token(ergo_box: &ErgoBox, token_id: &TokenId) -> bool {
+    ergo_box
+        .tokens
+        .as_ref()
+        .map(|tokens| tokens.iter().any(|t| t.token_id == *token_id))
+        .unwrap_or(false)
+}
+
+fn register<T: TryExtractFrom<Constant>>(
+    ergo_box: &ErgoBox,
+    register_id: NonMandatoryRegisterId,
+) -> Option<T> {
+    ergo_box
+        .get_register(register_id.into())
+        .and_then(|c| c.try_extract_into::<T>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use sigma_test_util::force_any_val;
+
+    use crate::box_kind::PoolBox;
+    use crate::contracts::pool::PoolContractParameters;
+    use crate::pool_commands::test_utils::generate_token_ids;
+    use crate::pool_commands::test_utils::make_datapoint_box;
+    use crate::pool_commands::test_utils::make_pool_box;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct ChainIndexMock {
+        boxes: HashMap<BoxId, ErgoBox>,
+        txs: HashMap<TxId, IndexedTransaction>,
+    }
+
+    impl ChainIndexMock {
+        /// `output` is the pool box, followed by `other_outputs` in the tx
+        fn add_tx(&mut self, output: &ErgoBox, inputs: Vec<ErgoBox>, other_outputs: Vec<ErgoBox>) {
+            let outputs: Vec<ErgoBox> = std::iter::once(output.clone())
+                .chain(other_outputs)
+                .collect();
+            let tx = IndexedTransaction {
+                id: output.transaction_id,
+                inputs: inputs
+                    .iter()
+                    .map(|b| crate::node_interface::node_api::IndexedInput { box_id: b.box_id() })
+                    .collect(),
+                outputs: outputs.clone(),
+                inclusion_height: output.creation_height,
+            };
+            self.txs.insert(tx.id, tx);
+            for b in inputs.into_iter().chain(outputs) {
+                self.boxes.insert(b.box_id(), b);
+            }
+        }
+    }
+
+    impl ChainIndex for ChainIndexMock {
+        fn get_box(&self, box_id: &BoxId) -> Result<ErgoBox, PoolHistoryError> {
+            self.boxes
+                .get(box_id)
+                .cloned()
+                .ok_or_else(|| PoolHistoryError::Cache(format!("no box {box_id:?}")))
+        }
+
+        fn get_transaction(&self, tx_id: &TxId) -> Result<IndexedTransaction, PoolHistoryError> {
+            self.txs
+                .get(tx_id)
+                .cloned()
+                .ok_or_else(|| PoolHistoryError::Cache(format!("no tx {tx_id:?}")))
+        }
+    }
+
+    #[test]
+    fn walks_back_refresh_txs() {
+        let token_ids = generate_token_ids();
+        let pool_contract_parameters = PoolContractParameters::default();
+        let value = BoxValue::SAFE_USER_MIN;
+        let pool_box = |rate, epoch, height| {
+            make_pool_box(
+                rate,
+                EpochCounter(epoch),
+                value,
+                BlockHeight(height),
+                &pool_contract_parameters,
+                &token_ids,
+            )
+            .get_box()
+            .clone()
+        };
+        let oracle_box_of = |pk: &EcPoint, rate, epoch, reward_tokens| {
+            make_datapoint_box(
+                pk.clone(),
+                rate,
+                EpochCounter(epoch),
+                &token_ids,
+                value,
+                BlockHeight(90),
+                reward_tokens,
+            )
+        };
+        let oracle_box = |rate, epoch| oracle_box_of(&force_any_val::<EcPoint>(), rate, epoch, 1);
+        let p0 = pool_box(100, 1, 100);
+        let p1 = pool_box(200, 2, 130);
+        let p2 = pool_box(300, 3, 160);
+        let mut chain = ChainIndexMock::default();
+        // bootstrap tx, the pool box is not spent
+        chain.add_tx(&p0, vec![oracle_box(1, 0)], vec![]);
+        // the oracle boxes are sorted by rate in the refresh tx, the collector is the second one
+        // and gets 1 + 2 reward tokens
+        let (pk1, pk2) = (force_any_val::<EcPoint>(), force_any_val::<EcPoint>());
+        chain.add_tx(
+            &p1,
+            vec![
+                p0.clone(),
+                oracle_box(0, 0),
+                oracle_box_of(&pk1, 199, 1, 10),
+                oracle_box_of(&pk2, 201, 1, 10),
+            ],
+            vec![
+                oracle_box(0, 0),
+                oracle_box_of(&pk1, 0, 1, 11),
+                oracle_box_of(&pk2, 0, 1, 13),
+            ],
+        );
+        chain.add_tx(
+            &p2,
+            vec![p1.clone(), oracle_box(0, 0), oracle_box(300, 2)],
+            vec![],
+        );
+
+        let mut cache = PoolHistoryCache::new();
+        let history = get_pool_history(
+            &chain,
+            &mut cache,
+            &token_ids,
+            NetworkPrefix::Mainnet,
+            p2.box_id(),
+            10,
+        )
+        .unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].rate, Rate::from(300));
+        assert_eq!(history[1].epoch_counter, EpochCounter(2));
+        assert_eq!(history[1].oracles.len(), 2);
+        assert_eq!(history[1].oracles[0].rate, Rate::from(199));
+        assert_eq!(
+            history[1].collector,
+            Some(history[1].oracles[1].address.clone())
+        );
+        // no output oracle box of the collector
+        assert_eq!(history[0].collector, None);
+        assert_eq!(history[1].previous_pool_box_id, Some(p0.box_id()));
+        assert!(history[2].oracles.is_empty());
+        assert_eq!(history[2].previous_pool_box_id, None);
+
+        // served from the cache
+        let history = get_pool_history(
+            &ChainIndexMock::default(),
+            &mut cache,
+            &token_ids,
+            NetworkPrefix::Mainnet,
+            p2.box_id(),
+            2,
+        )
+        .unwrap();
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn corrupt_cache_is_ignored() {
+        let path = std::env::temp_dir().join(format!(
+            "oracle_core_pool_history_{}.json",
+            std::process::id()
+        ));
+        fs::write(&path, "[{\"pool_box_id\":").unwrap();
+        let cache = PoolHistoryCache::load(&path);
+        assert!(cache.entries.is_empty());
+        cache.save().unwrap();
+        assert!(read_cache_file(&path).unwrap().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
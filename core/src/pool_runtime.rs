@@ -0,0 +1,346 @@
+//! State of one pool run by the oracle: the pool boxes, the datapoint source, the state machine
+//! controls and everything the main loop keeps between iterations. The multi-pool mode (`pools` in
+//! the oracle config) runs one of these per pool, each with its own pool config, data folder
+//! (scan IDs, pool history, posted datapoints), REST routes under `/pools/<name>` and metrics
+//! labelled with `pool="<name>"`.
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::action_report::ActionReportStorage;
+use crate::api::PoolRoutes;
+use crate::context::OracleContext;
+use crate::context::SharedContext;
+use crate::datapoint_source::RuntimeDataPointSource;
+use crate::events::ChainObserver;
+use crate::events::EventBus;
+use crate::metrics::PoolMetrics;
+use crate::node_interface::node_api::NodeApi;
+use crate::notifier::Alert;
+use crate::notifier::Notifier;
+use crate::oracle_config::BoxSource;
+use crate::oracle_config::OracleConfig;
+use crate::oracle_config::OracleSecrets;
+use crate::oracle_state::OraclePool;
+use crate::oracle_state::PoolTokenBoxes;
+use crate::oracle_stats::OracleStatsTracker;
+use crate::oracle_types::EpochCounter;
+use crate::pending_tx::PendingTxTracker;
+use crate::pool_config::PoolConfig;
+use crate::pool_control::PoolControl;
+use crate::pool_history::get_pool_history_file_path;
+use crate::pool_history::PoolHistoryCache;
+use crate::pool_history::SharedPoolHistoryCache;
+use crate::public_boxes::with_explorer_fallback;
+use crate::public_boxes::PublicChainReader;
+use crate::reorg::ReorgDetector;
+use crate::reorg::SeenBoxes;
+use crate::scans::NodeScanRegistry;
+use crate::scans::SharedScanRegistry;
+use crate::snapshot::SnapshotCache;
+
+/// How often the running oracle checks that the node still has the pool's scans
+pub const SCAN_VERIFICATION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Pool of the multi-pool mode
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PoolEntryConfig {
+    /// Used in the REST routes, the metric labels and as the name of the pool's data folder
+    pub name: String,
+    pub pool_config_file: PathBuf,
+    /// Datapoint source of this pool, the pool config `data_point_source` is used if not set
+    pub data_point_source_custom_script: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum PoolEntryConfigError {
+    #[error("pool name {0:?} is used more than once")]
+    DuplicateName(String),
+    #[error("pool name {0:?} must be non-empty and only contain letters, digits, '-' and '_'")]
+    InvalidName(String),
+}
+
+/// Checks the pool names, they end up in URLs, metric labels and folder names
+pub fn check_pool_entries(entries: &[PoolEntryConfig]) -> Result<(), PoolEntryConfigError> {
+    let mut names = HashSet::new();
+    for entry in entries {
+        let is_valid = !entry.name.is_empty()
+            && entry
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_valid {
+            return Err(PoolEntryConfigError::InvalidName(entry.name.clone()));
+        }
+        if !names.insert(entry.name.as_str()) {
+            return Err(PoolEntryConfigError::DuplicateName(entry.name.clone()));
+        }
+    }
+    Ok(())
+}
+
+pub struct PoolRuntime {
+    /// Empty in the single-pool mode
+    pub name: String,
+    /// Folder of the pool's data files
+    pub data_dir: PathBuf,
+    /// Replaced when the oracle config is reloaded
+    pub ctx: Arc<SharedContext>,
+    pub oracle_pool: Arc<OraclePool>,
+    /// Scan IDs read by `oracle_pool`, `None` with the node indexer box source
+    pub scan_registry: Option<Arc<SharedScanRegistry>>,
+    scans_verified_at: Instant,
+    /// Set by [`PoolRuntime::verify_scans`] when scans were registered again, the next iteration
+    /// takes no action (the node wallet rescan has just started)
+    pub rescan_started: bool,
+    pub datapoint_source: RuntimeDataPointSource,
+    /// Pool epoch for which the datapoint source was last asked for a new datapoint
+    pub repost_requested_epoch: Option<EpochCounter>,
+    pub pool_control: Arc<PoolControl>,
+    pub events: Arc<EventBus>,
+    pub snapshot_cache: Arc<SnapshotCache>,
+    pub metrics: PoolMetrics,
+    pub notifier: Notifier,
+    pub pending_txs: PendingTxTracker,
+    pub chain_observer: ChainObserver,
+    pub reorg_detector: ReorgDetector,
+    pub seen_boxes: SeenBoxes,
+    pub oracle_stats_tracker: OracleStatsTracker,
+    /// Shared with the REST API
+    pub history_cache: SharedPoolHistoryCache,
+    pub report_storage: Arc<RwLock<ActionReportStorage>>,
+}
+
+impl PoolRuntime {
+    /// Pool read through the box source of the oracle config: the node scans of
+    /// `<data_dir>/scanIDs.json` (registered if missing) or the node's blockchain indexer. Metrics
+    /// are labelled with the pool name unless it is empty (single-pool mode).
+    pub fn new(
+        name: String,
+        ctx: Arc<OracleContext>,
+        datapoint_source: RuntimeDataPointSource,
+        data_dir: PathBuf,
+    ) -> Result<Self, anyhow::Error> {
+        let (token_boxes, scan_registry) = match ctx.oracle_config.box_source {
+            BoxSource::NodeScans => {
+                let scan_registry = Arc::new(SharedScanRegistry::new(
+                    NodeScanRegistry::ensure_node_registered_scans(
+                        &ctx,
+                        &data_dir.join("scanIDs.json"),
+                    )?,
+                ));
+                (
+                    PoolTokenBoxes::from_shared_node_scans(&scan_registry, &ctx.node_api),
+                    Some(scan_registry),
+                )
+            }
+            BoxSource::NodeIndexer => {
+                log::info!("Reading the pool boxes through the node's blockchain indexer");
+                let reader = Arc::new(PublicChainReader::oracle_node_indexer(&ctx)?);
+                (reader.pool_token_boxes(&ctx.pool_config), None)
+            }
+        };
+        let oracle_pool = Arc::new(OraclePool::with_token_boxes(
+            ctx.pool_config.clone(),
+            with_explorer_fallback(&ctx, token_boxes),
+            Some(ctx.oracle_pk()),
+        ));
+        let history_cache = Arc::new(Mutex::new(PoolHistoryCache::load(
+            &get_pool_history_file_path(&data_dir),
+        )));
+        let oracle_stats_tracker = OracleStatsTracker::load(
+            &data_dir,
+            ctx.pool_config.token_ids.clone(),
+            history_cache.clone(),
+        );
+        let oracle_address = ctx.oracle_config.oracle_address.to_base58();
+        let (metrics, notifier_source) = if name.is_empty() {
+            (PoolMetrics::new(None), oracle_address)
+        } else {
+            (
+                PoolMetrics::new(Some(&name)),
+                format!("{oracle_address} [{name}]"),
+            )
+        };
+        let notifier = Notifier::new(ctx.oracle_config.notifications.clone(), notifier_source);
+        Ok(Self {
+            name,
+            data_dir,
+            ctx: Arc::new(SharedContext::new(ctx)),
+            oracle_pool,
+            scan_registry,
+            scans_verified_at: Instant::now(),
+            rescan_started: false,
+            repost_requested_epoch: None,
+            datapoint_source,
+            pool_control: Arc::new(PoolControl::new()),
+            events: Arc::new(EventBus::new()),
+            snapshot_cache: Arc::new(SnapshotCache::new()),
+            metrics,
+            notifier,
+            pending_txs: PendingTxTracker::new(),
+            chain_observer: ChainObserver::new(),
+            reorg_detector: ReorgDetector::new(),
+            seen_boxes: SeenBoxes::new(),
+            oracle_stats_tracker,
+            history_cache,
+            report_storage: Arc::new(RwLock::new(ActionReportStorage::new())),
+        })
+    }
+
+    /// Loads the pool config of `entry` and registers its scans (scan IDs are kept in
+    /// `<data_dir>/<name>/scanIDs.json`)
+    pub fn load(
+        entry: &PoolEntryConfig,
+        oracle_config: &Arc<OracleConfig>,
+        secrets: &Arc<OracleSecrets>,
+        node_api: &Arc<NodeApi>,
+        data_dir: &Path,
+    ) -> Result<Self, anyhow::Error> {
+        log::info!("Loading pool {}", entry.name);
+        let pool_config = PoolConfig::load_from_file(&entry.pool_config_file)?;
+        let pool_ctx = Arc::new(OracleContext::new(
+            oracle_config.clone(),
+            Arc::new(pool_config),
+            secrets.clone(),
+            node_api.clone(),
+        )?);
+        let pool_data_dir = data_dir.join(&entry.name);
+        std::fs::create_dir_all(&pool_data_dir)?;
+        let datapoint_source = RuntimeDataPointSource::new(
+            pool_ctx.pool_config.data_point_source,
+            entry.data_point_source_custom_script.clone(),
+            None,
+        )?;
+        Self::new(
+            entry.name.clone(),
+            pool_ctx,
+            datapoint_source,
+            pool_data_dir,
+        )
+    }
+
+    /// Registers again the scans lost by the node (see [`NodeScanRegistry::heal`]), at most once
+    /// every [`SCAN_VERIFICATION_INTERVAL`]. The rescan is not waited for here: the pool actions
+    /// are skipped while the node wallet is rescanning (see [`crate::wallet_monitor::WalletState::is_ready`]).
+    pub fn verify_scans(&mut self) -> Result<(), anyhow::Error> {
+        let Some(scan_registry) = &self.scan_registry else {
+            return Ok(());
+        };
+        if self.scans_verified_at.elapsed() < SCAN_VERIFICATION_INTERVAL {
+            return Ok(());
+        }
+        self.scans_verified_at = Instant::now();
+        let ctx = self.ctx.load();
+        let registry = scan_registry.load();
+        if let Some(healed) = registry.heal(&ctx, &self.data_dir.join("scanIDs.json"))? {
+            scan_registry.store(healed);
+            self.rescan_started = true;
+        }
+        Ok(())
+    }
+
+    /// Compares the node's last blocks with the previous call. If a chain reorganization rolled
+    /// back the pool boxes or our txs, alerts and drops the state read from the old chain (the
+    /// snapshot, the pending txs and the event baseline) so that the next iteration starts over.
+    pub fn check_reorg(&mut self) -> Result<(), anyhow::Error> {
+        let ctx = self.ctx.load();
+        let Some(reorg) = self.reorg_detector.observe(ctx.node_api.as_ref())? else {
+            return Ok(());
+        };
+        self.metrics.reorg(reorg.depth);
+        let affected = reorg.affects_pool(&self.seen_boxes, self.pending_txs.recently_confirmed());
+        if !affected {
+            log::info!(
+                "Chain reorganization of {} blocks after height {}, the pool boxes are not affected",
+                reorg.depth,
+                reorg.fork_height
+            );
+            return Ok(());
+        }
+        log::warn!(
+            "Chain reorganization of {} blocks after height {} rolled back the pool boxes or our txs, dropping the pool state",
+            reorg.depth,
+            reorg.fork_height
+        );
+        self.notifier.alert(Alert::Reorg {
+            depth: reorg.depth,
+            fork_height: reorg.fork_height,
+        });
+        self.snapshot_cache.clear();
+        self.pending_txs.clear();
+        self.seen_boxes = SeenBoxes::new();
+        self.chain_observer = ChainObserver::new();
+        Ok(())
+    }
+
+    pub fn routes(&self) -> PoolRoutes {
+        PoolRoutes {
+            name: self.name.clone(),
+            ctx: self.ctx.clone(),
+            oracle_pool: self.oracle_pool.clone(),
+            events: self.events.clone(),
+            snapshot_cache: self.snapshot_cache.clone(),
+            history_cache: self.history_cache.clone(),
+            data_dir: self.data_dir.clone(),
+        }
+    }
+}
+
+/// Loads all pools of the multi-pool mode
+pub fn load_pools(
+    entries: &[PoolEntryConfig],
+    oracle_config: &Arc<OracleConfig>,
+    secrets: &Arc<OracleSecrets>,
+    node_api: &Arc<NodeApi>,
+    data_dir: &Path,
+) -> Result<Vec<PoolRuntime>, anyhow::Error> {
+    check_pool_entries(entries)?;
+    entries
+        .iter()
+        .map(|entry| {
+            PoolRuntime::load(entry, oracle_config, secrets, node_api, data_dir)
+                .context(format!("failed to load pool {}", entry.name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> PoolEntryConfig {
+        PoolEntryConfig {
+            name: name.to_string(),
+            pool_config_file: PathBuf::from(format!("{name}.yaml")),
+            data_point_source_custom_script: None,
+        }
+    }
+
+    #[test]
+    fn pool_names_are_checked() {
+        assert!(check_pool_entries(&[entry("erg-usd"), entry("erg_xau")]).is_ok());
+        assert!(matches!(
+            check_pool_entries(&[entry("erg-usd"), entry("erg-usd")]),
+            Err(PoolEntryConfigError::DuplicateName(_))
+        ));
+        assert!(matches!(
+            check_pool_entries(&[entry("erg/usd")]),
+            Err(PoolEntryConfigError::InvalidName(_))
+        ));
+        assert!(matches!(
+            check_pool_entries(&[entry("")]),
+            Err(PoolEntryConfigError::InvalidName(_))
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+//! Pool boxes read through public endpoints (no node API key, wallet or scans): the node's
+//! blockchain indexer or the explorer API
+use std::sync::Arc;
+
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use reqwest::Url;
+
+use crate::context::OracleContext;
+use crate::explorer_api::ExplorerApi;
+use crate::metrics::inc_explorer_fallback_reads;
+use crate::node_interface::node_api::NodeApi;
+use crate::oracle_state::PoolTokenBoxes;
+use crate::oracle_types::BlockHeight;
+use crate::pool_config::PoolConfig;
+use crate::scans::ScanError;
+use crate::scans::ScanGetBoxes;
+use crate::spec_token::TokenIdKind;
+
+/// Boxes requested per page
+const PAGE_SIZE: usize = 100;
+
+pub enum PublicChainReader {
+    /// Node with the blockchain indexer enabled (`extraIndex = true`)
+    NodeIndexer(NodeApi),
+    Explorer(ExplorerApi),
+}
+
+impl PublicChainReader {
+    /// Node's public endpoints only, the API key is left empty
+    pub fn node_indexer(node_url: &Url) -> Self {
+        PublicChainReader::NodeIndexer(NodeApi::new(String::new(), None, node_url))
+    }
+
+    /// Blockchain indexer of the oracle's node (`box_source: node_indexer`), fails if the indexer
+    /// is disabled
+    #[doc(hidden)]
+    pub fn oracle_node_indexer(ctx: &OracleContext) -> Result<Self, ScanError> {
+        let node_api = NodeApi::new(
+            ctx.secrets.node_api_key.clone(),
+            None,
+            &ctx.oracle_config.node_url,
+        );
+        if node_api.get_indexed_height()?.is_none() {
+            return Err(ScanError::IndexerDisabled);
+        }
+        Ok(PublicChainReader::NodeIndexer(node_api))
+    }
+
+    pub fn explorer(explorer_url: Url) -> Self {
+        PublicChainReader::Explorer(ExplorerApi::new(explorer_url))
+    }
+
+    pub fn current_height(&self) -> Result<BlockHeight, ScanError> {
+        let height = match self {
+            PublicChainReader::NodeIndexer(node_api) => {
+                node_api.node.current_block_height()? as u32
+            }
+            PublicChainReader::Explorer(explorer_api) => explorer_api.get_height_v1()?,
+        };
+        Ok(BlockHeight(height))
+    }
+
+    /// All unspent boxes holding the token (all pages)
+    pub fn get_unspent_boxes_by_token_id(
+        &self,
+        token_id: &TokenId,
+    ) -> Result<Vec<ErgoBox>, ScanError> {
+        let mut boxes = vec![];
+        loop {
+            let page = match self {
+                PublicChainReader::NodeIndexer(node_api) => {
+                    node_api.get_unspent_boxes_by_token_id(token_id, boxes.len(), PAGE_SIZE)?
+                }
+                PublicChainReader::Explorer(explorer_api) => explorer_api
+                    .get_unspent_boxes_by_token_id_v1(token_id, boxes.len(), PAGE_SIZE)?,
+            };
+            let last_page = page.len() < PAGE_SIZE;
+            boxes.extend(page);
+            if last_page {
+                return Ok(boxes);
+            }
+        }
+    }
+
+    /// Box sources of each pool token
+    pub fn pool_token_boxes(self: &Arc<Self>, pool_config: &PoolConfig) -> PoolTokenBoxes {
+        let token_ids = &pool_config.token_ids;
+        PoolTokenBoxes {
+            oracle: self.token_boxes(&token_ids.oracle_token_id),
+            pool: self.token_boxes(&token_ids.pool_nft_token_id),
+            ballot: self.token_boxes(&token_ids.ballot_token_id),
+            refresh: self.token_boxes(&token_ids.refresh_nft_token_id),
+            update: self.token_boxes(&token_ids.update_nft_token_id),
+            buyback: pool_config
+                .buyback_token_id
+                .as_ref()
+                .map(|token_id| self.token_boxes(token_id)),
+        }
+    }
+
+    fn token_boxes<T: TokenIdKind>(self: &Arc<Self>, token_id: &T) -> Arc<dyn ScanGetBoxes> {
+        Arc::new(PublicTokenBoxes {
+            reader: self.clone(),
+            token_id: token_id.token_id(),
+        })
+    }
+}
+
+impl std::fmt::Debug for PublicChainReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PublicChainReader::NodeIndexer(_) => write!(f, "PublicChainReader::NodeIndexer"),
+            PublicChainReader::Explorer(explorer_api) => {
+                write!(f, "PublicChainReader::Explorer({})", explorer_api.url)
+            }
+        }
+    }
+}
+
+/// Pool token boxes of the oracle's box source, with the explorer API as a fallback if
+/// `explorer_fallback` is set in the oracle config
+#[doc(hidden)]
+pub fn with_explorer_fallback(ctx: &OracleContext, token_boxes: PoolTokenBoxes) -> PoolTokenBoxes {
+    if ctx.oracle_config.explorer_fallback {
+        let explorer = Arc::new(PublicChainReader::Explorer(ctx.explorer_api()));
+        token_boxes.with_fallback(explorer.pool_token_boxes(&ctx.pool_config))
+    } else {
+        token_boxes
+    }
+}
+
+/// Boxes of `primary` (node scans or indexer), read from `fallback` (explorer API) when they look
+/// inconsistent: `primary` fails, or does not return exactly one box of a singleton token (pool,
+/// refresh, update NFT, buyback token)
+#[derive(Debug)]
+pub struct FallbackBoxes {
+    primary: Arc<dyn ScanGetBoxes>,
+    fallback: Arc<dyn ScanGetBoxes>,
+    singleton: bool,
+}
+
+impl FallbackBoxes {
+    pub fn new(
+        primary: Arc<dyn ScanGetBoxes>,
+        fallback: Arc<dyn ScanGetBoxes>,
+        singleton: bool,
+    ) -> Arc<dyn ScanGetBoxes> {
+        Arc::new(Self {
+            primary,
+            fallback,
+            singleton,
+        })
+    }
+}
+
+impl ScanGetBoxes for FallbackBoxes {
+    fn get_boxes(&self) -> Result<Vec<ErgoBox>, ScanError> {
+        match self.primary.get_boxes() {
+            Ok(boxes) if !self.singleton || boxes.len() == 1 => return Ok(boxes),
+            Ok(boxes) => log::warn!(
+                "{:?} returned {} boxes of a singleton token, reading them from {:?}",
+                self.primary,
+                boxes.len(),
+                self.fallback
+            ),
+            Err(e) => log::warn!(
+                "{:?} failed ({e}), reading the boxes from {:?}",
+                self.primary,
+                self.fallback
+            ),
+        }
+        inc_explorer_fallback_reads();
+        self.fallback.get_boxes()
+    }
+}
+
+#[derive(Debug)]
+struct PublicTokenBoxes {
+    reader: Arc<PublicChainReader>,
+    token_id: TokenId,
+}
+
+impl ScanGetBoxes for PublicTokenBoxes {
+    fn get_boxes(&self) -> Result<Vec<ErgoBox>, ScanError> {
+        self.reader.get_unspent_boxes_by_token_id(&self.token_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::tests::mock_explorer::MockExplorer;
+
+    /// Node scan returning `boxes`, or failing if `None`
+    #[derive(Debug)]
+    struct NodeBoxes(Option<Vec<ErgoBox>>);
+
+    impl ScanGetBoxes for NodeBoxes {
+        fn get_boxes(&self) -> Result<Vec<ErgoBox>, ScanError> {
+            self.0.clone().ok_or(ScanError::NoBoxesFound)
+        }
+    }
+
+    fn explorer_boxes(explorer: &MockExplorer, token_id: TokenId) -> Arc<dyn ScanGetBoxes> {
+        Arc::new(PublicTokenBoxes {
+            reader: Arc::new(PublicChainReader::explorer(explorer.url.clone())),
+            token_id,
+        })
+    }
+
+    #[test]
+    fn explorer_fallback() {
+        let token_id = force_any_val::<TokenId>();
+        let node_box = force_any_val::<ErgoBox>();
+        let explorer_box = force_any_val::<ErgoBox>();
+        let explorer = MockExplorer::start(vec![(
+            format!("/api/v1/boxes/unspent/byTokenId/{}", String::from(token_id)),
+            serde_json::json!({ "items": [explorer_box], "total": 1 }).to_string(),
+        )]);
+        let read_box_ids = |node_boxes: Option<Vec<ErgoBox>>, singleton: bool| {
+            FallbackBoxes::new(
+                Arc::new(NodeBoxes(node_boxes)),
+                explorer_boxes(&explorer, token_id),
+                singleton,
+            )
+            .get_boxes()
+            .unwrap()
+            .iter()
+            .map(|b| b.box_id())
+            .collect::<Vec<_>>()
+        };
+        // consistent node boxes
+        assert_eq!(
+            read_box_ids(Some(vec![node_box.clone()]), true),
+            vec![node_box.box_id()]
+        );
+        assert_eq!(read_box_ids(Some(vec![]), false), vec![]);
+        // node scan lost the pool box, or failed
+        assert_eq!(
+            read_box_ids(Some(vec![]), true),
+            vec![explorer_box.box_id()]
+        );
+        assert_eq!(read_box_ids(None, false), vec![explorer_box.box_id()]);
+    }
+}
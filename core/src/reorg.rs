@@ -0,0 +1,257 @@
+//! Detection of chain reorganizations by comparing the node's last blocks between main loop
+//! iterations
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+
+use crate::node_interface::node_api::BlockRef;
+use crate::node_interface::node_api::NodeApiError;
+use crate::node_interface::node_api::RecentBlocksSource;
+use crate::oracle_types::BlockHeight;
+use crate::pending_tx::PendingTx;
+
+/// Number of last blocks compared, deeper reorgs are reported with this depth
+pub const REORG_WINDOW: u32 = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reorg {
+    /// Number of blocks rolled back
+    pub depth: u32,
+    /// Height of the last block kept (common to the old and the new chain)
+    pub fork_height: BlockHeight,
+}
+
+impl Reorg {
+    /// Whether a box or tx at `height` was in a rolled back block
+    pub fn rolled_back(&self, height: BlockHeight) -> bool {
+        height > self.fork_height
+    }
+
+    /// Whether the pool box or our oracle box, or one of our confirmed txs, may have been in a
+    /// rolled back block. Only the heights at which they were first seen are known (a box is
+    /// included at or below it, and the creation height may be lower than the inclusion height),
+    /// so a box seen after the fork height is considered rolled back.
+    pub fn affects_pool(&self, seen_boxes: &SeenBoxes, confirmed_txs: &[PendingTx]) -> bool {
+        seen_boxes.heights().any(|height| self.rolled_back(height))
+            || confirmed_txs
+                .iter()
+                .filter_map(|tx| tx.confirmed_height)
+                .any(|height| self.rolled_back(height))
+    }
+}
+
+/// Heights at which the main loop first saw the current pool box and our oracle box
+#[derive(Debug, Default)]
+pub struct SeenBoxes {
+    pool_box: Option<(BoxId, BlockHeight)>,
+    local_box: Option<(BoxId, BlockHeight)>,
+}
+
+impl SeenBoxes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the boxes read at `height`, keeping the height of the boxes already seen
+    pub fn record(&mut self, pool_box_id: BoxId, local_box_id: Option<BoxId>, height: BlockHeight) {
+        record_box(&mut self.pool_box, Some(pool_box_id), height);
+        record_box(&mut self.local_box, local_box_id, height);
+    }
+
+    fn heights(&self) -> impl Iterator<Item = BlockHeight> + '_ {
+        self.pool_box
+            .iter()
+            .chain(self.local_box.iter())
+            .map(|(_, height)| *height)
+    }
+}
+
+fn record_box(seen: &mut Option<(BoxId, BlockHeight)>, box_id: Option<BoxId>, height: BlockHeight) {
+    *seen = match (seen.take(), box_id) {
+        (Some((seen_id, seen_height)), Some(box_id)) if seen_id == box_id => {
+            Some((seen_id, seen_height))
+        }
+        (_, box_id) => box_id.map(|box_id| (box_id, height)),
+    };
+}
+
+/// Last blocks seen by the main loop
+#[derive(Debug, Default)]
+pub struct ReorgDetector {
+    /// Oldest first
+    blocks: Vec<BlockRef>,
+}
+
+impl ReorgDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the node's last blocks and compares them with the ones of the previous call
+    pub fn observe(
+        &mut self,
+        node: &dyn RecentBlocksSource,
+    ) -> Result<Option<Reorg>, NodeApiError> {
+        Ok(self.update(node.recent_blocks(REORG_WINDOW)?))
+    }
+
+    fn update(&mut self, blocks: Vec<BlockRef>) -> Option<Reorg> {
+        let first_height = blocks.first()?.height;
+        let old_blocks = std::mem::replace(&mut self.blocks, blocks);
+        // the old blocks below the new window cannot be compared
+        let overlap: Vec<&BlockRef> = old_blocks
+            .iter()
+            .filter(|b| b.height >= first_height)
+            .collect();
+        let old_tip = overlap.last()?;
+        let fork_height = match overlap.iter().rev().find(|b| self.blocks.contains(**b)) {
+            Some(common) if common == old_tip => return None,
+            Some(common) => common.height,
+            None => BlockHeight(first_height.0.saturating_sub(1)),
+        };
+        Some(Reorg {
+            depth: old_tip.height.0 - fork_height.0,
+            fork_height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_chain_sim::Block;
+    use ergo_chain_sim::ChainSim;
+    use ergo_lib::chain::ergo_box::box_builder::ErgoBoxCandidateBuilder;
+    use ergo_lib::chain::transaction::input::prover_result::ProverResult;
+    use ergo_lib::chain::transaction::Input;
+    use ergo_lib::chain::transaction::Transaction;
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ProofBytes;
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    impl RecentBlocksSource for ChainSim {
+        fn recent_blocks(&self, count: u32) -> Result<Vec<BlockRef>, NodeApiError> {
+            Ok(self
+                .last_blocks(count as usize)
+                .into_iter()
+                .map(|(height, id)| BlockRef {
+                    id,
+                    height: BlockHeight(height),
+                })
+                .collect())
+        }
+    }
+
+    fn add_blocks(chain: &mut ChainSim, count: u32) {
+        (0..count).for_each(|_| chain.add_block(Block::new(vec![])));
+    }
+
+    #[test]
+    fn new_blocks_are_not_a_reorg() {
+        let mut chain = ChainSim::new();
+        let mut detector = ReorgDetector::new();
+        assert_eq!(detector.observe(&chain).unwrap(), None);
+        add_blocks(&mut chain, 10);
+        assert_eq!(detector.observe(&chain).unwrap(), None);
+        add_blocks(&mut chain, 2);
+        assert_eq!(detector.observe(&chain).unwrap(), None);
+        // more new blocks than the window
+        add_blocks(&mut chain, REORG_WINDOW + 5);
+        assert_eq!(detector.observe(&chain).unwrap(), None);
+    }
+
+    #[test]
+    fn reorg_is_detected() {
+        let mut chain = ChainSim::new();
+        let mut detector = ReorgDetector::new();
+        add_blocks(&mut chain, 10);
+        detector.observe(&chain).unwrap();
+        chain.rollback(3);
+        add_blocks(&mut chain, 4);
+        let reorg = detector.observe(&chain).unwrap().unwrap();
+        assert_eq!(
+            reorg,
+            Reorg {
+                depth: 3,
+                fork_height: BlockHeight(7),
+            }
+        );
+        assert!(reorg.rolled_back(BlockHeight(8)));
+        assert!(!reorg.rolled_back(BlockHeight(7)));
+        // reported once
+        assert_eq!(detector.observe(&chain).unwrap(), None);
+    }
+
+    #[test]
+    fn rollback_without_new_blocks_is_a_reorg() {
+        let mut chain = ChainSim::new();
+        let mut detector = ReorgDetector::new();
+        add_blocks(&mut chain, 10);
+        detector.observe(&chain).unwrap();
+        chain.rollback(1);
+        assert_eq!(detector.observe(&chain).unwrap().map(|r| r.depth), Some(1));
+    }
+
+    #[test]
+    fn reorg_deeper_than_window() {
+        let mut chain = ChainSim::new();
+        let mut detector = ReorgDetector::new();
+        add_blocks(&mut chain, REORG_WINDOW + 10);
+        detector.observe(&chain).unwrap();
+        chain.rollback(REORG_WINDOW + 5);
+        add_blocks(&mut chain, REORG_WINDOW + 5);
+        assert_eq!(
+            detector.observe(&chain).unwrap(),
+            Some(Reorg {
+                depth: REORG_WINDOW,
+                fork_height: BlockHeight(10),
+            })
+        );
+    }
+
+    #[test]
+    fn box_included_above_its_creation_height_is_rolled_back() {
+        let mut chain = ChainSim::new();
+        let mut detector = ReorgDetector::new();
+        let mut seen_boxes = SeenBoxes::new();
+        let tree = force_any_val::<ErgoTree>();
+        chain.generate_unspent_box(tree.clone(), BoxValue::SAFE_USER_MIN, None);
+        add_blocks(&mut chain, 10);
+        detector.observe(&chain).unwrap();
+        // the pool box is created at height 10 and included in block 11
+        let input = chain.get_unspent_boxes(&tree)[0].box_id();
+        let output = ErgoBoxCandidateBuilder::new(BoxValue::SAFE_USER_MIN, tree, 10)
+            .build()
+            .unwrap();
+        let tx = Transaction::new_from_vec(
+            vec![Input::new(
+                input,
+                ProverResult {
+                    proof: ProofBytes::Empty,
+                    extension: ContextExtension::empty(),
+                },
+            )],
+            vec![],
+            vec![output],
+        )
+        .unwrap();
+        chain.add_block(Block::new(vec![tx.clone()]));
+        let pool_box = tx.outputs.first().clone();
+        assert_eq!(pool_box.creation_height, 10);
+        assert_eq!(detector.observe(&chain).unwrap(), None);
+        seen_boxes.record(pool_box.box_id(), None, BlockHeight(chain.height));
+
+        // one block reorg forking at the creation height
+        chain.rollback(1);
+        add_blocks(&mut chain, 2);
+        let reorg = detector.observe(&chain).unwrap().unwrap();
+        assert_eq!(reorg.fork_height, BlockHeight(10));
+        assert!(!reorg.rolled_back(BlockHeight(pool_box.creation_height)));
+        assert!(reorg.affects_pool(&seen_boxes, &[]));
+        // a box seen at the fork height was not rolled back
+        let mut old_boxes = SeenBoxes::new();
+        old_boxes.record(force_any_val::<BoxId>(), None, BlockHeight(10));
+        assert!(!reorg.affects_pool(&old_boxes, &[]));
+    }
+}
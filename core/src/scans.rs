@@ -1,7 +1,9 @@
 use crate::contracts::pool::PoolContractError;
 use crate::contracts::refresh::RefreshContractError;
+use crate::explorer_api::ExplorerApiError;
 use crate::node_interface::node_api::{NodeApi, NodeApiError};
-use crate::oracle_config::{ORACLE_CONFIG, ORACLE_SECRETS};
+
+use std::sync::Arc;
 
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
 use ergo_node_interface::node_interface::NodeError;
@@ -33,24 +35,84 @@ pub enum ScanError {
     RefreshContract(#[from] RefreshContractError),
     #[error("pool contract error: {0}")]
     PoolContract(#[from] PoolContractError),
+    #[error("explorer api error: {0}")]
+    ExplorerApiError(#[from] ExplorerApiError),
+    #[error(
+        "the node's blockchain indexer is disabled (set extraIndex = true in the node config)"
+    )]
+    IndexerDisabled,
 }
 
 pub trait NodeScanId {
     fn scan_id(&self) -> ScanId;
 }
 
-pub trait ScanGetBoxes: NodeScanId {
-    fn get_boxes(&self) -> Result<Vec<ErgoBox>, ScanError> {
-        let node_api = NodeApi::new(
-            ORACLE_SECRETS.node_api_key.clone(),
-            ORACLE_SECRETS.wallet_password.clone(),
-            &ORACLE_CONFIG.node_url,
-        );
-        let boxes = node_api.node.scan_boxes(self.scan_id())?;
-        Ok(boxes)
-    }
+/// Unspent boxes holding one of the pool tokens, read through a node scan or another backend
+pub trait ScanGetBoxes: std::fmt::Debug + Send + Sync {
+    fn get_boxes(&self) -> Result<Vec<ErgoBox>, ScanError>;
 
     fn get_box(&self) -> Result<Option<ErgoBox>, ScanError> {
         Ok(self.get_boxes()?.first().cloned())
     }
 }
+
+/// Boxes of a node scan. The scan ID is read from the registry on every call, so the boxes follow
+/// the scans registered again by [`NodeScanRegistry::heal`].
+pub struct NodeScanBoxes {
+    registry: Arc<SharedScanRegistry>,
+    /// Scan of the registry, `None` if the registry has no such scan (buyback token)
+    scan_id: fn(&NodeScanRegistry) -> Option<ScanId>,
+    node_api: Arc<NodeApi>,
+}
+
+impl NodeScanBoxes {
+    pub fn new(
+        registry: &Arc<SharedScanRegistry>,
+        scan_id: fn(&NodeScanRegistry) -> Option<ScanId>,
+        node_api: &Arc<NodeApi>,
+    ) -> Arc<dyn ScanGetBoxes> {
+        Arc::new(Self {
+            registry: registry.clone(),
+            scan_id,
+            node_api: node_api.clone(),
+        })
+    }
+}
+
+impl std::fmt::Debug for NodeScanBoxes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.scan_id)(&self.registry.load()) {
+            Some(scan_id) => write!(f, "NodeScanBoxes({})", scan_id),
+            None => write!(f, "NodeScanBoxes(none)"),
+        }
+    }
+}
+
+impl ScanGetBoxes for NodeScanBoxes {
+    fn get_boxes(&self) -> Result<Vec<ErgoBox>, ScanError> {
+        match (self.scan_id)(&self.registry.load()) {
+            Some(scan_id) => Ok(self.node_api.node.scan_boxes(scan_id)?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Boxes read once from another source and returned as is on every call
+#[derive(Debug)]
+pub struct SnapshotBoxes {
+    boxes: Vec<ErgoBox>,
+}
+
+impl SnapshotBoxes {
+    pub fn read(source: &Arc<dyn ScanGetBoxes>) -> Result<Arc<dyn ScanGetBoxes>, ScanError> {
+        Ok(Arc::new(Self {
+            boxes: source.get_boxes()?,
+        }))
+    }
+}
+
+impl ScanGetBoxes for SnapshotBoxes {
+    fn get_boxes(&self) -> Result<Vec<ErgoBox>, ScanError> {
+        Ok(self.boxes.clone())
+    }
+}
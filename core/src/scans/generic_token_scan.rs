@@ -1,5 +1,5 @@
+use crate::node_interface::node_api::NodeApi;
 use crate::spec_token::TokenIdKind;
-use crate::NodeApi;
 use derive_more::From;
 use derive_more::Into;
 use ergo_node_interface::ScanId;
@@ -9,7 +9,6 @@ use serde_json::json;
 
 use super::NodeScanId;
 use super::ScanError;
-use super::ScanGetBoxes;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, From, Into)]
 #[serde(try_from = "String", into = "String")]
@@ -72,5 +71,3 @@ impl<T: TokenIdKind + Clone> NodeScanId for GenericTokenScan<T> {
         self.id
     }
 }
-
-impl<T: TokenIdKind + Clone> ScanGetBoxes for GenericTokenScan<T> {}
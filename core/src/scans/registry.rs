@@ -1,18 +1,24 @@
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::RwLock;
 
+use crate::context::OracleContext;
 use crate::node_interface::node_api::NodeApi;
 use crate::node_interface::node_api::NodeApiError;
+use crate::node_interface::node_api::NodeScan;
 use crate::pool_config::PoolConfig;
 use crate::spec_token::BallotTokenId;
 use crate::spec_token::BuybackTokenId;
 use crate::spec_token::OracleTokenId;
 use crate::spec_token::PoolTokenId;
 use crate::spec_token::RefreshTokenId;
+use crate::spec_token::TokenIdKind;
 use crate::spec_token::UpdateTokenId;
 
-use crate::oracle_config::ORACLE_CONFIG;
 use ::serde::Deserialize;
 use ::serde::Serialize;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use ergo_node_interface::ScanId;
 use once_cell::sync;
 use thiserror::Error;
 
@@ -20,12 +26,21 @@ use super::generic_token_scan::GenericTokenScan;
 use super::NodeScanId;
 use super::ScanError;
 
+#[doc(hidden)]
 pub static SCANS_DIR_PATH: sync::OnceCell<PathBuf> = sync::OnceCell::new();
 
+#[doc(hidden)]
 pub fn get_scans_file_path() -> PathBuf {
     SCANS_DIR_PATH.get().unwrap().join("scanIDs.json")
 }
 
+const ORACLE_TOKEN_SCAN: &str = "oracle token";
+const POOL_TOKEN_SCAN: &str = "pool NFT";
+const BALLOT_TOKEN_SCAN: &str = "ballot token";
+const REFRESH_TOKEN_SCAN: &str = "refresh NFT";
+const UPDATE_TOKEN_SCAN: &str = "update NFT";
+const BUYBACK_TOKEN_SCAN: &str = "buyback token";
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct NodeScanRegistry {
     #[serde(rename = "All Datapoints Scan")]
@@ -51,7 +66,7 @@ impl NodeScanRegistry {
         serde_json::to_string_pretty(&self).unwrap()
     }
 
-    fn save_to_json_file(&self, file_path: &PathBuf) -> Result<(), anyhow::Error> {
+    fn save_to_json_file(&self, file_path: &Path) -> Result<(), anyhow::Error> {
         let json_str = self.save_to_json_str();
         log::debug!("Saving scan IDs to {}", file_path.display());
         Ok(std::fs::write(file_path, json_str)
@@ -59,9 +74,11 @@ impl NodeScanRegistry {
     }
 
     fn register_and_save_scans_inner(
-        node_api: &NodeApi,
-        pool_config: &PoolConfig,
+        ctx: &OracleContext,
+        scans_file_path: &Path,
     ) -> std::result::Result<Self, anyhow::Error> {
+        let node_api = ctx.node_api.as_ref();
+        let pool_config = ctx.pool_config.as_ref();
         log::info!("Registering UTXO-Set Scans");
         let oracle_token_scan =
             GenericTokenScan::register(node_api, &pool_config.token_ids.oracle_token_id)?;
@@ -87,13 +104,17 @@ impl NodeScanRegistry {
             update_token_scan,
             buyback_token_scan,
         };
-        registry.save_to_json_file(&get_scans_file_path())?;
-        node_api.rescan_from_height(ORACLE_CONFIG.scan_start_height)?;
+        registry.save_to_json_file(scans_file_path)?;
+        node_api.rescan_from_height(ctx.oracle_config.scan_start_height)?;
         Ok(registry)
     }
 
+    #[doc(hidden)]
     pub fn load() -> Result<Self, anyhow::Error> {
-        let path = get_scans_file_path();
+        Self::load_from_file(&get_scans_file_path())
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Self, anyhow::Error> {
         log::info!("Loading scan IDs from {}", path.display());
         let json_str =
             std::fs::read_to_string(path).map_err(|e| NodeScanRegistryError::Io(e.to_string()))?;
@@ -101,15 +122,117 @@ impl NodeScanRegistry {
         Ok(registry)
     }
 
+    /// Scans of the registry with the token each one must track according to `pool_config`
+    pub fn token_scans(&self, pool_config: &PoolConfig) -> Vec<(&'static str, ScanId, TokenId)> {
+        let token_ids = &pool_config.token_ids;
+        let mut scans = vec![
+            (
+                ORACLE_TOKEN_SCAN,
+                self.oracle_token_scan.scan_id(),
+                token_ids.oracle_token_id.token_id(),
+            ),
+            (
+                POOL_TOKEN_SCAN,
+                self.pool_token_scan.scan_id(),
+                token_ids.pool_nft_token_id.token_id(),
+            ),
+            (
+                BALLOT_TOKEN_SCAN,
+                self.ballot_token_scan.scan_id(),
+                token_ids.ballot_token_id.token_id(),
+            ),
+            (
+                REFRESH_TOKEN_SCAN,
+                self.refresh_token_scan.scan_id(),
+                token_ids.refresh_nft_token_id.token_id(),
+            ),
+            (
+                UPDATE_TOKEN_SCAN,
+                self.update_token_scan.scan_id(),
+                token_ids.update_nft_token_id.token_id(),
+            ),
+        ];
+        if let (Some(scan), Some(buyback_token_id)) =
+            (&self.buyback_token_scan, &pool_config.buyback_token_id)
+        {
+            scans.push((
+                BUYBACK_TOKEN_SCAN,
+                scan.scan_id(),
+                buyback_token_id.token_id(),
+            ));
+        }
+        scans
+    }
+
+    /// Registers again the scans of the registry that the node lost (database wiped, node
+    /// swapped) or that track other boxes than the pool tokens, then rescans from
+    /// `scan_start_height`. Returns the new registry (saved to `scans_file_path`) if any scan was
+    /// registered again.
+    #[doc(hidden)]
+    pub fn heal(
+        &self,
+        ctx: &OracleContext,
+        scans_file_path: &Path,
+    ) -> std::result::Result<Option<Self>, anyhow::Error> {
+        let node_api = ctx.node_api.as_ref();
+        let pool_config = ctx.pool_config.as_ref();
+        let problems = scan_problems(&self.token_scans(pool_config), &node_api.list_scans()?);
+        if problems.is_empty() {
+            log::debug!("All scans are registered on the node");
+            return Ok(None);
+        }
+        for problem in &problems {
+            log::warn!("{problem}, registering it again");
+        }
+        let is_lost = |scan: &str| problems.iter().any(|problem| problem.scan() == scan);
+        let token_ids = &pool_config.token_ids;
+        let mut healed = self.clone();
+        if is_lost(ORACLE_TOKEN_SCAN) {
+            healed.oracle_token_scan =
+                GenericTokenScan::register(node_api, &token_ids.oracle_token_id)?;
+        }
+        if is_lost(POOL_TOKEN_SCAN) {
+            healed.pool_token_scan =
+                GenericTokenScan::register(node_api, &token_ids.pool_nft_token_id)?;
+        }
+        if is_lost(BALLOT_TOKEN_SCAN) {
+            healed.ballot_token_scan =
+                GenericTokenScan::register(node_api, &token_ids.ballot_token_id)?;
+        }
+        if is_lost(REFRESH_TOKEN_SCAN) {
+            healed.refresh_token_scan =
+                GenericTokenScan::register(node_api, &token_ids.refresh_nft_token_id)?;
+        }
+        if is_lost(UPDATE_TOKEN_SCAN) {
+            healed.update_token_scan =
+                GenericTokenScan::register(node_api, &token_ids.update_nft_token_id)?;
+        }
+        if let (true, Some(buyback_token_id)) =
+            (is_lost(BUYBACK_TOKEN_SCAN), &pool_config.buyback_token_id)
+        {
+            healed.buyback_token_scan =
+                Some(GenericTokenScan::register(node_api, buyback_token_id)?);
+        }
+        healed.save_to_json_file(scans_file_path)?;
+        node_api.rescan_from_height(ctx.oracle_config.scan_start_height)?;
+        Ok(Some(healed))
+    }
+
+    /// Loads the scan IDs of the pool from `scans_file_path`, registering the missing scans and
+    /// the scans lost by the node
+    #[doc(hidden)]
     pub fn ensure_node_registered_scans(
-        node_api: &NodeApi,
-        pool_config: &PoolConfig,
+        ctx: &OracleContext,
+        scans_file_path: &Path,
     ) -> std::result::Result<Self, anyhow::Error> {
-        let path = get_scans_file_path();
-        log::info!("Loading scan IDs from {}", path.display());
-        let registry = if let Ok(json_str) = std::fs::read_to_string(path) {
+        let node_api = ctx.node_api.as_ref();
+        let pool_config = ctx.pool_config.as_ref();
+        log::info!("Loading scan IDs from {}", scans_file_path.display());
+        let registry = if let Ok(json_str) = std::fs::read_to_string(scans_file_path) {
             let loaded_registry = Self::load_from_json_str(&json_str)?;
-            if let Some(pool_config_buyback_token_id) = pool_config.buyback_token_id.clone() {
+            let registry = if let Some(pool_config_buyback_token_id) =
+                pool_config.buyback_token_id.clone()
+            {
                 log::info!("Buyback token is found in pool config, checking if scan is registered");
                 if loaded_registry.buyback_token_scan.is_some() {
                     log::info!("Buyback token scan is already registered");
@@ -117,12 +240,12 @@ impl NodeScanRegistry {
                 } else {
                     let buyback_token_scan =
                         GenericTokenScan::register(node_api, &pool_config_buyback_token_id)?;
-                    node_api.rescan_from_height(ORACLE_CONFIG.scan_start_height)?;
+                    node_api.rescan_from_height(ctx.oracle_config.scan_start_height)?;
                     let new_registry = Self {
                         buyback_token_scan: Some(buyback_token_scan),
                         ..loaded_registry
                     };
-                    new_registry.save_to_json_file(&get_scans_file_path())?;
+                    new_registry.save_to_json_file(scans_file_path)?;
                     new_registry
                 }
             } else {
@@ -135,15 +258,16 @@ impl NodeScanRegistry {
                         buyback_token_scan: None,
                         ..loaded_registry
                     };
-                    new_registry.save_to_json_file(&get_scans_file_path())?;
+                    new_registry.save_to_json_file(scans_file_path)?;
                     new_registry
                 } else {
                     loaded_registry
                 }
-            }
+            };
+            registry.heal(ctx, scans_file_path)?.unwrap_or(registry)
         } else {
             log::info!("Scans not found");
-            Self::register_and_save_scans_inner(node_api, pool_config)?
+            Self::register_and_save_scans_inner(ctx, scans_file_path)?
         };
         wait_for_node_rescan(node_api)?;
         Ok(registry)
@@ -162,6 +286,103 @@ impl NodeScanRegistry {
     }
 }
 
+/// Scan IDs of a pool shared by the boxes sources of the running oracle, replaced when lost
+/// scans are registered again
+#[derive(Debug)]
+pub struct SharedScanRegistry {
+    current: RwLock<NodeScanRegistry>,
+}
+
+impl SharedScanRegistry {
+    pub fn new(registry: NodeScanRegistry) -> Self {
+        Self {
+            current: RwLock::new(registry),
+        }
+    }
+
+    pub fn load(&self) -> NodeScanRegistry {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn store(&self, registry: NodeScanRegistry) {
+        *self.current.write().unwrap() = registry;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanProblem {
+    /// No scan with this ID on the node
+    Missing { scan: &'static str, scan_id: ScanId },
+    /// The node's scan with this ID does not track the token
+    WrongToken {
+        scan: &'static str,
+        scan_id: ScanId,
+        token_id: TokenId,
+    },
+}
+
+impl ScanProblem {
+    pub fn scan(&self) -> &'static str {
+        match self {
+            ScanProblem::Missing { scan, .. } | ScanProblem::WrongToken { scan, .. } => scan,
+        }
+    }
+}
+
+impl std::fmt::Display for ScanProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanProblem::Missing { scan, scan_id } => {
+                write!(f, "{scan} scan {scan_id} is not registered on the node")
+            }
+            ScanProblem::WrongToken {
+                scan,
+                scan_id,
+                token_id,
+            } => write!(
+                f,
+                "{scan} scan {scan_id} does not track token {}",
+                String::from(*token_id)
+            ),
+        }
+    }
+}
+
+/// Scans of `expected` (see [`NodeScanRegistry::token_scans`]) that are missing in `node_scans`
+/// (`/scan/listAll`) or whose tracking rule is not on the expected token
+pub fn scan_problems(
+    expected: &[(&'static str, ScanId, TokenId)],
+    node_scans: &[NodeScan],
+) -> Vec<ScanProblem> {
+    expected
+        .iter()
+        .filter_map(|(scan, scan_id, token_id)| {
+            match node_scans
+                .iter()
+                .find(|node_scan| ScanId::from(node_scan.scan_id) == *scan_id)
+            {
+                None => Some(ScanProblem::Missing {
+                    scan,
+                    scan_id: *scan_id,
+                }),
+                Some(node_scan)
+                    if !node_scan
+                        .tracking_rule
+                        .to_string()
+                        .contains(&String::from(*token_id)) =>
+                {
+                    Some(ScanProblem::WrongToken {
+                        scan,
+                        scan_id: *scan_id,
+                        token_id: *token_id,
+                    })
+                }
+                Some(_) => None,
+            }
+        })
+        .collect()
+}
+
 pub fn wait_for_node_rescan(node_api: &NodeApi) -> Result<(), NodeApiError> {
     let wallet_height = node_api.node.wallet_status()?.height;
     let block_height = node_api.node.current_block_height()?;
@@ -197,6 +418,7 @@ pub enum NodeScanRegistryError {
 mod tests {
     use super::*;
     use crate::scans::NodeScanId;
+    use ergo_lib::ergo_chain_types::Digest32;
     use ergo_node_interface::ScanId;
     use expect_test::expect;
     use pretty_assertions::assert_eq;
@@ -205,6 +427,47 @@ mod tests {
         expected_json.assert_eq(json_str);
     }
 
+    fn node_scan(scan_id: u64, token_id: &TokenId) -> NodeScan {
+        NodeScan {
+            scan_id,
+            scan_name: "token scan".to_string(),
+            tracking_rule: serde_json::json!({
+                "predicate": "and",
+                "args": [{"predicate": "containsAsset", "assetId": String::from(*token_id)}]
+            }),
+        }
+    }
+
+    #[test]
+    fn lost_scans_are_found() {
+        let oracle_token = TokenId::from(Digest32::from([1u8; 32]));
+        let pool_nft = TokenId::from(Digest32::from([2u8; 32]));
+        let expected = vec![
+            (ORACLE_TOKEN_SCAN, ScanId::from(10), oracle_token),
+            (POOL_TOKEN_SCAN, ScanId::from(11), pool_nft),
+        ];
+        assert!(scan_problems(
+            &expected,
+            &[node_scan(10, &oracle_token), node_scan(11, &pool_nft)]
+        )
+        .is_empty());
+        // node database wiped, scan 10 now belongs to another app
+        assert_eq!(
+            scan_problems(&expected, &[node_scan(10, &pool_nft)]),
+            vec![
+                ScanProblem::WrongToken {
+                    scan: ORACLE_TOKEN_SCAN,
+                    scan_id: ScanId::from(10),
+                    token_id: oracle_token,
+                },
+                ScanProblem::Missing {
+                    scan: POOL_TOKEN_SCAN,
+                    scan_id: ScanId::from(11),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn parse_legacy_json() {
         let json_str = r#"{ 
@@ -0,0 +1,215 @@
+//! Pool state read once per main loop iteration and shared with the REST API and the metrics
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Instant;
+
+use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use serde_json::json;
+
+use crate::box_kind::OracleBox;
+use crate::box_kind::PoolBox;
+use crate::context::OracleContext;
+use crate::fee_runway::check_fee_runway;
+use crate::fee_runway::FeeRunway;
+use crate::monitor::check_oracle_health;
+use crate::monitor::check_pool_health;
+use crate::monitor::HealthConfig;
+use crate::monitor::OracleHealth;
+use crate::monitor::PoolHealth;
+use crate::node_sync::NodeSyncStatus;
+use crate::oracle_state::LiveEpochState;
+use crate::oracle_state::OraclePool;
+use crate::oracle_stats::PostedDatapoint;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::oracle_types::Rate;
+use crate::pool_commands::preview_refresh;
+use crate::pool_commands::refresh::RefreshPreview;
+use crate::state::PoolState;
+use crate::wallet_monitor::WalletState;
+
+#[derive(Debug)]
+pub struct PoolSnapshot {
+    /// Blockchain height at which the snapshot was taken
+    pub height: BlockHeight,
+    pub taken_at: Instant,
+    pub pool_box_id: BoxId,
+    pub pool_box_height: BlockHeight,
+    pub pool_box_epoch_counter: EpochCounter,
+    pub pool_box_rate: Rate,
+    pub pool_box_reward_tokens: u64,
+    pub pool_state: PoolState,
+    /// Our oracle box (if any)
+    pub local_box_id: Option<BoxId>,
+    /// Reward tokens in our oracle box (if any)
+    pub local_reward_tokens: Option<u64>,
+    /// Reward tokens in the buyback box (if the pool has one)
+    pub buyback_reward_tokens: Option<u64>,
+    /// Posted (not yet collected) datapoints of all oracles
+    pub posted_datapoints: Vec<PostedDatapoint>,
+    pub pool_health: PoolHealth,
+    /// `None` when observing the pool without being one of its oracles
+    pub oracle_health: Option<OracleHealth>,
+    /// Not computed when the wallet is not ready
+    pub fee_runway: Option<FeeRunway>,
+    /// Next refresh outcome from the posted datapoints read with the pool box, `None` when
+    /// observing the pool or if it could not be computed
+    pub refresh_preview: Option<RefreshPreview>,
+}
+
+impl PoolSnapshot {
+    /// Reads the pool boxes and runs the health checks, at the height of the pool boxes snapshot
+    /// if `oracle_pool` is one (see [`OraclePool::snapshot`])
+    pub fn take(
+        ctx: &OracleContext,
+        oracle_pool: Arc<OraclePool>,
+        node_sync: &NodeSyncStatus,
+        wallet: &WalletState,
+        secs_since_last_action: Option<u64>,
+    ) -> Result<PoolSnapshot, anyhow::Error> {
+        let height = match oracle_pool.snapshot_block() {
+            Some(block) => block.height,
+            None => BlockHeight(ctx.node_api.node.current_block_height()? as u32),
+        };
+        let mut snapshot = Self::observe(
+            oracle_pool.clone(),
+            height,
+            ctx.network_prefix,
+            &ctx.oracle_config.health,
+        )?;
+        // the oracle checks are reported without failing the snapshot, the pool state is still
+        // needed to act
+        snapshot.oracle_health = check_oracle_health(
+            oracle_pool.clone(),
+            snapshot.pool_box_height,
+            height,
+            snapshot.pool_health.details.epoch_length,
+            node_sync.clone(),
+            wallet.clone(),
+            secs_since_last_action,
+            &ctx.oracle_config.health,
+        )
+        .map_err(|e| log::error!("Failed to check the oracle health: {:?}", e))
+        .ok();
+        snapshot.fee_runway = if wallet.is_ready() {
+            check_fee_runway(ctx, &oracle_pool)
+                .map_err(|e| log::error!("Failed to check the fee runway: {:?}", e))
+                .ok()
+        } else {
+            None
+        };
+        snapshot.refresh_preview = preview_refresh(ctx, &oracle_pool, height)
+            .map_err(|e| log::error!("Failed to preview the refresh: {:?}", e))
+            .ok();
+        Ok(snapshot)
+    }
+
+    /// Reads the pool boxes and runs the pool health checks, without the checks of our oracle
+    /// (observer)
+    pub fn observe(
+        oracle_pool: Arc<OraclePool>,
+        height: BlockHeight,
+        network_prefix: NetworkPrefix,
+        health_config: &HealthConfig,
+    ) -> Result<PoolSnapshot, anyhow::Error> {
+        let pool_box = oracle_pool.get_pool_box_source().get_pool_box()?;
+        let pool_box_height = BlockHeight(pool_box.get_box().creation_height);
+        let pool_box_reward_tokens = *pool_box.reward_token().amount.as_u64();
+        let local_oracle_box = oracle_pool
+            .get_local_datapoint_box_source()
+            .get_local_oracle_datapoint_box()?;
+        let pool_state = PoolState::LiveEpoch(LiveEpochState {
+            pool_box_epoch_id: pool_box.epoch_counter(),
+            latest_pool_datapoint: pool_box.rate(),
+            latest_pool_box_height: pool_box_height,
+            local_datapoint_box_state: local_oracle_box.as_ref().map(|b| b.into()),
+        });
+        let buyback_reward_tokens = oracle_pool
+            .get_buyback_box_source()
+            .map(|s| s.get_buyback_box())
+            .transpose()?
+            .flatten()
+            .map(|b| b.reward_token().map(|t| *t.amount.as_u64()).unwrap_or(0));
+        let posted_datapoints = oracle_pool
+            .get_posted_datapoint_boxes_source()
+            .get_posted_datapoint_boxes()?
+            .iter()
+            .map(|b| PostedDatapoint {
+                address: NetworkAddress::new(network_prefix, &Address::P2Pk(b.public_key().into()))
+                    .to_base58(),
+                epoch_counter: b.epoch_counter(),
+                rate: b.rate(),
+            })
+            .collect();
+        let pool_health = check_pool_health(
+            height,
+            pool_box_height,
+            pool_box.rate(),
+            pool_box_reward_tokens,
+            oracle_pool.clone(),
+            network_prefix,
+            health_config,
+        )?;
+        Ok(PoolSnapshot {
+            height,
+            taken_at: Instant::now(),
+            pool_box_id: pool_box.get_box().box_id(),
+            pool_box_height,
+            pool_box_epoch_counter: pool_box.epoch_counter(),
+            pool_box_rate: pool_box.rate(),
+            pool_box_reward_tokens,
+            pool_state,
+            local_box_id: local_oracle_box.as_ref().map(|b| b.get_box().box_id()),
+            local_reward_tokens: local_oracle_box.map(|b| *b.reward_token().amount.as_u64()),
+            buyback_reward_tokens,
+            posted_datapoints,
+            pool_health,
+            oracle_health: None,
+            fee_runway: None,
+            refresh_preview: None,
+        })
+    }
+
+    pub fn age_secs(&self) -> u64 {
+        self.taken_at.elapsed().as_secs()
+    }
+
+    /// Snapshot info attached to the REST API responses
+    pub fn info(&self) -> serde_json::Value {
+        json!({
+            "height": self.height,
+            "age_secs": self.age_secs(),
+        })
+    }
+}
+
+/// Latest snapshot, replaced as a whole by the main loop so the readers never see a partial update
+#[derive(Debug, Default)]
+pub struct SnapshotCache {
+    latest: RwLock<Option<Arc<PoolSnapshot>>>,
+}
+
+impl SnapshotCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn store(&self, snapshot: PoolSnapshot) -> Arc<PoolSnapshot> {
+        let snapshot = Arc::new(snapshot);
+        *self.latest.write().unwrap() = Some(snapshot.clone());
+        snapshot
+    }
+
+    /// `None` until the main loop has taken the first snapshot
+    pub fn load(&self) -> Option<Arc<PoolSnapshot>> {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// Drops the latest snapshot (chain reorganization), until the main loop takes a new one
+    pub fn clear(&self) {
+        *self.latest.write().unwrap() = None;
+    }
+}
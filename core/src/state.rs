@@ -5,10 +5,6 @@ use crate::oracle_types::BlockHeight;
 use crate::oracle_types::EpochLength;
 use crate::pool_commands::PoolCommand;
 
-pub struct EpochState {
-    epoch_start_height: u64,
-}
-
 /// Enum for the state that the oracle pool is currently in
 #[derive(Debug, Clone)]
 pub enum PoolState {
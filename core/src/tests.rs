@@ -1 +1,2 @@
 mod bootstrap_and_run;
+pub(crate) mod mock_explorer;
@@ -10,6 +10,7 @@ use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInp
 use ergo_lib::ergotree_ir::chain::address::Address;
 use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
 use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::wallet::tx_builder::SUGGESTED_TX_FEE;
 use ergo_lib::wallet::Wallet;
 use sigma_test_util::force_any_val;
 
@@ -18,7 +19,6 @@ use crate::cli_commands::bootstrap::BootstrapConfig;
 use crate::cli_commands::bootstrap::BootstrapInput;
 use crate::node_interface;
 use crate::node_interface::SubmitTransaction;
-use crate::oracle_config::BASE_FEE;
 use crate::oracle_types::BlockHeight;
 use crate::pool_commands::test_utils::init_log_tests;
 use crate::pool_commands::test_utils::LocalTxSigner;
@@ -58,8 +58,8 @@ fn bootstrap(wallet: &Wallet, net_address: &NetworkAddress, chain: &mut ChainSim
         },
         tx_signer: &mut LocalTxSigner { ctx: &ctx, wallet },
         submit_tx: &mut submit_tx_mock,
-        tx_fee: *BASE_FEE,
-        erg_value_per_box: *BASE_FEE,
+        tx_fee: SUGGESTED_TX_FEE(),
+        erg_value_per_box: SUGGESTED_TX_FEE(),
         change_address: net_address.address(),
         height,
     })
@@ -0,0 +1,56 @@
+//! Local HTTP server answering explorer API requests with canned JSON responses
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+
+use reqwest::Url;
+
+pub(crate) struct MockExplorer {
+    pub url: Url,
+}
+
+impl MockExplorer {
+    /// Serves `routes` (request path without the query string, JSON response body) in a
+    /// background thread, other paths get a 404
+    pub fn start(routes: Vec<(String, String)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond(stream, &routes);
+            }
+        });
+        Self { url }
+    }
+}
+
+fn respond(mut stream: TcpStream, routes: &[(String, String)]) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // skip the headers, the explorer API requests have no body
+    let mut header = String::new();
+    while reader.read_line(&mut header).is_ok() && header != "\r\n" && !header.is_empty() {
+        header.clear();
+    }
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    let response = match routes.iter().find(|(route, _)| route == path) {
+        Some((_, body)) => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        ),
+        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            .to_string(),
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
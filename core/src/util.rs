@@ -1,3 +1,6 @@
+use std::path::Path;
+use std::path::PathBuf;
+
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
 use ergo_lib::ergotree_ir::chain::token::TokenId;
 use ergo_lib::wallet::box_selector::ErgoBoxAssets;
@@ -13,3 +16,13 @@ pub fn get_token_count(b: ErgoBox, token_id: TokenId) -> u64 {
     }
     count
 }
+
+/// Writes the file through a temporary file renamed over it, so that a crash never leaves a
+/// partially written file behind
+pub fn write_file_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)
+}
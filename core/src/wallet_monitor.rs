@@ -0,0 +1,160 @@
+//! Keeps the node wallet unlocked while the oracle is running and tracks its state
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+
+const MIN_UNLOCK_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_UNLOCK_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletState {
+    pub unlocked: bool,
+    pub rescanning: bool,
+    pub balance_nano_erg: Option<u64>,
+    pub reason: Option<String>,
+}
+
+impl WalletState {
+    /// Query the wallet state from the node without trying to unlock it. The wallet is considered
+    /// rescanning if its height lags the node's height by more than `max_lag_blocks`.
+    pub fn fetch(node_api: &NodeApi, max_lag_blocks: u32) -> Result<WalletState, NodeApiError> {
+        let wallet_status = node_api.node.wallet_status()?;
+        let block_height = node_api.node.current_block_height()?;
+        let unlocked = wallet_status.unlocked;
+        let rescanning = is_rescanning(wallet_status.height, block_height, max_lag_blocks);
+        let balance_nano_erg = if unlocked {
+            Some(node_api.node.wallet_nano_ergs_balance()?)
+        } else {
+            None
+        };
+        let reason = if !unlocked {
+            Some("wallet is locked".to_string())
+        } else if rescanning {
+            Some(format!(
+                "wallet is rescanning ({}/{} blocks)",
+                wallet_status.height, block_height
+            ))
+        } else {
+            None
+        };
+        Ok(WalletState {
+            unlocked,
+            rescanning,
+            balance_nano_erg,
+            reason,
+        })
+    }
+
+    /// Whether the wallet can be used to build and sign transactions
+    pub fn is_ready(&self) -> bool {
+        self.unlocked && !self.rescanning
+    }
+}
+
+fn is_rescanning(wallet_height: u64, block_height: u64, max_lag_blocks: u32) -> bool {
+    block_height.saturating_sub(wallet_height) > max_lag_blocks as u64
+}
+
+/// Re-unlocks the wallet (if the password is set) with an exponential backoff between failed
+/// attempts
+#[derive(Debug, Default)]
+pub struct WalletMonitor {
+    failed_unlock_attempts: u32,
+    next_unlock_attempt: Option<Instant>,
+    /// Whether the missing wallet password was already reported
+    missing_password_logged: bool,
+}
+
+impl WalletMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ensure_unlocked(
+        &mut self,
+        node_api: &NodeApi,
+        max_lag_blocks: u32,
+    ) -> Result<WalletState, NodeApiError> {
+        let state = WalletState::fetch(node_api, max_lag_blocks)?;
+        if state.unlocked {
+            self.failed_unlock_attempts = 0;
+            self.next_unlock_attempt = None;
+            return Ok(state);
+        }
+        let wallet_pass = match &node_api.wallet_pass {
+            Some(wallet_pass) => wallet_pass,
+            None => {
+                if self.missing_password_logged {
+                    log::debug!("Wallet is locked and ORACLE_NODE_WALLET_PASSWORD is not set");
+                } else {
+                    log::error!("Wallet is locked and ORACLE_NODE_WALLET_PASSWORD is not set");
+                    self.missing_password_logged = true;
+                }
+                return Ok(state);
+            }
+        };
+        if let Some(next_attempt) = self.next_unlock_attempt {
+            if Instant::now() < next_attempt {
+                log::debug!("Wallet is locked, waiting before the next unlock attempt");
+                return Ok(state);
+            }
+        }
+        log::info!("Wallet is locked, trying to unlock it");
+        match node_api.wallet_unlock(wallet_pass) {
+            Ok(_) => {
+                log::info!("Wallet unlocked");
+                self.failed_unlock_attempts = 0;
+                self.next_unlock_attempt = None;
+                WalletState::fetch(node_api, max_lag_blocks)
+            }
+            Err(e) => {
+                self.failed_unlock_attempts += 1;
+                let backoff = unlock_backoff(self.failed_unlock_attempts);
+                log::error!(
+                    "Failed to unlock wallet (attempt {}), retrying in {}s. error: {:?}",
+                    self.failed_unlock_attempts,
+                    backoff.as_secs(),
+                    e
+                );
+                self.next_unlock_attempt = Some(Instant::now() + backoff);
+                Ok(state)
+            }
+        }
+    }
+}
+
+fn unlock_backoff(failed_attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failed_attempts.saturating_sub(1));
+    MIN_UNLOCK_BACKOFF
+        .checked_mul(factor)
+        .unwrap_or(MAX_UNLOCK_BACKOFF)
+        .min(MAX_UNLOCK_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlock_backoff_grows_and_caps() {
+        assert_eq!(unlock_backoff(1), Duration::from_secs(30));
+        assert_eq!(unlock_backoff(2), Duration::from_secs(60));
+        assert_eq!(unlock_backoff(3), Duration::from_secs(120));
+        assert_eq!(unlock_backoff(10), MAX_UNLOCK_BACKOFF);
+        assert_eq!(unlock_backoff(100), MAX_UNLOCK_BACKOFF);
+    }
+
+    #[test]
+    fn wallet_lagging_by_a_block_is_not_rescanning() {
+        assert!(!is_rescanning(100, 100, 1));
+        assert!(!is_rescanning(99, 100, 1));
+        assert!(is_rescanning(98, 100, 1));
+        assert!(is_rescanning(99, 100, 0));
+        // wallet ahead of the node while it applies a block
+        assert!(!is_rescanning(101, 100, 1));
+    }
+}
@@ -12,6 +12,12 @@ use crate::Block;
 /// Ergo blockchain(UTXO) simulation
 pub struct ChainSim {
     blocks: Vec<Block>,
+    /// ID of each block in `blocks`
+    block_ids: Vec<String>,
+    /// Number of blocks ever added, to give every block (re-added after a rollback too) a new ID
+    blocks_added: u64,
+    /// Boxes created by `generate_unspent_box`
+    generated_boxes: Vec<ErgoBox>,
     all_boxes: Vec<ErgoBox>,
     unspent_boxes: Vec<ErgoBox>,
 
@@ -69,6 +75,9 @@ impl ChainSim {
     pub fn new() -> ChainSim {
         ChainSim {
             blocks: Vec::new(),
+            block_ids: Vec::new(),
+            blocks_added: 0,
+            generated_boxes: Vec::new(),
             all_boxes: Vec::new(),
             unspent_boxes: Vec::new(),
             height: 0,
@@ -82,9 +91,38 @@ impl ChainSim {
             self.update_utxo(tx.clone());
         });
         self.blocks.push(block);
+        self.blocks_added += 1;
+        self.block_ids.push(format!("{:064x}", self.blocks_added));
         self.height += 1;
     }
 
+    /// Removes the last `blocks` blocks (chain reorganization) and reverts their txs
+    pub fn rollback(&mut self, blocks: u32) {
+        let height = self.height.saturating_sub(blocks) as usize;
+        self.blocks.truncate(height);
+        self.block_ids.truncate(height);
+        self.height = height as u32;
+        self.unspent_boxes = self.generated_boxes.clone();
+        self.all_boxes = self.generated_boxes.clone();
+        let txs: Vec<Transaction> = self
+            .blocks
+            .iter()
+            .flat_map(|block| block.txs.clone())
+            .collect();
+        txs.into_iter().for_each(|tx| self.update_utxo(tx));
+    }
+
+    /// Height and ID of the last `count` blocks, oldest first
+    pub fn last_blocks(&self, count: usize) -> Vec<(u32, String)> {
+        let first = self.block_ids.len().saturating_sub(count);
+        self.block_ids
+            .iter()
+            .enumerate()
+            .skip(first)
+            .map(|(i, id)| (i as u32 + 1, id.clone()))
+            .collect()
+    }
+
     /// Generates an unspent box guarded by a given ErgoTree holding a given assests
     pub fn generate_unspent_box(
         &mut self,
@@ -103,7 +141,8 @@ impl ChainSim {
         )
         .unwrap();
         self.unspent_boxes.push(b.clone());
-        self.all_boxes.push(b);
+        self.all_boxes.push(b.clone());
+        self.generated_boxes.push(b);
     }
 
     /// Returns unspent boxes guarder by the given ErgoTree